@@ -34,6 +34,7 @@ in
             just
             bacon
             oranda
+            taskopen
             perSystem.cargo-nextest-xdg.default
           ]
           # Include the extra packages we use to build our crate
@@ -0,0 +1,29 @@
+# Von taskhelper selbst ausgegebene Meldungen. Die Ausgabe von taskwarrior wird nicht übersetzt
+
+## Hinweise zum eingefügten Projekt
+injected = + { $scope } eingefügt { $placement ->
+    [first-filter] als erster Filter
+    [after-subcommand] nach dem Unterbefehl
+    [last-modifier] als letzter Modifikator
+   *[context] als temporärer Kontext '{ $context }'
+}
+
+## Zusammengeführte Ansichten
+column-source = QUELLE
+column-id = ID
+column-project = PROJEKT
+column-description = BESCHREIBUNG
+
+label-source = Quelle
+label-id = ID
+label-project = Projekt
+label-description = Beschreibung
+
+## Synchronisieren
+sync-retry = Synchronisieren fehlgeschlagen (Versuch { $attempt } von { $attempts }), neuer Versuch in { $delay }s
+sync-failed = Synchronisieren nach { $attempts } Versuchen fehlgeschlagen, Abbruch
+sync-auto-failed = Automatisches Synchronisieren fehlgeschlagen, Änderungen sind nur lokal gespeichert
+
+## Version
+version-compatible = kompatibel
+version-incompatible = inkompatibel
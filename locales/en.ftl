@@ -0,0 +1,29 @@
+# Messages printed by taskhelper itself. Taskwarrior's own output is not translated
+
+## Project injection notes
+injected = + injected { $scope } { $placement ->
+    [first-filter] as first filter
+    [after-subcommand] after the subcommand
+    [last-modifier] as last modifier
+   *[context] as temporary context '{ $context }'
+}
+
+## Merged views
+column-source = SOURCE
+column-id = ID
+column-project = PROJECT
+column-description = DESCRIPTION
+
+label-source = Source
+label-id = ID
+label-project = Project
+label-description = Description
+
+## Synchronize
+sync-retry = Synchronize failed (attempt { $attempt } of { $attempts }), retrying in { $delay }s
+sync-failed = Synchronize failed after { $attempts } attempts, giving up
+sync-auto-failed = Automatic synchronize failed, changes are only stored locally
+
+## Version
+version-compatible = compatible
+version-incompatible = incompatible
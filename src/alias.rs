@@ -0,0 +1,77 @@
+use crate::args::{options_with_values, Cli};
+use clap::CommandFactory;
+use log::debug;
+use std::collections::BTreeMap;
+
+/// Replace the first word of `args` naming one of `aliases` with the words it's defined as.
+/// `args` starts with the program name. Subcommands can't be redefined, and option values and
+/// anything after a `--` are left alone
+pub fn expand(args: Vec<String>, aliases: &BTreeMap<String, String>) -> Vec<String> {
+    if aliases.is_empty() {
+        return args;
+    }
+    let cli = Cli::command();
+    let options = options_with_values();
+    let mut skip_value = false;
+    let position = args.iter().skip(1).position(|arg| {
+        if std::mem::take(&mut skip_value) {
+            return false;
+        }
+        if arg == "--" || cli.find_subcommand(arg).is_some() {
+            return true;
+        }
+        skip_value = options.contains(arg);
+        aliases.contains_key(arg)
+    });
+    let Some(index) = position.map(|p| p + 1) else {
+        return args;
+    };
+    if cli.find_subcommand(&args[index]).is_some() {
+        return args;
+    }
+    let Some(definition) = aliases.get(&args[index]) else {
+        return args;
+    };
+    debug!("Expanding alias '{}' to '{}'", args[index], definition);
+    let mut expanded = args[..index].to_vec();
+    expanded.extend(definition.split_whitespace().map(String::from));
+    expanded.extend_from_slice(&args[index + 1..]);
+    expanded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use s_string::s;
+
+    fn argv(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_expand() {
+        let aliases = BTreeMap::from([
+            (s!("bug"), s!("add +bug priority:H")),
+            (s!("list"), s!("next")),
+        ]);
+        assert_eq!(
+            argv(&[
+                "taskhelper",
+                "--verbose",
+                "add",
+                "+bug",
+                "priority:H",
+                "crash"
+            ]),
+            expand(argv(&["taskhelper", "--verbose", "bug", "crash"]), &aliases)
+        );
+        // Option values and subcommands aren't aliases
+        let args = argv(&["taskhelper", "--project", "bug", "list"]);
+        assert_eq!(args, expand(args.clone(), &aliases));
+        let args = argv(&["taskhelper", "list"]);
+        assert_eq!(args, expand(args.clone(), &aliases));
+        let args = argv(&["taskhelper", "add", "bug"]);
+        assert_eq!(args, expand(args.clone(), &aliases));
+    }
+}
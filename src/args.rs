@@ -2,14 +2,9 @@ use crate::task_args::burndown::Burndown;
 use crate::task_args::filter::Filters;
 use crate::task_args::history::History;
 use crate::task_args::modifier::Modifier;
-use crate::task_args::project::Project;
-use clap::builder::{IntoResettable, Resettable};
-use clap::Args;
-use clap::{Parser, Subcommand};
-use std::env;
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use serde::Deserialize;
 use std::fmt;
-use std::fs::File;
-use std::io::{Read, Write};
 use std::path::PathBuf;
 
 #[derive(Debug, Parser)]
@@ -20,33 +15,208 @@ pub struct Cli {
     #[arg(short = 'V', long)]
     pub version: bool,
 
+    /// Print a note describing what was added to the command to scope it to the project
+    #[arg(short, long)]
+    pub verbose: bool,
+
+    /// Print how the command line was interpreted and the taskwarrior command it translates to,
+    /// without running it
+    #[arg(long)]
+    pub explain: bool,
+
+    /// Answer yes to taskwarrior's confirmations, by passing the rc overrides under `yes` in the
+    /// config file, or `rc.confirmation=off rc.recurrence.confirmation=yes`
+    #[arg(short, long, global = true)]
+    pub yes: bool,
+
+    /// Print the taskwarrior command instead of running it. Subcommands adding tasks themselves
+    /// list the changes they would make
+    #[arg(long)]
+    pub dry_run: bool,
+
+    /// Print native output as plain `label: value` lines, without color, tables or hyperlinks.
+    /// Implied when $TERM is `dumb`
+    #[arg(long)]
+    pub plain: bool,
+
+    /// Run taskwarrior with lowered CPU and IO priority
+    #[arg(long)]
+    pub low_priority: bool,
+
+    /// Print the time and memory taskwarrior used
+    #[arg(long)]
+    pub timings: bool,
+
+    /// Append structured log events to FILE as JSON lines
+    #[arg(long, value_name = "FILE")]
+    pub log_json: Option<PathBuf>,
+
+    /// taskrc to use instead of taskwarrior's default
+    #[arg(long, value_name = "FILE")]
+    pub taskrc: Option<PathBuf>,
+
+    /// Task data directory to use instead of taskwarrior's default
+    #[arg(long, value_name = "DIR")]
+    pub taskdata: Option<PathBuf>,
+
+    /// Scope the command to this project instead of the one found from the working directory
+    #[arg(long, value_name = "NAME", conflicts_with = "no_project")]
+    pub project: Option<String>,
+
+    /// Don't scope the command to the project found from the working directory, running it
+    /// against all tasks
+    #[arg(short = 'g', long, visible_alias = "global", global = true)]
+    pub no_project: bool,
+
+    /// How the project found from the working directory is passed to taskwarrior. Defaults to
+    /// `injection` under [project] in the config file, or filter
+    #[arg(long, value_enum)]
+    pub inject: Option<Injection>,
+
+    /// Synchronize after a command that changes task data. Defaults to `auto` under [sync] in the
+    /// config file
+    #[arg(long, conflicts_with = "no_sync")]
+    pub sync: bool,
+
+    /// Don't synchronize after a command that changes task data
+    #[arg(long)]
+    pub no_sync: bool,
+
+    /// Format reports are printed in. `json` prints the tasks along with the detected project and
+    /// `table` renders them itself, both independent of taskwarrior's formatting
+    #[arg(long, value_enum, value_name = "FORMAT", default_value = "text")]
+    pub output: Output,
+
+    /// Run the report again whenever taskwarrior's data changes, for a live view in a spare
+    /// terminal
+    #[arg(long)]
+    pub watch: bool,
+
     /// Taskwarrior filter
+    #[arg(allow_hyphen_values = true)]
     pub filter: Option<Filters>,
 
     #[command(subcommand)]
     pub command: Option<Commands>,
+
+    /// `rc.<name>=<value>` overrides taken from anywhere on the command line, passed on to
+    /// taskwarrior ahead of everything else
+    #[arg(skip)]
+    pub overrides: Vec<String>,
+}
+
+/// Strategy used to scope a command to the project found from the working directory
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Injection {
+    /// Insert `project:<name>` as a filter or modifier token
+    #[default]
+    Filter,
+    /// Define a temporary context with `rc.context.*` overrides. Plays better with reports whose
+    /// filters taskwarrior composes itself (burndown, history)
+    Context,
+}
+
+/// Format reports are printed in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Output {
+    /// Taskwarrior's own output
+    #[default]
+    Text,
+    /// Tasks as JSON, in a layout that doesn't depend on the taskwarrior version
+    Json,
+    /// Tasks in a table rendered by taskhelper, with the columns under [table] in the config
+    /// file
+    Table,
+}
+
+/// What the sections of a Markdown export are made of
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum GroupBy {
+    /// Pending, then completed tasks
+    #[default]
+    Status,
+    /// A section for each tag, listing the tasks with it
+    Tag,
+}
+
+/// Shell to complete commands in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+/// Hook scripts bundled with taskhelper
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum BundledHook {
+    /// Hands on-add and on-modify events to taskhelper
+    Dispatcher,
+    /// Annotates tasks started in a git repository with the branch and commit
+    GitAnnotate,
+    /// Git post-commit hook annotating the project's active tasks with each commit. Installed
+    /// into the git repository in the current directory
+    CommitAnnotate,
+    /// Git post-commit hook completing the tasks a commit message references with
+    /// `task:<id|uuid>` or `closes #<id>`. Installed into the git repository in the current
+    /// directory, sharing the script with commit-annotate
+    CommitClose,
+}
+
+/// Taskwarrior hook event handled by taskhelper
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum HookHandler {
+    OnAdd,
+    OnModify,
+    GitAnnotate,
 }
 
 #[derive(Debug, Subcommand, Clone)]
+#[allow(clippy::enum_variant_names)]
 pub enum Commands {
     Add {
+        /// Start from the tags and attributes of this template from the config file
+        #[arg(long, value_name = "NAME")]
+        template: Option<String>,
+
         /// Modifiers
+        #[arg(allow_hyphen_values = true)]
         mods: Vec<Modifier>,
     },
-    All,
+    /// Show all tasks, ignoring the project from the working dir. When more than one task database
+    /// is configured, the report is run against each of them and the results merged
+    All {
+        /// Report whose filter selects the tasks
+        report: Option<String>,
+    },
     /// Show all tasks, filtered by the project from the working dir
     Project,
 
     Annotate {
+        /// Append the remote URL and commit of the git repository in the current directory, so
+        /// the work can be traced back to the code
+        #[arg(long)]
+        here: bool,
+
+        /// Also append this location in the repository, implies --here
+        #[arg(long, value_name = "PATH[:LINE]")]
+        at: Option<String>,
+
         /// Modifiers
+        #[arg(allow_hyphen_values = true)]
         mods: Vec<Modifier>,
     },
     Append {
         /// Modifiers
+        #[arg(allow_hyphen_values = true)]
         mods: Vec<Modifier>,
     },
     Blocked,
     Blocking,
+    /// Start the task named after the git branch checked out, adding it to the project first if
+    /// there isn't one
+    Branch,
     Burndown {
         burndown: Burndown,
     },
@@ -67,7 +237,23 @@ pub enum Commands {
         extra_args: Vec<String>,
     },
     Commands,
+    /// Complete the tasks the commit just made references. Run by the bundled commit-close hook
+    #[command(name = "_commit-close", hide = true)]
+    CommitClose,
+    /// Print candidates for the last word of a command line. Used by the completion scripts
+    #[command(name = "_complete", hide = true)]
+    Complete {
+        shell: Shell,
+        /// Command line up to the cursor
+        #[arg(allow_hyphen_values = true)]
+        line: String,
+    },
     Completed,
+    /// Print a script completing taskhelper commands, flags and subcommand arguments, including
+    /// project, tag, context and ID values queried from taskwarrior
+    Completions {
+        shell: Shell,
+    },
     Config {
         /// Extra args to pass to `task config`
         extra_args: Vec<String>,
@@ -79,6 +265,7 @@ pub enum Commands {
     Count,
     Delete {
         /// Modifiers
+        #[arg(allow_hyphen_values = true)]
         mods: Vec<Modifier>,
     },
     Denotate {
@@ -86,12 +273,16 @@ pub enum Commands {
         extra_args: Vec<String>,
     },
     Diagnostics,
+    /// Check taskhelper's setup: config, taskwarrior and where data and logs are kept
+    Doctor,
     Done {
         /// Modifiers
+        #[arg(allow_hyphen_values = true)]
         mods: Vec<Modifier>,
     },
     Duplicate {
         /// Modifiers
+        #[arg(allow_hyphen_values = true)]
         mods: Vec<Modifier>,
     },
     Edit,
@@ -100,12 +291,69 @@ pub enum Commands {
         cmd: Vec<String>,
     },
     Export {
-        /// Path to write report to
-        report: PathBuf,
+        /// Write the JSON to FILE instead of stdout
+        #[arg(long, short, value_name = "FILE")]
+        output: Option<PathBuf>,
+
+        /// Report whose filter selects the exported tasks
+        report: Option<String>,
+    },
+    /// Print the tasks matching the filter in the project as CSV
+    ExportCsv {
+        /// Write the CSV to FILE instead of stdout
+        #[arg(long, short, value_name = "FILE")]
+        output: Option<PathBuf>,
+
+        /// Comma separated attributes or UDAs to make the columns of
+        #[arg(
+            long,
+            value_delimiter = ',',
+            default_value = "uuid,description,project,due,urgency"
+        )]
+        columns: Vec<String>,
+    },
+    /// Print an iCalendar feed with an event for each due and scheduled date of the project's
+    /// pending tasks, for calendars to subscribe to
+    ExportIcal {
+        /// Write the feed to FILE instead of stdout
+        #[arg(long, short, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+    /// Print the project's pending and completed tasks as a Markdown checklist
+    ExportMd {
+        /// Write the Markdown to FILE instead of stdout
+        #[arg(long, short, value_name = "FILE")]
+        output: Option<PathBuf>,
+
+        /// Make a section of each status or each tag
+        #[arg(long, value_enum, default_value_t)]
+        group_by: GroupBy,
+    },
+    /// Print the project's pending and completed tasks as a todo.txt file
+    ExportTodotxt {
+        /// Write the todo.txt file to FILE instead of stdout
+        #[arg(long, short, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+    /// Manage named filters defined in the config file
+    Filters {
+        #[command(subcommand)]
+        command: FiltersCommand,
     },
     Ghistory {
         history: History,
     },
+    /// Mirror the open issues of the GitLab project the origin remote points at into the
+    /// project, completing tasks whose issues were closed. With --dry-run the changes are only
+    /// listed
+    Gitlab,
+    /// Install a git post-commit hook completing the tasks commit messages reference with
+    /// `task:<id|uuid>` or `closes #<id>`, annotated with the commit
+    GitHook {
+        /// Remove the hook instead
+        #[arg(long)]
+        remove: bool,
+    },
     /// Show taskwarrior's help message ('help' shows this program's help message)
     TaskHelp {
         /// Show only usage section
@@ -115,51 +363,161 @@ pub enum Commands {
     History {
         history: History,
     },
+    /// Handle a taskwarrior hook event. Run by the bundled hook scripts
+    #[command(name = "_hook", hide = true)]
+    Hook {
+        handler: HookHandler,
+    },
+    /// Manage taskwarrior hook scripts
+    Hooks {
+        #[command(subcommand)]
+        command: HooksCommand,
+    },
     Ids,
+    /// Print the IDs of pending tasks taskwarrior knows, one per line. Used by the completion scripts
+    #[command(name = "_ids", hide = true)]
+    IdValues,
     Import {
-        /// Files to import
+        /// Don't check the files hold tasks before handing them to taskwarrior
+        #[arg(long)]
+        no_check: bool,
+
+        /// Files to import, `-` for stdin
         files: Vec<PathBuf>,
     },
+    /// Add the tasks of a todo.txt file to the project, updating the ones imported before. With
+    /// --dry-run the tasks are only listed
+    ImportTodotxt {
+        /// todo.txt file, `-` for stdin
+        file: PathBuf,
+    },
+    /// Add a task to the project for each Jira issue a JQL query matches, skipping issues
+    /// imported before. With --dry-run the issues are only listed
+    ImportJira {
+        /// JQL selecting the issues, by default the query of the config or the unresolved issues
+        /// assigned to you
+        jql: Option<String>,
+    },
     Information,
     Info,
     List,
     Log {
         /// Modifiers
+        #[arg(allow_hyphen_values = true)]
         mods: Vec<Modifier>,
     },
     Logo,
     Long,
     Ls,
+    /// Write man pages for taskhelper and its subcommands
+    Mangen {
+        /// Directory to write the man pages to
+        dir: PathBuf,
+    },
     Minimal,
     Modify {
         /// Modifiers
+        #[arg(allow_hyphen_values = true)]
         mods: Vec<Modifier>,
     },
     Newest,
     News,
+    /// Send a desktop notification for each overdue task and task due today in the project, or
+    /// in all projects with --global. Meant to be run from a timer or cron job
+    Notify,
     Next,
     Oldest,
+    /// Open the files and URLs annotated on matching tasks in the project with taskopen
+    Open {
+        /// Extra args to pass to taskopen
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        extra_args: Vec<String>,
+    },
     Overdue,
+    /// Annotate the project's active tasks with the commit just made. Run by the bundled
+    /// post-commit hook
+    #[command(name = "_post-commit", hide = true)]
+    PostCommit,
     Prepend {
         /// Modifiers
+        #[arg(allow_hyphen_values = true)]
         mods: Vec<Modifier>,
     },
     Projects,
+    /// Print the projects taskwarrior knows, one per line. Used by the completion scripts
+    #[command(name = "_projects", hide = true)]
+    ProjectValues,
+    /// Print the detected project and its number of pending tasks, for a shell prompt
+    Prompt,
     Purge,
     Ready,
+    /// List previous taskhelper commands, or run one again from the directory it ran in. Named
+    /// so as not to clash with taskwarrior's history report
+    Recall {
+        /// Only list commands containing this text. With --run, the number of the command to
+        /// run, or text it contains
+        search: Option<String>,
+        /// Run the latest matching command again, like `!!` in a shell
+        #[arg(long, short)]
+        run: bool,
+    },
     Recurring,
     Reports,
+    /// Walk through the project's tasks not reviewed in the past week, asking to modify,
+    /// complete, delete or skip each one. Reviews are recorded in the `reviewed` UDA
+    Review,
+    /// Print the doctor output and latest crash report, to attach to a bug report
+    ReportBug,
+    /// Run a taskwarrior command against a throwaway task database
+    Sandbox {
+        /// Export to import into the sandbox before running the command
+        #[arg(long)]
+        seed: Option<PathBuf>,
+        /// Arguments to pass to taskwarrior
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+    /// Add a task to the project for each TODO and FIXME comment in the git repository, skipping
+    /// comments added before. With --dry-run the comments are only listed
+    Scan,
+    /// Update taskhelper to the latest release, once its minisign signature is verified
+    SelfUpdate {
+        /// Only report whether an update is available
+        #[arg(long)]
+        check: bool,
+    },
+    /// Walk through setting up taskhelper: project detection, the config file, the 'task' link,
+    /// shell integration and hooks
+    Setup,
+    /// Print shell code setting up completions, a shorthand function and a prompt segment.
+    /// Add `eval "$(taskhelper shell-init bash)"` to your rc file
+    ShellInit {
+        shell: Shell,
+        /// Name of the shorthand function
+        #[arg(long, default_value = "t")]
+        alias: String,
+        /// Leave the prompt as it is
+        #[arg(long)]
+        no_prompt: bool,
+    },
+    /// Read commands from a prompt, with history and completion, each scoped to the project
+    /// detected when the shell started
+    Shell,
     Show {
         /// Extra args to pass to `task show`
         extra_args: Vec<String>,
     },
     Start {
         /// Modifiers
+        #[arg(allow_hyphen_values = true)]
         mods: Vec<Modifier>,
     },
+    /// Summarize the project for a standup: tasks done since yesterday, in progress and next
+    Standup,
     Stats,
     Stop {
         /// Modifiers
+        #[arg(allow_hyphen_values = true)]
         mods: Vec<Modifier>,
     },
     Summary,
@@ -168,23 +526,163 @@ pub enum Commands {
         extra_args: Vec<String>,
     },
     Tags,
+    /// Print the tags taskwarrior knows, one per line. Used by the completion scripts
+    #[command(name = "_tags", hide = true)]
+    TagValues,
     Timesheet,
+    /// Pending tasks due or scheduled today or earlier, in the next report
+    Today,
+    /// Manage taskwarrior installs downloaded by taskhelper
+    Toolchain {
+        #[command(subcommand)]
+        command: ToolchainCommand,
+    },
+    /// Start tracking time in timewarrior on the matching task, tagged with its description,
+    /// project and tags
+    Track,
+    /// Browse the project's pending tasks, with keys to complete, start, stop, annotate and edit
+    /// them
+    Tui,
     Udas,
     Unblocked,
     Undo,
+    /// Stop tracking time in timewarrior
+    Untrack,
     Uuids,
     Waiting,
+    /// Pending tasks due or scheduled this week or earlier, in the next report
+    Week,
     Rm {
         /// Modifiers
+        #[arg(allow_hyphen_values = true)]
         mods: Vec<Modifier>,
     },
 }
 
+#[derive(Debug, Subcommand, Clone)]
+pub enum FiltersCommand {
+    /// List named filters and what they expand to
+    List,
+}
+
+#[derive(Debug, Subcommand, Clone)]
+pub enum HooksCommand {
+    /// List installed hook scripts, noting any taskwarrior won't run
+    List,
+    /// Install a hook script bundled with taskhelper
+    Install { hook: BundledHook },
+    /// Remove a bundled hook script, or any hook script by file name
+    Remove { hook: String },
+}
+
+#[derive(Debug, Subcommand, Clone)]
+pub enum ToolchainCommand {
+    /// Download a prebuilt taskwarrior and use it instead of the one on the $PATH
+    Install {
+        /// Taskwarrior version, e.g. 3.1.0
+        version: String,
+    },
+    /// List installed taskwarrior versions, marking the one in use
+    List,
+}
+
+impl Commands {
+    /// Name of the subcommand without any variant, e.g. `burndown` for `burndown.weekly`
+    pub fn base_name(&self) -> String {
+        let name = self.to_string();
+        match name.split_once('.') {
+            Some((base, _)) => base.to_string(),
+            None => name,
+        }
+    }
+
+    /// Modifiers given to the subcommand, empty for subcommands that don't take any
+    pub fn modifiers(&self) -> &[Modifier] {
+        match self {
+            Commands::Add { mods, .. }
+            | Commands::Annotate { mods, .. }
+            | Commands::Append { mods }
+            | Commands::Delete { mods }
+            | Commands::Done { mods }
+            | Commands::Duplicate { mods }
+            | Commands::Log { mods }
+            | Commands::Modify { mods }
+            | Commands::Prepend { mods }
+            | Commands::Start { mods }
+            | Commands::Stop { mods }
+            | Commands::Rm { mods } => mods,
+            _ => &[],
+        }
+    }
+
+    /// Whether the subcommand is a taskwarrior report listing tasks, which `task export` can
+    /// select the tasks of
+    pub fn is_report(&self) -> bool {
+        matches!(
+            self,
+            Commands::Blocked
+                | Commands::Blocking
+                | Commands::Completed
+                | Commands::List
+                | Commands::Long
+                | Commands::Ls
+                | Commands::Minimal
+                | Commands::Newest
+                | Commands::Next
+                | Commands::Oldest
+                | Commands::Overdue
+                | Commands::Ready
+                | Commands::Recurring
+                | Commands::Unblocked
+                | Commands::Waiting
+        )
+    }
+
+    /// Whether the subcommand acts on a single task, which can be picked interactively when no
+    /// filter names one
+    pub fn picks_task(&self) -> bool {
+        matches!(
+            self,
+            Commands::Annotate { .. } | Commands::Done { .. } | Commands::Start { .. }
+        )
+    }
+
+    /// Whether the subcommand takes over the terminal, like `edit` does by starting an editor
+    pub fn is_interactive(&self) -> bool {
+        matches!(self, Commands::Edit)
+    }
+
+    /// Whether the subcommand changes task data
+    pub fn is_mutating(&self) -> bool {
+        matches!(
+            self,
+            Commands::Add { .. }
+                | Commands::Annotate { .. }
+                | Commands::Append { .. }
+                | Commands::Delete { .. }
+                | Commands::Denotate { .. }
+                | Commands::Done { .. }
+                | Commands::Duplicate { .. }
+                | Commands::Edit
+                | Commands::Import { .. }
+                | Commands::Log { .. }
+                | Commands::Modify { .. }
+                | Commands::Prepend { .. }
+                | Commands::Purge
+                | Commands::Rm { .. }
+                | Commands::Start { .. }
+                | Commands::Stop { .. }
+                | Commands::Synchronize { .. }
+                | Commands::Undo
+        )
+    }
+}
+
 impl fmt::Display for Commands {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let repr = match self {
             Commands::Add { .. } => "add",
-            Commands::All => "all",
+            Commands::All { .. } => "all",
             Commands::Project => "project",
             Commands::Annotate { .. } => "annotate",
             Commands::Append { .. } => "append",
@@ -200,24 +698,35 @@ impl fmt::Display for Commands {
             Commands::Colors { .. } => "colors",
             Commands::Columns { .. } => "columns",
             Commands::Commands => "commands",
+            Commands::Complete { .. } => "_complete",
             Commands::Completed => "completed",
+            Commands::CommitClose => "_commit-close",
+            Commands::Completions { .. } => "completions",
             Commands::Config { .. } => "config",
             Commands::Context { .. } => "context",
             Commands::Count => "count",
             Commands::Delete { .. } => "delete",
             Commands::Denotate { .. } => "denotate",
             Commands::Diagnostics => "diagnostics",
+            Commands::Doctor => "doctor",
             Commands::Done { .. } => "done",
             Commands::Duplicate { .. } => "duplicate",
             Commands::Edit => "edit",
             Commands::Execute { .. } => "execute",
             Commands::Export { .. } => "export",
+            Commands::ExportCsv { .. } => "export-csv",
+            Commands::ExportIcal { .. } => "export-ical",
+            Commands::ExportMd { .. } => "export-md",
+            Commands::ExportTodotxt { .. } => "export-todotxt",
+            Commands::Filters { .. } => "filters",
             Commands::Ghistory { history } => match history {
                 History::Annual => "ghistory.annual",
                 History::Daily => "ghistory.daily",
                 History::Monthly => "ghistory.monthly",
                 History::Weekly => "ghistory.weekly",
             },
+            Commands::GitHook { .. } => "git-hook",
+            Commands::Gitlab => "gitlab",
             Commands::TaskHelp { .. } => "help",
             Commands::History { history } => match history {
                 History::Annual => "history.annual",
@@ -225,42 +734,195 @@ impl fmt::Display for Commands {
                 History::Monthly => "history.monthly",
                 History::Weekly => "history.weekly",
             },
+            Commands::Hook { .. } => "_hook",
+            Commands::Hooks { .. } => "hooks",
             Commands::Ids => "ids",
+            Commands::IdValues => "_ids",
             Commands::Import { .. } => "import",
+            Commands::ImportJira { .. } => "import-jira",
+            Commands::ImportTodotxt { .. } => "import-todotxt",
             Commands::Information | Commands::Info => "information",
             Commands::List => "list",
             Commands::Log { .. } => "log",
             Commands::Logo => "logo",
             Commands::Long => "long",
             Commands::Ls => "ls",
+            Commands::Mangen { .. } => "mangen",
             Commands::Minimal => "minimal",
             Commands::Modify { .. } => "modify",
             Commands::Newest => "newest",
             Commands::News => "news",
             Commands::Next => "next",
             Commands::Oldest => "oldest",
+            Commands::Open { .. } => "open",
             Commands::Overdue => "overdue",
+            Commands::PostCommit => "_post-commit",
             Commands::Prepend { .. } => "prepend",
             Commands::Projects => "projects",
+            Commands::ProjectValues => "_projects",
+            Commands::Prompt => "prompt",
             Commands::Purge => "purge",
             Commands::Ready => "ready",
+            Commands::Recall { .. } => "recall",
             Commands::Recurring => "recurring",
             Commands::Reports => "reports",
+            Commands::ReportBug => "report-bug",
+            Commands::Sandbox { .. } => "sandbox",
+            Commands::Scan => "scan",
+            Commands::Setup => "setup",
+            Commands::ShellInit { .. } => "shell-init",
+            Commands::SelfUpdate { .. } => "self-update",
             Commands::Show { .. } => "show",
             Commands::Stats => "stats",
             Commands::Stop { .. } => "stop",
             Commands::Summary => "summary",
             Commands::Synchronize { .. } => "synchronize",
             Commands::Tags => "tags",
+            Commands::TagValues => "_tags",
             Commands::Timesheet => "timesheet",
+            Commands::Today => "today",
+            Commands::Toolchain { .. } => "toolchain",
+            Commands::Track => "track",
+            Commands::Branch => "branch",
+            Commands::Shell => "shell",
+            Commands::Review => "review",
+            Commands::Standup => "standup",
+            Commands::Notify => "notify",
+            Commands::Tui => "tui",
             Commands::Udas => "udas",
             Commands::Unblocked => "unblocked",
             Commands::Undo => "undo",
+            Commands::Untrack => "untrack",
             Commands::Uuids => "uuids",
             Commands::Waiting => "waiting",
+            Commands::Week => "week",
             Commands::Start { .. } => "start",
             Commands::Rm { .. } => "rm",
         };
         write!(f, "{}", repr)
     }
 }
+
+/// Options of `Cli` followed by a value, like `--project <NAME>`
+pub fn options_with_values() -> Vec<String> {
+    Cli::command()
+        .get_arguments()
+        .filter(|arg| !arg.is_positional() && arg.get_action().takes_values())
+        .flat_map(|arg| {
+            let long = arg.get_long().map(|long| format!("--{}", long));
+            let short = arg.get_short().map(|short| format!("-{}", short));
+            long.into_iter().chain(short)
+        })
+        .collect()
+}
+
+/// Whether `arg` is one of our options rather than a filter like `-bug`. Short flags may be
+/// grouped, as in `-vg`
+fn is_option(arg: &str, shorts: &[char]) -> bool {
+    match arg.strip_prefix('-') {
+        Some(rest) if rest.starts_with('-') => true,
+        Some(rest) => !rest.is_empty() && rest.chars().all(|c| shorts.contains(&c)),
+        None => false,
+    }
+}
+
+/// Join the words ahead of the subcommand that aren't options into one filter argument, so a
+/// filter can be typed as several words like taskwarrior takes it: `+urgent project:foo start`.
+/// `args` starts with the program name
+pub fn join_leading_filter(args: Vec<String>) -> Vec<String> {
+    let cli = Cli::command();
+    let options = options_with_values();
+    let mut shorts: Vec<char> = cli
+        .get_arguments()
+        .filter_map(|arg| arg.get_short())
+        .collect();
+    shorts.push('h');
+    let mut joined = Vec::new();
+    let mut words = Vec::new();
+    let mut rest = args.into_iter();
+    joined.extend(rest.next());
+    let mut skip_value = false;
+    for arg in rest.by_ref() {
+        if std::mem::take(&mut skip_value) {
+            joined.push(arg);
+        } else if arg == "--" || cli.find_subcommand(&arg).is_some() {
+            if !words.is_empty() {
+                joined.push(words.join(" "));
+                words.clear();
+            }
+            joined.push(arg);
+            break;
+        } else if is_option(&arg, &shorts) {
+            skip_value = options.contains(&arg);
+            joined.push(arg);
+        } else {
+            words.push(arg);
+        }
+    }
+    if !words.is_empty() {
+        joined.push(words.join(" "));
+    }
+    joined.extend(rest);
+    joined
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn argv(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_join_leading_filter() {
+        assert_eq!(
+            argv(&["taskhelper", "-v", "+urgent project:foo", "start", "a", "b"]),
+            join_leading_filter(argv(&[
+                "taskhelper",
+                "+urgent",
+                "-v",
+                "project:foo",
+                "start",
+                "a",
+                "b"
+            ]))
+        );
+        assert_eq!(
+            argv(&["taskhelper", "--project", "web", "-vg", "+bug -home"]),
+            join_leading_filter(argv(&[
+                "taskhelper",
+                "--project",
+                "web",
+                "-vg",
+                "+bug",
+                "-home"
+            ]))
+        );
+        let args = argv(&["taskhelper", "12", "done"]);
+        assert_eq!(args, join_leading_filter(args.clone()));
+    }
+
+    #[test]
+    fn test_modify_remove_tag() {
+        let cli = Cli::parse_from(join_leading_filter(argv(&[
+            "taskhelper",
+            "1",
+            "modify",
+            "-y",
+            "-tag",
+            "+next",
+        ])));
+        let Some(Commands::Modify { mods }) = cli.command else {
+            panic!("expected modify, got {:?}", cli.command);
+        };
+        let removed = Modifier::Tag {
+            name: String::from("tag"),
+            removed: true,
+        };
+        assert_eq!(Some(&removed), mods.first());
+        assert_eq!(2, mods.len());
+        assert!(cli.yes);
+    }
+}
@@ -0,0 +1,98 @@
+use crate::detect::Scope;
+use crate::git;
+use crate::task::{export_tasks, Task};
+use crate::taskwarrior::Taskwarrior;
+use color_eyre::eyre::{bail, eyre};
+use color_eyre::Result;
+use std::env;
+
+/// Run taskwarrior with `rc` followed by `args`, failing with `what` if it does
+fn task_command(task: &Taskwarrior, rc: &[String], args: &[&str], what: &str) -> Result<()> {
+    let mut task_args = rc.to_vec();
+    task_args.push(String::from("rc.verbose=nothing"));
+    task_args.extend(args.iter().map(|arg| arg.to_string()));
+    let output = task.command(&task_args).output()?;
+    if !output.status.success() {
+        bail!(
+            "Unable to {}: {}",
+            what,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// The unfinished task in `scope` named after `branch`
+fn find(
+    task: &Taskwarrior,
+    rc: &[String],
+    scope: Option<&Scope>,
+    branch: &str,
+) -> Result<Option<Task>> {
+    let mut args = rc.to_vec();
+    args.extend(scope.map(Scope::tokens).unwrap_or_default());
+    args.extend([
+        String::from("("),
+        String::from("status:pending"),
+        String::from("or"),
+        String::from("status:waiting"),
+        String::from(")"),
+    ]);
+    Ok(export_tasks(task, &args)?
+        .into_iter()
+        .find(|task| task.description == branch))
+}
+
+/// Start the task named after the git branch checked out, adding it to `scope` first if there
+/// isn't one. The task is annotated with the repository it belongs to. `rc` is prepended to
+/// each taskwarrior command
+pub fn start_branch_task(task: &Taskwarrior, rc: &[String], scope: Option<&Scope>) -> Result<()> {
+    let cwd = env::current_dir()?;
+    let Some(branch) = git::branch(&cwd) else {
+        bail!("Not on a git branch");
+    };
+    let root = git::git(Some(&cwd), &["rev-parse", "--show-toplevel"])
+        .ok_or_else(|| eyre!("Unable to find the root of the git repository"))?;
+
+    let existing = find(task, rc, scope, &branch)?;
+    let branch_task = match existing {
+        Some(branch_task) => branch_task,
+        None => {
+            let mut args = vec!["add"];
+            let tokens = scope.map(Scope::tokens).unwrap_or_default();
+            args.extend(tokens.iter().map(String::as_str));
+            args.extend(["--", branch.as_str()]);
+            task_command(task, rc, &args, "add the branch task")?;
+            println!("Added task '{}'", branch);
+            find(task, rc, scope, &branch)?
+                .ok_or_else(|| eyre!("Unable to find the task added for '{}'", branch))?
+        }
+    };
+
+    let annotation = format!("Repository: {}", root);
+    let annotated = branch_task
+        .annotations
+        .iter()
+        .any(|existing| existing.description == annotation);
+    if !annotated {
+        let args = [
+            branch_task.uuid.as_str(),
+            "annotate",
+            "--",
+            annotation.as_str(),
+        ];
+        task_command(task, rc, &args, "annotate the branch task")?;
+    }
+    if branch_task.start.is_none() {
+        task_command(
+            task,
+            rc,
+            &[branch_task.uuid.as_str(), "start"],
+            "start the branch task",
+        )?;
+        println!("Started task '{}'", branch);
+    } else {
+        println!("Task '{}' is already started", branch);
+    }
+    Ok(())
+}
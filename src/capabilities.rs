@@ -0,0 +1,72 @@
+use crate::args::Commands;
+use crate::parse_version;
+use color_eyre::eyre::bail;
+use color_eyre::Result;
+use semver::{Version, VersionReq};
+
+/// Subcommands only some taskwarrior releases have: the subcommand, the semver range of releases
+/// that have it and the release that added or dropped it
+const CAPABILITIES: &[(&str, &str, &str)] = &[
+    ("news", ">=2.6", "was added in taskwarrior 2.6"),
+    ("purge", ">=2.6", "was added in taskwarrior 2.6"),
+    ("history.daily", ">=2.6", "was added in taskwarrior 2.6"),
+    ("history.weekly", ">=2.6", "was added in taskwarrior 2.6"),
+    ("ghistory.daily", ">=2.6", "was added in taskwarrior 2.6"),
+    ("ghistory.weekly", ">=2.6", "was added in taskwarrior 2.6"),
+];
+
+fn supports(version: &Version, range: &str) -> bool {
+    VersionReq::parse(range).is_ok_and(|range| range.matches(version))
+}
+
+/// Fail if the taskwarrior release `version` can't run `command` the way it was given, rather
+/// than letting taskwarrior misread it. Versions that can't be parsed aren't checked
+pub fn check(version: &str, command: &Commands) -> Result<()> {
+    let Ok(version) = parse_version(version) else {
+        return Ok(());
+    };
+    let name = command.to_string();
+    for (subcommand, range, reason) in CAPABILITIES {
+        if *subcommand == name && !supports(&version, range) {
+            bail!("'{}' {}, found {}", subcommand, reason, version);
+        }
+    }
+    // Taskwarrior 3 syncs with a TaskChampion server, which needs no initial upload
+    if let Commands::Synchronize { extra_args } = command {
+        let initialize = extra_args
+            .first()
+            .is_some_and(|arg| "initialize".starts_with(arg.as_str()) && arg.len() >= 4);
+        if initialize && version.major >= 3 {
+            bail!(
+                "'synchronize initialize' was dropped in taskwarrior 3, found {}. Set \
+                 sync.server.url or sync.local.server_dir in the taskrc instead",
+                version
+            );
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task_args::history::History;
+    use s_string::s;
+
+    #[test]
+    fn test_check() {
+        assert!(check("3.1.0", &Commands::News).is_ok());
+        assert!(check("2.5.3", &Commands::News).is_err());
+        let daily = Commands::History {
+            history: History::Daily,
+        };
+        assert!(check("2.5.3", &daily).is_err());
+        assert!(check("2.6.2", &daily).is_ok());
+        let init = Commands::Synchronize {
+            extra_args: vec![s!("init")],
+        };
+        assert!(check("2.6.2", &init).is_ok());
+        assert!(check("3.1.0", &init).is_err());
+        assert!(check("unknown", &init).is_ok());
+    }
+}
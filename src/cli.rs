@@ -0,0 +1,1876 @@
+use crate::args::{
+    join_leading_filter, BundledHook, Cli, Commands, FiltersCommand, HooksCommand, Injection,
+    Output, ToolchainCommand,
+};
+use crate::branch::start_branch_task;
+use crate::command_hooks::{environment, run_hook, Stage};
+use crate::complete::{complete, print_values, script as completion_script, Source};
+use crate::config::{Config, LinksConfig};
+use crate::detect::{find_scope, isolated_data_dir, isolated_data_root, Scope};
+use crate::explain::{command_line, explain};
+use crate::i18n::tr;
+use crate::lock::lock_data_dir;
+use crate::logging::log_json_path;
+use crate::mangen::generate as generate_man_pages;
+use crate::merged::{databases, merged_view};
+use crate::notifications::notify_due;
+use crate::prompt::Prompt;
+use crate::report::{doctor, install_panic_hook, report_bug};
+use crate::review::review;
+use crate::sandbox::run_sandbox;
+use crate::scan::scan;
+use crate::standup::Standup;
+use crate::sync::{auto_synchronize, synchronize};
+use crate::task::{check_import, export_tasks, import_tasks};
+use crate::task_args::filter::Filters;
+use crate::task_args::modifier::{Modifier, TaskRef};
+use crate::task_args::project::Project;
+use crate::task_args::{is_rc_override, split_rc_overrides, ParseError};
+use crate::timew::Timew;
+use crate::update::self_update;
+use crate::{
+    alias, capabilities, csv, git, gitlab, hooks, hyperlink, i18n, ical, jira, logging, markdown,
+    picker, porcelain, recall, repl, run, run_interactive, run_quiet, setup, shell_init, table,
+    task_version, taskopen, taskwarrior_for, todotxt, toolchain, tui, version_supported, watch,
+    CommandResult, Taskwarrior, NAME, TASK_BIN, VERSION,
+};
+use clap::Parser as ClapParser;
+use clap::ValueEnum;
+use color_eyre::eyre::{bail, eyre};
+use color_eyre::owo_colors::OwoColorize;
+use color_eyre::Result;
+use log::debug;
+use log::info;
+use log::trace;
+use log::warn;
+use std::env;
+use std::fs;
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::str;
+use std::str::FromStr;
+use std::time::SystemTime;
+use tempfile::NamedTempFile;
+use terminal_size::{terminal_size, Width};
+
+const CONTEXT_NAME: &str = "taskhelper";
+/// Environment variable naming the project, overriding detection
+pub(crate) const PROJECT_VAR: &str = "TASKHELPER_PROJECT";
+/// rc overrides `--yes` passes, unless the config names others
+const YES_OVERRIDES: &[&str] = &["rc.confirmation=off", "rc.recurrence.confirmation=yes"];
+/// Report run when no subcommand is given, unless the config names another
+const DEFAULT_REPORT: &str = "next";
+/// Filters the `today` and `week` shortcuts stand for, listed with the next report
+const TODAY_FILTER: &str = "due.before:tomorrow or scheduled.before:tomorrow";
+const WEEK_FILTER: &str = "due.before:eow or scheduled.before:eow";
+/// Subcommands handled without running taskwarrior, before its setup is checked
+const STANDALONE_COMMANDS: &[&str] = &[
+    "_hook",
+    "doctor",
+    "recall",
+    "report-bug",
+    "self-update",
+    "setup",
+    "shell-init",
+    "toolchain",
+];
+
+fn no_filter(command: &Commands, filters: &Option<Filters>) -> Result<()> {
+    if filters.is_some() {
+        bail!(
+            "Subcommand '{}' does not allow preceding filters",
+            command.to_string()
+        );
+    }
+    Ok(())
+}
+
+#[derive(Debug)]
+enum Index {
+    Index(usize),
+    End,
+}
+
+/// Overrides defining and activating a temporary context scoped to the project
+fn context_overrides(scope: &Scope) -> Vec<String> {
+    vec![
+        format!("rc.context.{}.read={}", CONTEXT_NAME, scope),
+        format!("rc.context.{}.write={}", CONTEXT_NAME, scope),
+        format!("rc.context={}", CONTEXT_NAME),
+    ]
+}
+
+fn set_project(
+    scope: Option<&Scope>,
+    project_provided: bool,
+    args: &mut Vec<String>,
+    index: Index,
+    injection: Injection,
+) -> Option<String> {
+    if project_provided {
+        return None;
+    }
+    let scope = scope?;
+    let project_name = scope.project().name();
+    info!("Found project '{}' from cwd ansestory", project_name);
+    let placement = match injection {
+        Injection::Filter => match index {
+            Index::Index(i) => {
+                args.splice(i..i, scope.tokens());
+                if i == 0 {
+                    "first-filter"
+                } else {
+                    "after-subcommand"
+                }
+            }
+            Index::End => {
+                args.extend(scope.tokens());
+                "last-modifier"
+            }
+        },
+        Injection::Context => {
+            // rc overrides may appear anywhere on the command line, keep them up front
+            args.splice(0..0, context_overrides(scope));
+            "context"
+        }
+    };
+    Some(tr(
+        "injected",
+        &[
+            ("scope", scope),
+            ("placement", &placement),
+            ("context", &CONTEXT_NAME),
+        ],
+    ))
+}
+/// Arguments to run taskwarrior with
+#[derive(Debug)]
+struct Invocation {
+    args: Vec<String>,
+    /// Description of what was added to scope the command to the detected project
+    injected: Option<String>,
+}
+
+/// Translate parsed arguments into the argument list passed to taskwarrior, scoping the command
+/// to `scope` where it applies
+fn build_task_args(args: &Cli, scope: Option<&Scope>) -> Result<Invocation> {
+    let mut task_args = Vec::new();
+    let mut injected = None;
+
+    let mut project_filter_provided = false;
+    let mut project_mod_provided = false;
+    // Tasks picked out by id or uuid are used as is, wherever their project is
+    let mut tasks_named = false;
+
+    let injection = args.inject.unwrap_or_default();
+    let filters = &args.filter;
+    if let Some(filters) = &filters {
+        project_filter_provided = filters.constrains("project");
+        tasks_named = filters.names_tasks();
+        task_args.extend(filters.args());
+    }
+
+    match &args.command {
+        Some(command) => {
+            // Add the subcommand after any filters
+            task_args.push(command.to_string());
+
+            match command {
+                Commands::Add { mods, .. } => {
+                    no_filter(command, filters)?;
+                    check_recurrence(mods)?;
+
+                    for r#mod in mods {
+                        // TODO dont use match use let Modifier::Project()
+                        match r#mod {
+                            Modifier::Project(_project) => {
+                                project_mod_provided = true;
+                            }
+                            _ => {
+                                // do nothing
+                            }
+                        }
+                        task_args.push(r#mod.to_string());
+                    }
+
+                    // Set the project as the final argument, making it the last modifier
+                    injected = set_project(
+                        scope,
+                        project_mod_provided,
+                        &mut task_args,
+                        Index::End,
+                        injection,
+                    );
+                }
+                Commands::All { report } => {
+                    // Pass args unmodified to taskwarrior. This won't pickup a project from the cwd ansestory
+                    if let Some(report) = report {
+                        task_args.push(report.to_string());
+                    }
+                }
+                Commands::Blocked
+                | Commands::Blocking
+                | Commands::Completed
+                | Commands::Count
+                | Commands::Edit
+                | Commands::Ids
+                | Commands::Info
+                | Commands::Information
+                | Commands::Long
+                | Commands::Ls
+                | Commands::Minimal
+                | Commands::Newest
+                | Commands::Next
+                | Commands::Oldest
+                | Commands::Overdue
+                | Commands::Projects
+                | Commands::List
+                | Commands::Purge
+                | Commands::Recurring
+                | Commands::Stats
+                | Commands::Summary
+                | Commands::Tags
+                | Commands::Timesheet
+                | Commands::Unblocked
+                | Commands::Uuids
+                | Commands::Waiting
+                | Commands::Ready
+                | Commands::Burndown { .. }
+                | Commands::Ghistory { .. }
+                | Commands::History { .. }
+                | Commands::Export { .. } => {
+                    // Set project as the first arg, to make the first filter
+                    injected = set_project(
+                        scope,
+                        project_filter_provided || tasks_named,
+                        &mut task_args,
+                        Index::Index(0),
+                        injection,
+                    );
+                    if let Commands::Export {
+                        report: Some(report),
+                        ..
+                    } = command
+                    {
+                        task_args.push(report.to_string());
+                    }
+                }
+                Commands::Project => {
+                    if project_filter_provided {
+                        bail!("Usage error: project filter cannot be provided with 'project' subcommand");
+                    }
+                    injected =
+                        set_project(scope, false, &mut task_args, Index::Index(1), injection);
+                }
+                Commands::Start { mods }
+                | Commands::Stop { mods }
+                | Commands::Prepend { mods }
+                | Commands::Modify { mods }
+                | Commands::Log { mods }
+                | Commands::Done { mods }
+                | Commands::Duplicate { mods }
+                | Commands::Append { mods }
+                | Commands::Annotate { mods, .. }
+                | Commands::Delete { mods }
+                | Commands::Rm { mods } => {
+                    for r#mod in mods {
+                        // TODO dont use match use let Modifier::Project()
+                        match r#mod {
+                            Modifier::Project(_project) => {
+                                project_mod_provided = true;
+                            }
+                            _ => {
+                                // do nothing
+                            }
+                        }
+                        task_args.push(r#mod.to_string());
+                    }
+
+                    // A leading filter picks the tasks, so scope it rather than moving them. Without
+                    // one, set the project as the final argument, making it the last modifier
+                    let (project_provided, index) = match filters {
+                        Some(_) => (project_filter_provided || tasks_named, Index::Index(0)),
+                        None => (project_mod_provided, Index::End),
+                    };
+                    injected =
+                        set_project(scope, project_provided, &mut task_args, index, injection);
+                }
+                Commands::Calc { expression } => {
+                    no_filter(command, filters)?;
+                    task_args.extend_from_slice(expression);
+                }
+                Commands::Calendar { extra_args }
+                | Commands::Colors { extra_args }
+                | Commands::Columns { extra_args }
+                | Commands::Config { extra_args }
+                | Commands::Context { extra_args }
+                | Commands::Show { extra_args }
+                | Commands::Synchronize { extra_args } => {
+                    no_filter(command, filters)?;
+                    task_args.extend_from_slice(extra_args);
+                }
+
+                Commands::Denotate { extra_args } => {
+                    task_args.extend_from_slice(extra_args);
+                }
+                Commands::Execute { cmd } => {
+                    no_filter(command, filters)?;
+                    task_args.extend_from_slice(cmd);
+                }
+                Commands::TaskHelp { usage } => {
+                    no_filter(command, filters)?;
+                    if *usage {
+                        task_args.push(String::from("usage"));
+                    }
+                }
+                Commands::Import { files, .. } => {
+                    no_filter(command, filters)?;
+                    let files: Vec<String> =
+                        files.iter().map(|f| f.display().to_string()).collect();
+                    task_args.extend_from_slice(&files);
+                }
+                Commands::Complete { .. }
+                | Commands::Completions { .. }
+                | Commands::Doctor
+                | Commands::Filters { .. }
+                | Commands::CommitClose
+                | Commands::GitHook { .. }
+                | Commands::ExportCsv { .. }
+                | Commands::ExportIcal { .. }
+                | Commands::ExportMd { .. }
+                | Commands::ExportTodotxt { .. }
+                | Commands::Gitlab
+                | Commands::ImportJira { .. }
+                | Commands::ImportTodotxt { .. }
+                | Commands::Hook { .. }
+                | Commands::Hooks { .. }
+                | Commands::IdValues
+                | Commands::Mangen { .. }
+                | Commands::Open { .. }
+                | Commands::PostCommit
+                | Commands::ProjectValues
+                | Commands::Prompt
+                | Commands::Recall { .. }
+                | Commands::ReportBug
+                | Commands::SelfUpdate { .. }
+                | Commands::Sandbox { .. }
+                | Commands::Setup
+                | Commands::ShellInit { .. }
+                | Commands::TagValues
+                | Commands::Toolchain { .. }
+                | Commands::Branch
+                | Commands::Notify
+                | Commands::Review
+                | Commands::Scan
+                | Commands::Shell
+                | Commands::Standup
+                | Commands::Track
+                | Commands::Today
+                | Commands::Tui
+                | Commands::Untrack
+                | Commands::Week => {
+                    bail!(
+                        "Subcommand '{}' is handled by {} and not passed to taskwarrior",
+                        command,
+                        NAME
+                    );
+                }
+                Commands::Undo
+                | Commands::Udas
+                | Commands::Reports
+                | Commands::Diagnostics
+                | Commands::Commands
+                | Commands::Logo
+                | Commands::News => {
+                    no_filter(command, filters)?;
+                }
+            }
+        }
+        None => {
+            //
+        }
+    }
+
+    // rc overrides may appear anywhere on the command line, keep them up front
+    task_args.splice(0..0, args.overrides.iter().cloned());
+
+    Ok(Invocation {
+        args: task_args,
+        injected,
+    })
+}
+
+/// Put the overrides answering taskwarrior's confirmations ahead of the ones typed, which take
+/// precedence
+fn apply_yes(args: &mut Cli, config: &Config) -> Result<()> {
+    if !args.yes {
+        return Ok(());
+    }
+    let overrides = match &config.yes {
+        Some(overrides) => overrides.clone(),
+        None => YES_OVERRIDES.iter().map(|o| o.to_string()).collect(),
+    };
+    if let Some(invalid) = overrides.iter().find(|o| !is_rc_override(o)) {
+        bail!(
+            "'{}' under yes in the config is not an rc override",
+            invalid
+        );
+    }
+    args.overrides.splice(0..0, overrides);
+    Ok(())
+}
+
+/// Run the default report when no subcommand is given, so it's scoped like any other report.
+/// Filters naming tasks are left alone, taskwarrior shows their details
+fn apply_default_report(args: &mut Cli, config: &Config) -> Result<()> {
+    if args.command.is_some() || args.filter.as_ref().is_some_and(Filters::names_tasks) {
+        return Ok(());
+    }
+    let report = config.report.as_deref().unwrap_or(DEFAULT_REPORT);
+    let command = Cli::try_parse_from([NAME, report])
+        .ok()
+        .and_then(|cli| cli.command)
+        .filter(|command| !STANDALONE_COMMANDS.contains(&command.to_string().as_str()));
+    let Some(command) = command else {
+        bail!(
+            "Default report '{}' in the config isn't a subcommand",
+            report
+        );
+    };
+    debug!("Running the default report '{}'", command);
+    args.command = Some(command);
+    Ok(())
+}
+
+/// Turn the `today` and `week` shortcuts into the next report with their filters. Filters given
+/// on the same attributes take their place
+fn apply_shortcut(args: &mut Cli) -> Result<()> {
+    let shortcut = match &args.command {
+        Some(Commands::Today) => TODAY_FILTER,
+        Some(Commands::Week) => WEEK_FILTER,
+        _ => return Ok(()),
+    };
+    let shortcut: Filters = shortcut.parse()?;
+    args.filter = Some(match &args.filter {
+        Some(filters) => filters.with_defaults(&shortcut),
+        None => shortcut,
+    });
+    args.command = Some(Commands::Next);
+    Ok(())
+}
+
+/// Replace the named filters the user gives with their definitions. Done before anything else
+/// looks at the filters, so default filters see the attributes named filters constrain
+fn apply_named_filters(args: &mut Cli, config: &Config) -> Result<()> {
+    if let Some(filters) = &args.filter {
+        args.filter = Some(filters.expand(&config.filters)?);
+    }
+    Ok(())
+}
+
+/// Merge the config's default filters for the subcommand into the user's filters
+fn apply_default_filters(args: &mut Cli, config: &Config) -> Result<()> {
+    let Some(command) = &args.command else {
+        return Ok(());
+    };
+    let command = command.to_string();
+    let Some(defaults) = config.defaults.get(&command) else {
+        return Ok(());
+    };
+    let defaults = Filters::from_str(defaults)
+        .map_err(|_| ParseError::DefaultFilter(command.clone()))?
+        .expand(&config.filters)?;
+    debug!("Applying default filters '{}' to '{}'", defaults, command);
+    args.filter = Some(match &args.filter {
+        Some(filters) => filters.with_defaults(&defaults),
+        None => defaults,
+    });
+    Ok(())
+}
+
+/// Put the modifiers of the template `add` is given ahead of the ones on the command line,
+/// leaving out attributes the command line sets itself
+fn apply_template(args: &mut Cli, config: &Config) -> Result<()> {
+    let Some(Commands::Add {
+        template: Some(name),
+        mods,
+    }) = &mut args.command
+    else {
+        return Ok(());
+    };
+    let Some(template) = config.template.get(name.as_str()) else {
+        bail!("No template named '{}' in the config", name);
+    };
+    let mut merged = Vec::new();
+    for r#mod in template.modifiers() {
+        let r#mod = Modifier::from_str(&r#mod)
+            .map_err(|e| eyre!("Template '{}' has an invalid modifier: {}", name, e))?;
+        let overridden = r#mod
+            .attribute()
+            .is_some_and(|attribute| mods.iter().any(|m| m.attribute() == Some(attribute)));
+        if !overridden && !mods.contains(&r#mod) {
+            merged.push(r#mod);
+        }
+    }
+    debug!("Applying template '{}'", name);
+    merged.append(mods);
+    *mods = merged;
+    Ok(())
+}
+
+/// Annotation locating the work in a repository: the commit, the remote it can be found on and
+/// optionally a path and line in it
+fn location_annotation(commit: &str, remote: Option<&str>, at: Option<&str>) -> String {
+    let mut annotation = format!("Commit {}", commit);
+    if let Some(remote) = remote {
+        annotation.push_str(&format!(" of {}", remote));
+    }
+    if let Some(at) = at {
+        annotation.push_str(&format!(", {}", at));
+    }
+    annotation
+}
+
+/// Append the location in the git repository in the current directory to the text `annotate`
+/// is given with `--here` or `--at`
+fn apply_here(args: &mut Cli) -> Result<()> {
+    let Some(Commands::Annotate { here, at, mods }) = &mut args.command else {
+        return Ok(());
+    };
+    if !*here && at.is_none() {
+        return Ok(());
+    }
+    let commit = git::git(None, &["rev-parse", "--short", "HEAD"])
+        .ok_or_else(|| eyre!("Not in a git repository with a commit checked out"))?;
+    let remote = git::git(None, &["remote", "get-url", "origin"]);
+    let at = match at {
+        Some(at) => {
+            let path = match at.rsplit_once(':') {
+                Some((path, line)) if line.parse::<u32>().is_ok() => path,
+                _ => at.as_str(),
+            };
+            if !Path::new(path).exists() {
+                bail!("No file '{}' to annotate the task with", path);
+            }
+            // Relative to the root of the repository, like the remote shows it
+            let prefix = git::git(None, &["rev-parse", "--show-prefix"]).unwrap_or_default();
+            Some(format!("{}{}", prefix, at))
+        }
+        None => None,
+    };
+    let annotation = location_annotation(&commit, remote.as_deref(), at.as_deref());
+    mods.push(Modifier::Description(annotation));
+    Ok(())
+}
+
+/// Print a dimmed informational line to stderr
+fn print_note(note: &str, plain: bool) {
+    if !plain && io::stderr().is_terminal() {
+        eprintln!("{}", note.dimmed());
+    } else {
+        eprintln!("{}", note);
+    }
+}
+
+fn list_filters(config: &Config) {
+    for (name, definition) in &config.filters {
+        println!("@{} = {}", name, definition);
+    }
+}
+
+/// Scope for the command: the project named on the command line, else the one named by
+/// `env_project`, the value of $TASKHELPER_PROJECT, else the one detected from the working
+/// directory. An empty `env_project` turns injection off
+fn scope_with(args: &Cli, config: &Config, env_project: Option<String>) -> Result<Option<Scope>> {
+    if args.no_project {
+        return Ok(None);
+    }
+    let name = args.project.clone().or(env_project);
+    match name.as_deref() {
+        Some("") => Ok(None),
+        Some(name) => Ok(Some(Scope::with_project(Project::with_name(name)))),
+        None => find_scope(&config.project),
+    }
+}
+
+fn scope_for(args: &Cli, config: &Config) -> Result<Option<Scope>> {
+    scope_with(args, config, env::var(PROJECT_VAR).ok())
+}
+
+/// Check the files given to `import` hold tasks, so a mistake is reported with its line rather
+/// than by taskwarrior. Stdin, given as `-`, is read into a temporary file which is returned and
+/// passed to taskwarrior instead
+fn prepare_import(args: &mut Cli) -> Result<Option<NamedTempFile>> {
+    let Some(Commands::Import { no_check, files }) = &mut args.command else {
+        return Ok(None);
+    };
+    let mut stdin_file = None;
+    for file in files.iter_mut() {
+        let (name, json) = if file.as_os_str() == "-" {
+            let mut json = String::new();
+            io::stdin().read_to_string(&mut json)?;
+            let mut temp = tempfile::Builder::new().suffix(".json").tempfile()?;
+            temp.write_all(json.as_bytes())?;
+            *file = temp.path().to_path_buf();
+            stdin_file = Some(temp);
+            (String::from("stdin"), json)
+        } else if *no_check {
+            continue;
+        } else {
+            let json = fs::read_to_string(&file)
+                .map_err(|e| eyre!("Unable to read '{}': {}", file.display(), e))?;
+            (format!("'{}'", file.display()), json)
+        };
+        if !*no_check {
+            let count =
+                check_import(&json).map_err(|e| eyre!("Invalid task in {}, {}", name, e))?;
+            debug!("{} holds {} tasks to import", name, count);
+        }
+    }
+    Ok(stdin_file)
+}
+
+/// For `--output json` and `table`, turn the report into an export of its tasks and return the
+/// report's name
+fn report_export(args: &mut Cli) -> Result<Option<String>> {
+    if args.output == Output::Text {
+        return Ok(None);
+    }
+    let report = match &args.command {
+        Some(command) if command.is_report() => command.to_string(),
+        // Already JSON
+        Some(Commands::Export { .. }) if args.output == Output::Json => return Ok(None),
+        Some(command) => bail!("--output only applies to reports, not '{}'", command),
+        None => bail!("--output needs a report"),
+    };
+    args.command = Some(Commands::Export {
+        output: None,
+        report: Some(report.clone()),
+    });
+    Ok(Some(report))
+}
+
+/// Run `args`, an export, and print the tasks in a table fit to the terminal, or as `plain`
+/// lines. With `links`, tasks and URLs are hyperlinked
+fn print_table(
+    task: &Taskwarrior,
+    args: &[String],
+    config: &Config,
+    plain: bool,
+    links: Option<&LinksConfig>,
+) -> Result<CommandResult> {
+    let (res, tasks) = porcelain::run_export(task, args)?;
+    if let Some(tasks) = tasks {
+        let columns = if config.table.columns.is_empty() {
+            table::DEFAULT_COLUMNS
+                .iter()
+                .map(|c| c.to_string())
+                .collect()
+        } else {
+            config.table.columns.clone()
+        };
+        if plain {
+            print!("{}", table::render_records(&tasks, &columns));
+            return Ok(res);
+        }
+        let terminal = io::stdout().is_terminal();
+        let width = terminal_size()
+            .filter(|_| terminal)
+            .map(|(Width(width), _)| usize::from(width));
+        print!(
+            "{}",
+            table::render(&tasks, &columns, width, terminal, links)
+        );
+    }
+    Ok(res)
+}
+
+/// Run the export in `args`, writing the JSON to `path` instead of stdout
+fn export_to(task: &Taskwarrior, args: &[String], path: &Path) -> Result<CommandResult> {
+    let res = run_quiet(task, args)?;
+    eprint!("{}", res.stderr);
+    if res.exit.success() {
+        fs::write(path, &res.stdout)
+            .map_err(|e| eyre!("Unable to write '{}': {}", path.display(), e))?;
+    }
+    Ok(res)
+}
+
+/// Write `contents` to the file at `path`, or stdout without one
+fn write_output(path: Option<&Path>, contents: &str) -> Result<()> {
+    match path {
+        Some(path) => fs::write(path, contents)
+            .map_err(|e| eyre!("Unable to write '{}': {}", path.display(), e))?,
+        None => print!("{}", contents),
+    }
+    Ok(())
+}
+
+/// Start or stop tracking time in timewarrior along with the task `args` started or stopped
+fn follow_in_timew(
+    args: &Cli,
+    config: &Config,
+    scope: Option<&Scope>,
+    task: &Taskwarrior,
+) -> Result<()> {
+    match &args.command {
+        Some(Commands::Start { .. }) => {
+            let rc = scope_overrides(config, scope)?;
+            let mut filter = scoped_filters(args.filter.as_ref(), scope);
+            filter.push(String::from("+ACTIVE"));
+            Timew::find(&config.timew)?.track(task, &filter, &rc)
+        }
+        Some(Commands::Stop { .. }) => Timew::find(&config.timew)?.stop(),
+        _ => Ok(()),
+    }
+}
+
+/// `filters` followed by the tokens scoping them to `scope`, unless they already filter on a
+/// project. For commands run outside of taskwarrior's own argument handling
+fn scoped_filters(filters: Option<&Filters>, scope: Option<&Scope>) -> Vec<String> {
+    let project_provided = filters.is_some_and(|f| f.constrains("project"));
+    let mut args = filters.map(Filters::args).unwrap_or_default();
+    if !project_provided {
+        if let Some(scope) = scope {
+            args.extend(scope.tokens());
+        }
+    }
+    args
+}
+
+/// Taskwarrior only adds a recurring task with a due date to recur from, fail early without one
+fn check_recurrence(mods: &[Modifier]) -> Result<()> {
+    let recurs = mods.iter().any(|m| matches!(m, Modifier::Recur(Some(_))));
+    let due = mods
+        .iter()
+        .any(|m| matches!(m, Modifier::Date { name, value } if name == "due" && !value.is_empty()));
+    if recurs && !due {
+        bail!("A recurring task needs a due date, add one with 'due:<date>'");
+    }
+    Ok(())
+}
+
+/// Warn about modifiers setting attributes taskwarrior doesn't have, which it would add to the
+/// description instead
+fn warn_unknown_attributes(task: &Taskwarrior, command: &Commands) {
+    let unknown = |udas: &[String]| -> Vec<String> {
+        command
+            .modifiers()
+            .iter()
+            .filter(|m| m.unknown_attribute(udas).is_some())
+            .map(|m| m.to_string())
+            .collect()
+    };
+    // Only ask taskwarrior for its UDAs if the built in attributes don't cover everything
+    if unknown(&[]).is_empty() {
+        return;
+    }
+    let udas = match task.udas() {
+        Ok(udas) => udas,
+        Err(e) => {
+            debug!("Unable to list UDAs: {}", e);
+            return;
+        }
+    };
+    for modifier in unknown(udas) {
+        eprintln!(
+            "Warning: '{}' is not a known attribute or UDA, taskwarrior will add it to the description",
+            modifier
+        );
+    }
+}
+
+/// Ids `command` makes tasks depend on
+fn dependency_ids(command: &Commands) -> Vec<u64> {
+    command
+        .modifiers()
+        .iter()
+        .filter_map(|m| match m {
+            Modifier::Depends(dependencies) => Some(dependencies),
+            _ => None,
+        })
+        .flatten()
+        .filter(|d| !d.removed)
+        .filter_map(|d| match d.task {
+            TaskRef::Id(id) => Some(id),
+            TaskRef::Uuid(_) => None,
+        })
+        .collect()
+}
+
+/// Fail before running `command` if it adds a dependency on an id no task has. `overrides` are
+/// passed on to taskwarrior to look the ids up in the same data
+fn check_dependencies(task: &Taskwarrior, command: &Commands, overrides: &[String]) -> Result<()> {
+    let ids = dependency_ids(command);
+    if ids.is_empty() {
+        return Ok(());
+    }
+    let mut args = overrides.to_vec();
+    // Ids of tasks hidden by the user's context can be depended on too
+    args.extend([String::from("rc.context=none"), String::from("_ids")]);
+    let output = task.command(&args).output()?;
+    let known: Vec<u64> = String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .filter_map(|id| id.parse().ok())
+        .collect();
+    let missing: Vec<String> = ids
+        .iter()
+        .filter(|id| !known.contains(id))
+        .map(|id| id.to_string())
+        .collect();
+    if !missing.is_empty() {
+        bail!("No task with id {} to depend on", missing.join(", "));
+    }
+    Ok(())
+}
+
+/// Overrides pointing taskwarrior at the project's own database, if `config` isolates projects
+fn isolation_overrides(config: &Config, scope: &Scope) -> Result<Vec<String>> {
+    let mut rc = Vec::new();
+    if config.project.isolate {
+        let dir = isolated_data_dir(scope.project())?;
+        rc.push(format!("rc.data.location={}", dir.display()));
+    }
+    Ok(rc)
+}
+
+/// Overrides for commands run in `scope`, none without a scope
+fn scope_overrides(config: &Config, scope: Option<&Scope>) -> Result<Vec<String>> {
+    match scope {
+        Some(scope) => isolation_overrides(config, scope),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Give a subcommand acting on one task the task the user picks among the pending ones in scope
+fn pick_task(
+    args: &mut Cli,
+    task: &Taskwarrior,
+    config: &Config,
+    scope: Option<&Scope>,
+) -> Result<()> {
+    let mut export_args = scope_overrides(config, scope)?;
+    export_args.extend(scoped_filters(None, scope));
+    export_args.push(String::from("status:pending"));
+    let tasks = export_tasks(task, &export_args)?;
+    if tasks.is_empty() {
+        bail!("No pending tasks to pick from");
+    }
+    let command = args.command.as_ref().map(Commands::to_string);
+    let suggestions = recall::suggestions(scope);
+    let command = command.as_deref().unwrap_or_default();
+    let Some(picked) = picker::pick(&tasks, command, &suggestions)? else {
+        bail!("No task picked");
+    };
+    args.filter = Some(picked.uuid.parse()?);
+    Ok(())
+}
+
+/// The subcommand of `args` that runs without taskwarrior, wherever global flags put it
+fn standalone_command(args: &[String]) -> Option<Commands> {
+    Cli::try_parse_from(args)
+        .ok()?
+        .command
+        .filter(|command| STANDALONE_COMMANDS.contains(&command.to_string().as_str()))
+}
+
+/// Run the command line program with the process arguments
+pub fn main() -> Result<()> {
+    let (panic_hook, eyre_hook) = color_eyre::config::HookBuilder::default().into_hooks();
+    eyre_hook.install()?;
+
+    // Do some initial processing of args before passing off to clap to handle multicall
+    let args: Vec<String> = std::env::args().collect();
+    install_panic_hook(panic_hook, &args);
+    let this_program = PathBuf::from(&args[0]);
+    // let this_program = fs::canonicalize(this_program)?;
+
+    // Subcommands that don't need taskwarrior run before anything that fails on a broken setup
+    if let Some(command) = standalone_command(&args) {
+        match command {
+            Commands::Doctor => print!("{}", doctor(&this_program)),
+            Commands::Hook { handler } => hooks::run_hook(handler)?,
+            Commands::Recall { search, run: true } => {
+                std::process::exit(recall::rerun(search.as_deref())?)
+            }
+            Commands::Setup => setup::setup(&this_program)?,
+            Commands::ShellInit {
+                shell,
+                alias,
+                no_prompt,
+            } => print!("{}", shell_init::init(shell, &alias, no_prompt)?),
+            Commands::Recall { search, run: false } => {
+                print!("{}", recall::list(search.as_deref())?)
+            }
+            Commands::ReportBug => print!("{}", report_bug(&this_program)?),
+            Commands::SelfUpdate { check } => self_update(check)?,
+            Commands::Toolchain { command } => match command {
+                ToolchainCommand::Install { version } => {
+                    let path = toolchain::install(&Config::load()?.toolchain, &version)?;
+                    println!("Installed taskwarrior {} to '{}'", version, path.display());
+                }
+                ToolchainCommand::List => print!("{}", toolchain::list()?),
+            },
+            command => bail!("Subcommand {:?} does not run standalone", command),
+        }
+        std::process::exit(0);
+    }
+
+    let config = Config::load()?;
+    logging::init(log_json_path(&args).as_deref(), &config.log)?;
+    trace!("This program: {}", this_program.display());
+    i18n::init(config.locale.as_deref());
+    let mut task = taskwarrior_for(&config, &this_program)?;
+
+    let taskwarrior_version = task_version(&task)?;
+    let version_compat = version_supported(&taskwarrior_version, config.supported_versions())
+        .unwrap_or_else(|e| {
+            warn!("{}", e);
+            false
+        });
+
+    let name = this_program.file_name().unwrap();
+    debug!("name: {:?}", name);
+    if name == "task" {
+        // Mimic taskwarrior when invoked under 'task'. We do this by exec-ing taskwarrior and passing args unmodified
+        let task_args: Vec<String> = std::env::args().skip(1).collect();
+        let res = run(&task, &task_args)?;
+        std::process::exit(res.exit.code());
+    }
+
+    if let Some(first) = args.get(1) {
+        match first.as_str() {
+            "--version" => {
+                let compatibility = if version_compat {
+                    tr("version-compatible", &[])
+                } else {
+                    tr("version-incompatible", &[])
+                };
+                println!(
+                    "{}: {}, {}: {} ({})",
+                    NAME, VERSION, TASK_BIN, taskwarrior_version, compatibility
+                );
+                std::process::exit(0);
+            }
+            "-V" => {
+                println!("{}", VERSION);
+                std::process::exit(0);
+            }
+            _ => {}
+        }
+    }
+
+    if !version_compat {
+        warn!(
+            "Unsupported taskwarrior version {} found, but this program supports {}. Will continue anyways...",
+            taskwarrior_version,
+            config.supported_versions()
+        );
+    }
+
+    let typed = args[1..].to_vec();
+    let args = alias::expand(args, &config.aliases);
+    let (args, overrides) = split_rc_overrides(args);
+    let mut args = Cli::parse_from(join_leading_filter(args));
+    args.overrides = overrides;
+    let plain = args.plain || env::var("TERM").is_ok_and(|term| term == "dumb");
+    let links = (!plain && hyperlink::enabled(&config.links)).then_some(&config.links);
+
+    if let Some(taskrc) = args.taskrc.as_ref().or(config.taskrc.as_ref()) {
+        task = task.env("TASKRC", taskrc);
+    }
+    let low_priority = args.low_priority
+        || args
+            .command
+            .as_ref()
+            .is_some_and(|command| config.priority.commands.contains(&command.base_name()));
+    if low_priority {
+        task = task.low_priority(config.priority.nice);
+    }
+    let taskdata = args.taskdata.clone().or(config.taskdata.clone());
+    if let Some(taskdata) = &taskdata {
+        task = task.env("TASKDATA", taskdata);
+    }
+    task.check_env()?;
+    if config.project.isolate {
+        task.reach(&isolated_data_root()?)?;
+    }
+
+    match &args.command {
+        Some(Commands::Complete { shell, line }) => {
+            let scope = scope_for(&args, &config)?;
+            complete(&task, *shell, line, &recall::suggestions(scope.as_ref()))?;
+            std::process::exit(0);
+        }
+        Some(Commands::Completions { shell }) => {
+            print!("{}", completion_script(*shell));
+            std::process::exit(0);
+        }
+        Some(Commands::IdValues) => {
+            print_values(&task, Source::Ids)?;
+            std::process::exit(0);
+        }
+        Some(Commands::ProjectValues) => {
+            print_values(&task, Source::Projects)?;
+            std::process::exit(0);
+        }
+        Some(Commands::TagValues) => {
+            print_values(&task, Source::Tags)?;
+            std::process::exit(0);
+        }
+        Some(Commands::Filters { command }) => {
+            match command {
+                FiltersCommand::List => list_filters(&config),
+            }
+            std::process::exit(0);
+        }
+        Some(Commands::Hooks { command }) => {
+            match command {
+                HooksCommand::List => print!("{}", hooks::list(&hooks::hooks_dir(&task)?)?),
+                HooksCommand::Install { hook } => {
+                    let dir = hooks::install_dir(&task, Some(*hook))?;
+                    for path in hooks::install(&dir, *hook, &env::current_exe()?)? {
+                        println!("Installed '{}'", path.display());
+                    }
+                }
+                HooksCommand::Remove { hook } => {
+                    let dir = hooks::install_dir(&task, BundledHook::from_str(hook, false).ok())?;
+                    for path in hooks::remove(&dir, hook)? {
+                        println!("Removed '{}'", path.display());
+                    }
+                }
+            }
+            std::process::exit(0);
+        }
+        Some(Commands::GitHook { remove }) => {
+            let hook = BundledHook::CommitClose;
+            let dir = hooks::install_dir(&task, Some(hook))?;
+            if *remove {
+                for path in hooks::remove(&dir, "commit-close")? {
+                    println!("Removed '{}'", path.display());
+                }
+            } else {
+                for path in hooks::install(&dir, hook, &env::current_exe()?)? {
+                    println!("Installed '{}'", path.display());
+                }
+            }
+            std::process::exit(0);
+        }
+        Some(Commands::CommitClose) => {
+            let rc = scope_overrides(&config, scope_for(&args, &config)?.as_ref())?;
+            hooks::close_referenced(&task, &rc)?;
+            std::process::exit(0);
+        }
+        Some(Commands::PostCommit) => {
+            if let Some(scope) = scope_for(&args, &config)? {
+                hooks::annotate_commit(&task, &scope, &isolation_overrides(&config, &scope)?)?;
+            }
+            std::process::exit(0);
+        }
+        Some(Commands::Prompt) => {
+            if let Some(scope) = scope_for(&args, &config)? {
+                let rc = isolation_overrides(&config, &scope)?;
+                println!("{}", shell_init::segment(&task, &scope, &rc)?);
+            }
+            std::process::exit(0);
+        }
+        Some(Commands::Mangen { dir }) => {
+            generate_man_pages(dir)?;
+            std::process::exit(0);
+        }
+        Some(Commands::Sandbox {
+            seed,
+            args: sandbox_args,
+        }) => {
+            let code = run_sandbox(&task, seed.as_deref(), sandbox_args)?;
+            std::process::exit(code);
+        }
+        _ => {}
+    }
+
+    args.inject = args.inject.or(config.project.injection);
+    apply_yes(&mut args, &config)?;
+    apply_named_filters(&mut args, &config)?;
+    apply_default_report(&mut args, &config)?;
+    apply_shortcut(&mut args)?;
+    apply_default_filters(&mut args, &config)?;
+    apply_template(&mut args, &config)?;
+    apply_here(&mut args)?;
+
+    if let Some(Commands::All { report }) = &args.command {
+        let databases = databases(&config)?;
+        if databases.len() > 1 {
+            let filters = args.filter.iter().map(|f| f.to_string()).collect();
+            merged_view(&task, &databases, filters, report.as_deref(), links, plain)?;
+            std::process::exit(0);
+        }
+    }
+
+    let scope = scope_for(&args, &config)?;
+    if let Some(Commands::Open { extra_args }) = &args.command {
+        let taskrc = args.taskrc.as_ref().or(config.taskrc.as_ref());
+        let data_dir = match &scope {
+            Some(scope) if config.project.isolate => Some(isolated_data_dir(scope.project())?),
+            _ => taskdata,
+        };
+        let mut taskopen_args = scoped_filters(args.filter.as_ref(), scope.as_ref());
+        taskopen_args.extend_from_slice(extra_args);
+        let code = taskopen::open(
+            &taskopen_args,
+            taskrc.map(PathBuf::as_path),
+            data_dir.as_deref(),
+        )?;
+        std::process::exit(code);
+    }
+    match &args.command {
+        Some(Commands::Track) => {
+            let rc = scope_overrides(&config, scope.as_ref())?;
+            let filter = scoped_filters(args.filter.as_ref(), scope.as_ref());
+            Timew::find(&config.timew)?.track(&task, &filter, &rc)?;
+            std::process::exit(0);
+        }
+        Some(Commands::Branch) => {
+            let rc = scope_overrides(&config, scope.as_ref())?;
+            start_branch_task(&task, &rc, scope.as_ref())?;
+            std::process::exit(0);
+        }
+        Some(Commands::Notify) => {
+            let mut notify_args = scope_overrides(&config, scope.as_ref())?;
+            notify_args.extend(scoped_filters(args.filter.as_ref(), scope.as_ref()));
+            notify_due(&task, &notify_args, &config.notify)?;
+            std::process::exit(0);
+        }
+        Some(Commands::Review) => {
+            if !io::stdin().is_terminal() {
+                bail!("Review asks questions, so it must be run in a terminal");
+            }
+            let rc = scope_overrides(&config, scope.as_ref())?;
+            let filter = scoped_filters(args.filter.as_ref(), scope.as_ref());
+            let mut prompt = Prompt {
+                input: io::stdin().lock(),
+                output: io::stdout(),
+            };
+            review(&mut prompt, &task, &rc, &filter, links)?;
+            std::process::exit(0);
+        }
+        Some(Commands::Gitlab) => {
+            let Some(scope) = &scope else {
+                bail!("No project found in the current directory to import the issues to");
+            };
+            let rc = isolation_overrides(&config, scope)?;
+            gitlab::import(&task, &rc, scope, &config.gitlab, args.dry_run)?;
+            std::process::exit(0);
+        }
+        Some(Commands::ImportJira { jql }) => {
+            let Some(scope) = &scope else {
+                bail!("No project found in the current directory to import the issues to");
+            };
+            let rc = isolation_overrides(&config, scope)?;
+            jira::import(
+                &task,
+                &rc,
+                scope,
+                &config.jira,
+                jql.as_deref(),
+                args.dry_run,
+            )?;
+            std::process::exit(0);
+        }
+        Some(Commands::Scan) => {
+            let Some(scope) = &scope else {
+                bail!("No project found in the current directory to add the comments to");
+            };
+            let rc = isolation_overrides(&config, scope)?;
+            scan(&task, &rc, scope, args.dry_run)?;
+            std::process::exit(0);
+        }
+        Some(Commands::Shell) => {
+            let mut global = Vec::new();
+            if let Some(taskrc) = &args.taskrc {
+                global.extend([String::from("--taskrc"), taskrc.display().to_string()]);
+            }
+            if let Some(taskdata) = &args.taskdata {
+                global.extend([String::from("--taskdata"), taskdata.display().to_string()]);
+            }
+            repl::run(&task, scope.as_ref(), &global)?;
+            std::process::exit(0);
+        }
+        Some(Commands::Standup) => {
+            let mut standup_args = scope_overrides(&config, scope.as_ref())?;
+            standup_args.extend(scoped_filters(args.filter.as_ref(), scope.as_ref()));
+            let project = scope
+                .as_ref()
+                .map(|scope| scope.project().name().to_string());
+            print!(
+                "{}",
+                Standup::query(&task, &standup_args)?.render(project.as_deref(), links)
+            );
+            std::process::exit(0);
+        }
+        Some(Commands::ExportCsv { output, columns }) => {
+            let mut export_args = scope_overrides(&config, scope.as_ref())?;
+            export_args.extend(scoped_filters(args.filter.as_ref(), scope.as_ref()));
+            let tasks = export_tasks(&task, &export_args)?;
+            write_output(output.as_deref(), &csv::render(&tasks, columns))?;
+            std::process::exit(0);
+        }
+        Some(Commands::ExportIcal { output }) => {
+            let mut export_args = scope_overrides(&config, scope.as_ref())?;
+            export_args.extend(scoped_filters(args.filter.as_ref(), scope.as_ref()));
+            let tasks = ical::query(&task, &export_args)?;
+            let ics = ical::render(&tasks, SystemTime::now());
+            write_output(output.as_deref(), &ics)?;
+            std::process::exit(0);
+        }
+        Some(Commands::ExportMd { output, group_by }) => {
+            let mut export_args = scope_overrides(&config, scope.as_ref())?;
+            export_args.extend(scoped_filters(args.filter.as_ref(), scope.as_ref()));
+            let project = scope
+                .as_ref()
+                .map(|scope| scope.project().name().to_string());
+            let tasks = markdown::query(&task, &export_args)?;
+            let md = markdown::render(&tasks, *group_by, project.as_deref());
+            write_output(output.as_deref(), &md)?;
+            std::process::exit(0);
+        }
+        Some(Commands::ExportTodotxt { output }) => {
+            let mut export_args = scope_overrides(&config, scope.as_ref())?;
+            export_args.extend(scoped_filters(args.filter.as_ref(), scope.as_ref()));
+            let tasks = todotxt::query(&task, &export_args)?;
+            write_output(output.as_deref(), &todotxt::render(&tasks))?;
+            std::process::exit(0);
+        }
+        Some(Commands::ImportTodotxt { file }) => {
+            let contents = match file.as_os_str() == "-" {
+                true => {
+                    let mut contents = String::new();
+                    io::stdin().read_to_string(&mut contents)?;
+                    contents
+                }
+                false => fs::read_to_string(file)
+                    .map_err(|e| eyre!("Unable to read '{}': {}", file.display(), e))?,
+            };
+            let project = scope.as_ref().map(|scope| scope.project().name().as_str());
+            let tasks = todotxt::parse(&contents, project, SystemTime::now());
+            if args.dry_run {
+                for new in &tasks {
+                    println!(
+                        "Would import '{}'",
+                        new["description"].as_str().unwrap_or_default()
+                    );
+                }
+                std::process::exit(0);
+            }
+            let rc = scope_overrides(&config, scope.as_ref())?;
+            if !tasks.is_empty() {
+                import_tasks(&task, &rc, &tasks)?;
+            }
+            println!("Imported {} tasks", tasks.len());
+            std::process::exit(0);
+        }
+        Some(Commands::Tui) => {
+            let rc = scope_overrides(&config, scope.as_ref())?;
+            let filter = scoped_filters(args.filter.as_ref(), scope.as_ref());
+            tui::run(&task, &rc, &filter)?;
+            std::process::exit(0);
+        }
+        Some(Commands::Untrack) => {
+            Timew::find(&config.timew)?.stop()?;
+            std::process::exit(0);
+        }
+        _ => {}
+    }
+    let pick = args.filter.is_none()
+        && args.command.as_ref().is_some_and(Commands::picks_task)
+        && io::stdin().is_terminal()
+        && io::stdout().is_terminal();
+    if pick {
+        pick_task(&mut args, &task, &config, scope.as_ref())?;
+    }
+    if let Some(command) = &args.command {
+        capabilities::check(&taskwarrior_version, command)?;
+    }
+    if args.watch {
+        match &args.command {
+            Some(command) if command.is_report() => {}
+            Some(command) => bail!("--watch only applies to reports, not '{}'", command),
+            None => bail!("--watch needs a report"),
+        }
+        if !task.is_local() {
+            bail!("--watch needs taskwarrior's data on this machine");
+        }
+    }
+    let report = report_export(&mut args)?;
+    // Kept until taskwarrior has read it
+    let _stdin_file = prepare_import(&mut args)?;
+    let invocation = build_task_args(&args, scope.as_ref())?;
+    if let Some(injected) = &invocation.injected {
+        info!(event = "injection"; "{}", injected);
+        if args.verbose || config.project.audit {
+            print_note(injected, plain);
+        }
+    }
+    let mut task_args = invocation.args;
+    if config.project.isolate {
+        if let Some(scope) = &scope {
+            let dir = isolated_data_dir(scope.project())?;
+            task_args.insert(0, format!("rc.data.location={}", dir.display()));
+        }
+    }
+
+    if args.explain {
+        let cmd = task.command(&task_args);
+        print!(
+            "{}",
+            explain(
+                &args,
+                scope.as_ref(),
+                invocation.injected.as_deref(),
+                &task_args,
+                &cmd
+            )
+        );
+        std::process::exit(0);
+    }
+
+    if args.dry_run {
+        println!("{}", command_line(&task.command(&task_args)));
+        std::process::exit(0);
+    }
+
+    if let Some(command) = &args.command {
+        let overrides: Vec<String> = task_args
+            .iter()
+            .filter(|arg| arg.starts_with("rc.data.location="))
+            .cloned()
+            .collect();
+        check_dependencies(&task, command, &overrides)?;
+        warn_unknown_attributes(&task, command);
+    }
+
+    let mut hook_env = environment(&args, scope.as_ref(), &task_args);
+    if let Some(command) = &args.command {
+        run_hook(&config.hooks, Stage::Pre, command, &hook_env)?;
+    }
+
+    // Queue behind other taskhelper processes changing the same data
+    let mutating = args.command.as_ref().is_some_and(Commands::is_mutating);
+    let overrides: Vec<String> = task_args
+        .iter()
+        .take_while(|arg| is_rc_override(arg))
+        .cloned()
+        .collect();
+    // Wherever the taskrc, environment or overrides put the data, as taskwarrior resolves it
+    let data_dir = if mutating && task.is_local() {
+        Some(watch::data_dir(&task, &overrides)?)
+    } else {
+        None
+    };
+    let lock = match data_dir {
+        Some(dir) if dir.parent().is_some_and(Path::is_dir) => Some(lock_data_dir(&dir)?),
+        _ => None,
+    };
+
+    if let Err(e) = recall::record(&typed, scope.as_ref()) {
+        warn!("Unable to record the command in the history: {}", e);
+    }
+    info!(event = "command", args:% = task_args.join(" "); "Running taskwarrior");
+    let render = || {
+        if let Some(report) = &report {
+            return match args.output {
+                Output::Json => porcelain::print_report(&task, &task_args, report, scope.as_ref()),
+                _ => print_table(&task, &task_args, &config, plain, links),
+            };
+        }
+        match &args.command {
+            Some(Commands::Synchronize { .. }) => synchronize(&task, &task_args, &config.sync),
+            Some(Commands::Export {
+                output: Some(path), ..
+            }) => export_to(&task, &task_args, path),
+            Some(command) if command.is_interactive() => run_interactive(&task, &task_args),
+            _ => run(&task, &task_args),
+        }
+    };
+    let res = if args.watch {
+        watch::watch(&watch::data_dir(&task, &overrides)?, render)?
+    } else {
+        render()?
+    };
+    if config.timew.track && res.exit.success() {
+        if let Err(e) = follow_in_timew(&args, &config, scope.as_ref(), &task) {
+            warn!("Unable to track time in timewarrior: {}", e);
+        }
+    }
+    let auto_sync = (args.sync || config.sync.auto) && !args.no_sync;
+    let synced = matches!(args.command, Some(Commands::Synchronize { .. }));
+    if auto_sync && mutating && !synced && res.exit.success() {
+        let overrides: Vec<String> = task_args
+            .iter()
+            .take_while(|arg| is_rc_override(arg))
+            .cloned()
+            .collect();
+        auto_synchronize(&task, &overrides, &config.sync);
+    }
+    // Hooks may run taskhelper themselves
+    drop(lock);
+    if let Some(command) = &args.command {
+        hook_env.push(("TASKHELPER_EXIT", res.exit.code().to_string()));
+        if let Err(e) = run_hook(&config.hooks, Stage::Post, command, &hook_env) {
+            eprintln!("Warning: {}", e);
+        }
+    }
+    let code = res.exit.code();
+    info!(
+        event = "exit",
+        code = code,
+        wall_ms = res.usage.wall.as_millis() as u64;
+        "taskwarrior exited with {}", res.exit
+    );
+    if args.timings {
+        print_note(&format!("taskwarrior: {}", res.usage), plain);
+    }
+
+    /*
+    let Ok(child) = XCommand::builder(&task_bin)
+        //.args(&["help", "usage"])?
+        .build()
+        .spawn()
+    else {
+        bail!("Unable to run '{}'", task_bin.display());
+    };
+
+    let stream = child.stream();
+    pin_mut!(stream);
+    while let Some(output) = stream.next().await {
+        let (source, line) = output.unwrap();
+        match source {
+            StdioType::Stdout => {
+                println!("[stdout]{}", line);
+            }
+            StdioType::Stderr => {
+                println!("[stderr]{}", line);
+            }
+        }
+    }
+    */
+
+    //let code = run(&task_bin, &processed_args).await?;
+
+    std::process::exit(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task_args::project::Project;
+    use pretty_assertions::assert_eq;
+    use s_string::s;
+
+    #[test]
+    fn test_scoped_filters() {
+        let scope = Scope::with_project(Project::with_name("repo"));
+        let filters = Filters::from_str("status:pending").unwrap();
+        assert_eq!(
+            vec![s!("status:pending"), s!("project:repo")],
+            scoped_filters(Some(&filters), Some(&scope))
+        );
+        let filters = Filters::from_str("project:other").unwrap();
+        assert_eq!(
+            vec![s!("project:other")],
+            scoped_filters(Some(&filters), Some(&scope))
+        );
+        let filters = Filters::from_str("project.not:home").unwrap();
+        assert_eq!(
+            vec![s!("project.not:home")],
+            scoped_filters(Some(&filters), Some(&scope))
+        );
+        assert!(scoped_filters(None, None).is_empty());
+    }
+
+    fn task_args_for(argv: &[&str], scope: Option<&Scope>) -> Vec<String> {
+        let cli = Cli::parse_from(std::iter::once("taskhelper").chain(argv.iter().copied()));
+        build_task_args(&cli, scope).unwrap().args
+    }
+
+    fn assert_report_injection(report: &str) {
+        let scope = Scope::with_project(Project::with_name("test"));
+
+        let actual = task_args_for(&[report], Some(&scope));
+        assert_eq!(vec![s!("project:test"), report.to_string()], actual);
+
+        let actual = task_args_for(&["foo:bar", report], Some(&scope));
+        assert_eq!(
+            vec![s!("project:test"), s!("foo:bar"), report.to_string()],
+            actual
+        );
+
+        let actual = task_args_for(&["project:other", report], Some(&scope));
+        assert_eq!(vec![s!("project:other"), report.to_string()], actual);
+
+        let actual = task_args_for(&[report], None);
+        assert_eq!(vec![report.to_string()], actual);
+    }
+
+    #[test]
+    fn test_scope_flags() {
+        let config = Config::default();
+        let args = Cli::parse_from(["taskhelper", "--project", "website", "list"]);
+        let scope = scope_for(&args, &config).unwrap().unwrap();
+        assert_eq!(vec![s!("project:website")], scope.tokens());
+        let args = Cli::parse_from(["taskhelper", "--no-project", "list"]);
+        assert_eq!(None, scope_for(&args, &config).unwrap());
+        let args = Cli::parse_from(["taskhelper", "-g", "done", "12"]);
+        assert_eq!(None, scope_for(&args, &config).unwrap());
+        let args = Cli::parse_from(["taskhelper", "projects", "--global"]);
+        assert_eq!(None, scope_for(&args, &config).unwrap());
+        let args = Cli::parse_from(["taskhelper", "list"]);
+        let scope = scope_with(&args, &config, Some(s!("docs")))
+            .unwrap()
+            .unwrap();
+        assert_eq!(vec![s!("project:docs")], scope.tokens());
+        assert_eq!(None, scope_with(&args, &config, Some(s!(""))).unwrap());
+        let args = Cli::parse_from(["taskhelper", "--project", "website", "list"]);
+        let scope = scope_with(&args, &config, Some(s!("docs")))
+            .unwrap()
+            .unwrap();
+        assert_eq!(vec![s!("project:website")], scope.tokens());
+        assert!(
+            Cli::try_parse_from(["taskhelper", "--project", "a", "--no-project", "list"]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_apply_yes() {
+        let argv = ["taskhelper", "+old", "delete", "-y", "rc.confirmation=on"];
+        let (argv, overrides) = split_rc_overrides(argv.map(String::from).to_vec());
+        let mut cli = Cli::parse_from(argv);
+        cli.overrides = overrides;
+        apply_yes(&mut cli, &Config::default()).unwrap();
+        assert_eq!(
+            vec![
+                s!("rc.confirmation=off"),
+                s!("rc.recurrence.confirmation=yes"),
+                s!("rc.confirmation=on")
+            ],
+            cli.overrides
+        );
+
+        let config: Config = toml::from_str(r#"yes = ["confirmation=off"]"#).unwrap();
+        let mut cli = Cli::parse_from(["taskhelper", "--yes", "purge"]);
+        assert!(apply_yes(&mut cli, &config).is_err());
+    }
+
+    #[test]
+    fn test_apply_shortcut() {
+        let mut cli = Cli::parse_from(["taskhelper", "today"]);
+        apply_shortcut(&mut cli).unwrap();
+        assert!(matches!(cli.command, Some(Commands::Next)));
+        assert_eq!(
+            "( due.before:tomorrow or scheduled.before:tomorrow )",
+            cli.filter.unwrap().args().join(" ")
+        );
+
+        let mut cli = Cli::parse_from(["taskhelper", "+bug", "week"]);
+        apply_shortcut(&mut cli).unwrap();
+        assert_eq!(
+            "( due.before:eow or scheduled.before:eow ) +bug",
+            cli.filter.unwrap().args().join(" ")
+        );
+
+        let mut cli = Cli::parse_from(["taskhelper", "due.before:eom", "week"]);
+        apply_shortcut(&mut cli).unwrap();
+        assert_eq!("due.before:eom", cli.filter.unwrap().args().join(" "));
+    }
+
+    #[test]
+    fn test_standalone_command() {
+        let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        assert!(matches!(
+            standalone_command(&args(&["taskhelper", "doctor"])),
+            Some(Commands::Doctor)
+        ));
+        assert!(matches!(
+            standalone_command(&args(&["taskhelper", "--plain", "doctor"])),
+            Some(Commands::Doctor)
+        ));
+        assert!(standalone_command(&args(&["taskhelper", "list"])).is_none());
+        assert!(standalone_command(&args(&["taskhelper", "+doctor", "list"])).is_none());
+    }
+
+    #[test]
+    fn test_apply_default_filters() {
+        let config: Config = toml::from_str(
+            r#"
+            [filters]
+            done = "status:completed"
+            [defaults]
+            list = "status:pending"
+            next = "@done"
+            "#,
+        )
+        .unwrap();
+        let mut cli = Cli::parse_from(["taskhelper", "@done", "list"]);
+        apply_named_filters(&mut cli, &config).unwrap();
+        apply_default_filters(&mut cli, &config).unwrap();
+        assert_eq!("status:completed", cli.filter.unwrap().to_string());
+
+        let mut cli = Cli::parse_from(["taskhelper", "next"]);
+        apply_named_filters(&mut cli, &config).unwrap();
+        apply_default_filters(&mut cli, &config).unwrap();
+        assert_eq!("status:completed", cli.filter.unwrap().to_string());
+    }
+
+    #[test]
+    fn test_apply_default_report() {
+        let config = Config::default();
+        let mut cli = Cli::parse_from(["taskhelper", "+bug"]);
+        apply_default_report(&mut cli, &config).unwrap();
+        assert!(matches!(cli.command, Some(Commands::Next)));
+
+        let mut cli = Cli::parse_from(["taskhelper", "1"]);
+        apply_default_report(&mut cli, &config).unwrap();
+        assert!(cli.command.is_none());
+
+        let config: Config = toml::from_str(r#"report = "list""#).unwrap();
+        let mut cli = Cli::parse_from(["taskhelper"]);
+        apply_default_report(&mut cli, &config).unwrap();
+        assert!(matches!(cli.command, Some(Commands::List)));
+
+        let config: Config = toml::from_str(r#"report = "doctor""#).unwrap();
+        assert!(apply_default_report(&mut cli, &config).is_ok());
+        let mut cli = Cli::parse_from(["taskhelper"]);
+        assert!(apply_default_report(&mut cli, &config).is_err());
+    }
+
+    #[test]
+    fn test_apply_template() {
+        let config: Config = toml::from_str(
+            r#"
+            [template.bug]
+            tags = ["bug"]
+            priority = "H"
+            due = "eow"
+            "#,
+        )
+        .unwrap();
+        let argv = [
+            "taskhelper",
+            "add",
+            "--template",
+            "bug",
+            "crash",
+            "priority:L",
+        ];
+        let mut cli = Cli::parse_from(argv);
+        apply_template(&mut cli, &config).unwrap();
+        let actual = build_task_args(&cli, None).unwrap().args;
+        assert_eq!(
+            vec![
+                s!("add"),
+                s!("+bug"),
+                s!("due:eow"),
+                s!("crash"),
+                s!("priority:L")
+            ],
+            actual
+        );
+
+        let mut cli = Cli::parse_from(["taskhelper", "add", "--template", "nope", "crash"]);
+        assert!(apply_template(&mut cli, &config).is_err());
+    }
+
+    #[test]
+    fn test_location_annotation() {
+        assert_eq!(
+            "Commit abc1234 of git@github.com:me/repo.git, src/lib.rs:42",
+            location_annotation(
+                "abc1234",
+                Some("git@github.com:me/repo.git"),
+                Some("src/lib.rs:42")
+            )
+        );
+        assert_eq!("Commit abc1234", location_annotation("abc1234", None, None));
+    }
+
+    #[test]
+    fn test_rc_overrides() {
+        let scope = Scope::with_project(Project::with_name("test"));
+        let argv = ["taskhelper", "12", "done", "rc.confirmation=off"];
+        let (argv, overrides) = split_rc_overrides(argv.map(String::from).to_vec());
+        let mut cli = Cli::parse_from(argv);
+        cli.overrides = overrides;
+        let actual = build_task_args(&cli, Some(&scope)).unwrap().args;
+        assert_eq!(
+            vec![s!("rc.confirmation=off"), s!("12"), s!("done")],
+            actual
+        );
+    }
+
+    #[test]
+    fn test_recurrence_needs_due() {
+        let cli = Cli::try_parse_from(["taskhelper", "add", "pay", "recur:monthly"]).unwrap();
+        assert!(build_task_args(&cli, None).is_err());
+        let argv = ["taskhelper", "add", "pay", "recur:monthly", "due:eom"];
+        let cli = Cli::try_parse_from(argv).unwrap();
+        assert!(build_task_args(&cli, None).is_ok());
+    }
+
+    #[test]
+    fn test_dependency_ids() {
+        let cli = Cli::parse_from(["taskhelper", "add", "foo", "depends:3,7,-8,8d3f2a1c"]);
+        assert_eq!(vec![3, 7], dependency_ids(cli.command.as_ref().unwrap()));
+    }
+
+    #[test]
+    fn test_leading_filter_scoped() {
+        let scope = Scope::with_project(Project::with_name("test"));
+        let actual = task_args_for(&["+urgent", "start"], Some(&scope));
+        assert_eq!(vec![s!("project:test"), s!("+urgent"), s!("start")], actual);
+        let actual = task_args_for(&["+urgent project:foo", "done"], Some(&scope));
+        assert_eq!(vec![s!("+urgent"), s!("project:foo"), s!("done")], actual);
+        let actual = task_args_for(&["annotate", "note"], Some(&scope));
+        assert_eq!(vec![s!("annotate"), s!("note"), s!("project:test")], actual);
+        let actual = task_args_for(&["+urgent", "modify", "project:other"], Some(&scope));
+        assert_eq!(
+            vec![
+                s!("project:test"),
+                s!("+urgent"),
+                s!("modify"),
+                s!("project:other")
+            ],
+            actual
+        );
+    }
+
+    #[test]
+    fn test_report_export() {
+        let scope = Scope::with_project(Project::with_name("test"));
+        let mut cli = Cli::parse_from(["taskhelper", "--output", "json", "+bug", "next"]);
+        assert_eq!(Some(s!("next")), report_export(&mut cli).unwrap());
+        assert_eq!(
+            vec![s!("project:test"), s!("+bug"), s!("export"), s!("next")],
+            build_task_args(&cli, Some(&scope)).unwrap().args
+        );
+        let mut cli = Cli::parse_from(["taskhelper", "--output", "json", "add", "foo"]);
+        assert!(report_export(&mut cli).is_err());
+        let mut cli = Cli::parse_from(["taskhelper", "add", "foo"]);
+        assert_eq!(None, report_export(&mut cli).unwrap());
+        let mut cli = Cli::parse_from(["taskhelper", "--output", "table", "export"]);
+        assert!(report_export(&mut cli).is_err());
+    }
+
+    #[test]
+    fn test_export() {
+        let scope = Scope::with_project(Project::with_name("test"));
+        let actual = task_args_for(&["+bug", "export", "next", "-o", "bugs.json"], Some(&scope));
+        assert_eq!(
+            vec![s!("project:test"), s!("+bug"), s!("export"), s!("next")],
+            actual
+        );
+    }
+
+    #[test]
+    fn test_named_tasks_not_scoped() {
+        let scope = Scope::with_project(Project::with_name("test"));
+        let actual = task_args_for(&["12", "done"], Some(&scope));
+        assert_eq!(vec![s!("12"), s!("done")], actual);
+        let actual = task_args_for(&["1-3", "modify", "priority:H"], Some(&scope));
+        assert_eq!(vec![s!("1-3"), s!("modify"), s!("priority:H")], actual);
+    }
+
+    #[test]
+    fn test_list_injection() {
+        assert_report_injection("list");
+    }
+
+    #[test]
+    fn test_count_injection() {
+        assert_report_injection("count");
+    }
+
+    #[test]
+    fn test_ids_injection() {
+        assert_report_injection("ids");
+    }
+
+    #[test]
+    fn test_uuids_injection() {
+        assert_report_injection("uuids");
+    }
+
+    #[test]
+    fn test_export_injection() {
+        assert_report_injection("export");
+    }
+
+    #[test]
+    fn test_export_report() {
+        let scope = Scope::with_project(Project::with_name("test"));
+        let actual = task_args_for(&["foo:bar", "export", "next"], Some(&scope));
+        let expected = vec![s!("project:test"), s!("foo:bar"), s!("export"), s!("next")];
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_injection_note() {
+        let scope = Scope::with_project(Project::with_name("test"));
+        let cli = Cli::parse_from(["taskhelper", "list"]);
+        let invocation = build_task_args(&cli, Some(&scope)).unwrap();
+        assert_eq!(
+            Some(s!("+ injected project:test as first filter")),
+            invocation.injected
+        );
+
+        let cli = Cli::parse_from(["taskhelper", "project:other", "list"]);
+        let invocation = build_task_args(&cli, Some(&scope)).unwrap();
+        assert_eq!(None, invocation.injected);
+    }
+
+    #[test]
+    fn test_empty_project_opt_out() {
+        let scope = Scope::with_project(Project::with_name("test"));
+
+        let actual = task_args_for(&["project:", "list"], Some(&scope));
+        assert_eq!(vec![s!("project:"), s!("list")], actual);
+
+        let actual = task_args_for(&["add", "foo", "project:"], Some(&scope));
+        assert_eq!(vec![s!("add"), s!("foo"), s!("project:")], actual);
+    }
+
+    #[test]
+    fn test_subdir_tag_injection() {
+        let mut scope = Scope::with_project(Project::with_name("test"));
+        scope.add_tag("frontend");
+
+        let actual = task_args_for(&["list"], Some(&scope));
+        let expected = vec![s!("project:test"), s!("+frontend"), s!("list")];
+        assert_eq!(expected, actual);
+
+        let actual = task_args_for(&["add", "foo"], Some(&scope));
+        let expected = vec![s!("add"), s!("foo"), s!("project:test"), s!("+frontend")];
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_sandbox_args() {
+        let cli = Cli::parse_from(["taskhelper", "sandbox", "+work", "list", "rc.verbose=off"]);
+        let Some(Commands::Sandbox { seed, args }) = cli.command else {
+            panic!("Expected sandbox subcommand");
+        };
+        assert_eq!(None, seed);
+        assert_eq!(vec![s!("+work"), s!("list"), s!("rc.verbose=off")], args);
+    }
+
+    #[test]
+    fn test_context_injection() {
+        let scope = Scope::with_project(Project::with_name("test"));
+        let actual = task_args_for(&["--inject", "context", "count"], Some(&scope));
+        let expected = vec![
+            s!("rc.context.taskhelper.read=project:test"),
+            s!("rc.context.taskhelper.write=project:test"),
+            s!("rc.context=taskhelper"),
+            s!("count"),
+        ];
+        assert_eq!(expected, actual);
+    }
+}
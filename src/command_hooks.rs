@@ -0,0 +1,135 @@
+use crate::args::{Cli, Commands};
+use crate::config::HooksConfig;
+use crate::detect::Scope;
+use color_eyre::eyre::bail;
+use color_eyre::Result;
+use log::debug;
+use std::fmt;
+use std::process::Command;
+
+/// When a hook runs, relative to taskwarrior
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Pre,
+    Post,
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let repr = match self {
+            Stage::Pre => "pre",
+            Stage::Post => "post",
+        };
+        write!(f, "{}", repr)
+    }
+}
+
+/// Environment variables describing the command, exported to its hooks
+pub fn environment(
+    args: &Cli,
+    scope: Option<&Scope>,
+    task_args: &[String],
+) -> Vec<(&'static str, String)> {
+    let command = args.command.as_ref();
+    let modifiers: Vec<String> = command
+        .map(|command| command.modifiers().iter().map(|m| m.to_string()).collect())
+        .unwrap_or_default();
+    vec![
+        (
+            "TASKHELPER_COMMAND",
+            command
+                .map(|command| command.to_string())
+                .unwrap_or_default(),
+        ),
+        (
+            "TASKHELPER_FILTER",
+            args.filter
+                .as_ref()
+                .map(|filter| filter.to_string())
+                .unwrap_or_default(),
+        ),
+        ("TASKHELPER_MODIFIERS", modifiers.join(" ")),
+        // Also picked up by taskhelper run from the hook, which then scopes to the same project
+        (
+            "TASKHELPER_PROJECT",
+            scope
+                .map(|scope| scope.project().name().to_string())
+                .unwrap_or_default(),
+        ),
+        ("TASKHELPER_ARGS", task_args.join(" ")),
+    ]
+}
+
+/// Run the `stage` hook configured for `command`, if there is one, with `env` exported to it.
+/// Fails if the hook does
+pub fn run_hook(
+    config: &HooksConfig,
+    stage: Stage,
+    command: &Commands,
+    env: &[(&str, String)],
+) -> Result<()> {
+    let hooks = match stage {
+        Stage::Pre => &config.pre,
+        Stage::Post => &config.post,
+    };
+    let name = command.to_string();
+    let Some(hook) = hooks.get(&name) else {
+        return Ok(());
+    };
+    debug!("Running {} hook for '{}': {}", stage, name, hook);
+    let status = Command::new("sh")
+        .args(["-c", hook])
+        .envs(env.iter().map(|(key, value)| (key, value)))
+        .status()?;
+    if !status.success() {
+        bail!("The {} hook for '{}' failed with {}", stage, name, status);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task_args::project::Project;
+    use clap::Parser;
+    use pretty_assertions::assert_eq;
+    use s_string::s;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_environment() {
+        let args = Cli::parse_from(["taskhelper", "+bug", "modify", "priority:H"]);
+        let scope = Scope::with_project(Project::with_name("repo"));
+        let task_args = vec![
+            s!("+bug"),
+            s!("project:repo"),
+            s!("modify"),
+            s!("priority:H"),
+        ];
+        let expected = vec![
+            ("TASKHELPER_COMMAND", s!("modify")),
+            ("TASKHELPER_FILTER", s!("+bug")),
+            ("TASKHELPER_MODIFIERS", s!("priority:H")),
+            ("TASKHELPER_PROJECT", s!("repo")),
+            ("TASKHELPER_ARGS", s!("+bug project:repo modify priority:H")),
+        ];
+        assert_eq!(expected, environment(&args, Some(&scope), &task_args));
+    }
+
+    #[test]
+    fn test_run_hook() {
+        let config = HooksConfig {
+            pre: BTreeMap::from([(s!("done"), s!("test \"$TASKHELPER_COMMAND\" = done"))]),
+            post: BTreeMap::from([(s!("done"), s!("exit 1"))]),
+        };
+        let command = Commands::Done { mods: Vec::new() };
+        let env = [("TASKHELPER_COMMAND", s!("done"))];
+        assert!(run_hook(&config, Stage::Pre, &command, &env).is_ok());
+        assert!(run_hook(&config, Stage::Post, &command, &env).is_err());
+        let command = Commands::Add {
+            template: None,
+            mods: Vec::new(),
+        };
+        assert!(run_hook(&config, Stage::Post, &command, &env).is_ok());
+    }
+}
@@ -0,0 +1,342 @@
+use crate::args::{Cli, Shell};
+use crate::config::project_dirs;
+use crate::recall::Suggestions;
+use crate::taskwarrior::Taskwarrior;
+use crate::NAME;
+use clap::CommandFactory;
+use color_eyre::Result;
+use log::debug;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// How long values queried from taskwarrior are reused for
+const CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// Values completions are drawn from
+#[derive(Debug, Clone, Copy)]
+pub enum Source {
+    Projects,
+    Tags,
+    Contexts,
+    Ids,
+}
+
+impl Source {
+    fn name(&self) -> &'static str {
+        match self {
+            Source::Projects => "projects",
+            Source::Tags => "tags",
+            Source::Contexts => "contexts",
+            Source::Ids => "ids",
+        }
+    }
+
+    fn task_args(&self) -> &'static [&'static str] {
+        match self {
+            Source::Projects => &["_projects"],
+            Source::Tags => &["_tags"],
+            Source::Contexts => &["_context"],
+            Source::Ids => &["status:pending", "_ids"],
+        }
+    }
+}
+
+fn cache_path(source: Source) -> Option<PathBuf> {
+    project_dirs().map(|dirs| dirs.cache_dir().join("completions").join(source.name()))
+}
+
+fn read_cache(path: &PathBuf) -> Option<Vec<String>> {
+    let age = fs::metadata(path).ok()?.modified().ok()?.elapsed().ok()?;
+    if age > CACHE_TTL {
+        return None;
+    }
+    let contents = fs::read_to_string(path).ok()?;
+    Some(contents.lines().map(String::from).collect())
+}
+
+/// Values for `source`, straight from taskwarrior
+fn query(task: &Taskwarrior, source: Source) -> Result<Vec<String>> {
+    debug!("Querying taskwarrior for {}", source.name());
+    let output = task.command(source.task_args()).output()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(stdout
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(String::from)
+        .collect())
+}
+
+/// Values for `source`, from the cache if it's fresh or else from taskwarrior
+fn values(task: &Taskwarrior, source: Source) -> Result<Vec<String>> {
+    let path = cache_path(source);
+    if let Some(values) = path.as_ref().and_then(read_cache) {
+        return Ok(values);
+    }
+
+    let values = query(task, source)?;
+    if let Some(path) = path {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, values.join("\n"))?;
+    }
+    Ok(values)
+}
+
+fn subcommands() -> Vec<String> {
+    Cli::command()
+        .get_subcommands()
+        .filter(|sub| !sub.is_hide_set())
+        .map(|sub| sub.get_name().to_string())
+        .collect()
+}
+
+/// Candidates for the last word of `line`, given `lookup` to fetch values from taskwarrior. They're
+/// ranked by what the command history suggests
+fn candidates<F>(line: &str, mut lookup: F, suggestions: &Suggestions) -> Result<Vec<String>>
+where
+    F: FnMut(Source) -> Result<Vec<String>>,
+{
+    let mut words: Vec<&str> = line.split_whitespace().collect();
+    let current = if line.ends_with(char::is_whitespace) {
+        ""
+    } else {
+        words.pop().unwrap_or("")
+    };
+    // The program name
+    if !words.is_empty() {
+        words.remove(0);
+    }
+    let subcommands = subcommands();
+
+    let candidates = if let Some((attribute, _)) = current
+        .split_once(':')
+        .filter(|(attribute, _)| *attribute == "project" || *attribute == "proj")
+    {
+        lookup(Source::Projects)?
+            .into_iter()
+            .map(|project| format!("{}:{}", attribute, project))
+            .collect()
+    } else if let Some(sign) = current.chars().next().filter(|c| *c == '+' || *c == '-') {
+        lookup(Source::Tags)?
+            .into_iter()
+            .map(|tag| format!("{}{}", sign, tag))
+            .collect()
+    } else if words.last() == Some(&"context") {
+        lookup(Source::Contexts)?
+    } else if !current.is_empty() && current.chars().all(|c| c.is_ascii_digit()) {
+        lookup(Source::Ids)?
+    } else if words.is_empty() && current.is_empty() {
+        // Offer to carry on with the tasks the last command worked on
+        let mut subcommands = subcommands;
+        suggestions.rank(&mut subcommands);
+        let mut candidates = suggestions.ids.clone();
+        candidates.extend(subcommands);
+        return Ok(candidates);
+    } else if !words
+        .iter()
+        .any(|word| subcommands.iter().any(|s| s == word))
+    {
+        subcommands
+    } else {
+        Vec::new()
+    };
+
+    let mut candidates: Vec<String> = candidates
+        .into_iter()
+        .filter(|candidate| candidate.starts_with(current))
+        .collect();
+    suggestions.rank(&mut candidates);
+    Ok(candidates)
+}
+
+/// Print completion candidates for the last word of `line`, one per line
+pub fn complete(
+    task: &Taskwarrior,
+    shell: Shell,
+    line: &str,
+    suggestions: &Suggestions,
+) -> Result<()> {
+    for candidate in candidates(line, |source| values(task, source), suggestions)? {
+        // Bash splits words on ':', so it only wants what comes after the last one
+        let candidate = match shell {
+            Shell::Bash => candidate
+                .rsplit_once(':')
+                .map(|(_, value)| value.to_string())
+                .unwrap_or(candidate),
+            Shell::Zsh | Shell::Fish => candidate,
+        };
+        println!("{}", candidate);
+    }
+    Ok(())
+}
+
+/// Completion candidates for the last word of `line`, a command line without the program name
+pub fn line_candidates(task: &Taskwarrior, line: &str, suggestions: &Suggestions) -> Vec<String> {
+    let line = format!("{} {}", NAME, line);
+    candidates(&line, |source| values(task, source), suggestions).unwrap_or_default()
+}
+
+/// Print the projects, tags or pending task IDs, one per line, for shell snippets completing them
+pub fn print_values(task: &Taskwarrior, source: Source) -> Result<()> {
+    for value in query(task, source)? {
+        println!("{}", value);
+    }
+    Ok(())
+}
+
+/// Completions clap generates from the command line definition, covering flags and the arguments
+/// of nested subcommands. The completion function is renamed to `_<name>_clap` and not
+/// registered, so the dynamic completions can fall back to it
+fn clap_script(shell: Shell) -> String {
+    let generator = match shell {
+        Shell::Bash => clap_complete::Shell::Bash,
+        Shell::Zsh => clap_complete::Shell::Zsh,
+        Shell::Fish => clap_complete::Shell::Fish,
+    };
+    let mut buffer = Vec::new();
+    clap_complete::generate(generator, &mut Cli::command(), NAME, &mut buffer);
+    let script = String::from_utf8_lossy(&buffer);
+
+    let registration = match shell {
+        Shell::Bash => "\nif [[ \"${BASH_VERSINFO[0]}\"",
+        Shell::Zsh => "\nif [ \"$funcstack[1]\"",
+        Shell::Fish => return script.into_owned(),
+    };
+    let script = match script.rfind(registration) {
+        Some(end) => &script[..end + 1],
+        None => &script,
+    };
+    script
+        .lines()
+        .filter(|line| !line.starts_with("#compdef"))
+        .map(|line| match line == format!("_{}() {{", NAME) {
+            true => format!("_{}_clap() {{\n", NAME),
+            false => format!("{}\n", line),
+        })
+        .collect()
+}
+
+/// Script registering completions for `shell`. Values are completed dynamically, falling back to
+/// clap's completions for flags and subcommand arguments
+pub fn script(shell: Shell) -> String {
+    let dynamic = match shell {
+        Shell::Bash => format!(
+            r#"_{name}() {{
+    local IFS=$'\n'
+    COMPREPLY=($({name} _complete bash "${{COMP_LINE:0:$COMP_POINT}}"))
+    if [ ${{#COMPREPLY[@]}} -eq 0 ]; then
+        IFS=$' \t\n'
+        _{name}_clap "$@"
+    fi
+}}
+complete -o nosort -F _{name} {name}
+"#,
+            name = NAME
+        ),
+        Shell::Zsh => format!(
+            r#"_{name}() {{
+    local -a candidates
+    candidates=(${{(f)"$({name} _complete zsh "${{BUFFER[1,$CURSOR]}}")"}})
+    if (( ${{#candidates}} )); then
+        compadd -Q -- $candidates
+    else
+        _{name}_clap "$@"
+    fi
+}}
+compdef _{name} {name}
+"#,
+            name = NAME
+        ),
+        Shell::Fish => format!(
+            "complete -c {name} -f -a '({name} _complete fish (commandline -cp))'\n",
+            name = NAME
+        ),
+    };
+    let header = match shell {
+        Shell::Zsh => format!("#compdef {}\n", NAME),
+        Shell::Bash | Shell::Fish => String::new(),
+    };
+    format!("{}{}{}", header, clap_script(shell), dynamic)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use s_string::s;
+
+    fn lookup(source: Source) -> Result<Vec<String>> {
+        Ok(match source {
+            Source::Projects => vec![s!("home"), s!("work")],
+            Source::Tags => vec![s!("bug"), s!("next")],
+            Source::Contexts => vec![s!("evening")],
+            Source::Ids => vec![s!("1"), s!("12"), s!("2")],
+        })
+    }
+
+    #[test]
+    fn test_complete_project() {
+        let actual = candidates("taskhelper project:w", lookup, &Suggestions::default()).unwrap();
+        assert_eq!(vec![s!("project:work")], actual);
+    }
+
+    #[test]
+    fn test_complete_tag() {
+        let actual = candidates("taskhelper -", lookup, &Suggestions::default()).unwrap();
+        assert_eq!(vec![s!("-bug"), s!("-next")], actual);
+    }
+
+    #[test]
+    fn test_complete_context() {
+        let actual = candidates("taskhelper context ", lookup, &Suggestions::default()).unwrap();
+        assert_eq!(vec![s!("evening")], actual);
+    }
+
+    #[test]
+    fn test_complete_id() {
+        let actual = candidates("taskhelper done 1", lookup, &Suggestions::default()).unwrap();
+        assert_eq!(vec![s!("1"), s!("12")], actual);
+    }
+
+    #[test]
+    fn test_complete_subcommand() {
+        let actual = candidates(
+            "taskhelper project:home bur",
+            lookup,
+            &Suggestions::default(),
+        )
+        .unwrap();
+        assert_eq!(vec![s!("burndown")], actual);
+        let actual = candidates("taskhelper list ", lookup, &Suggestions::default()).unwrap();
+        assert!(actual.is_empty());
+    }
+
+    #[test]
+    fn test_script() {
+        let bash = script(Shell::Bash);
+        assert!(bash.contains("_taskhelper_clap() {\n"));
+        assert!(bash.contains("--project"));
+        assert_eq!(1, bash.matches("complete -o nosort -F _taskhelper").count());
+        assert!(!bash.contains("complete -F _taskhelper -o"));
+
+        let zsh = script(Shell::Zsh);
+        assert!(zsh.starts_with("#compdef taskhelper\n"));
+        assert_eq!(1, zsh.matches("#compdef").count());
+        assert!(zsh.contains("_taskhelper_clap() {\n"));
+        assert!(!zsh.contains("funcstack"));
+    }
+
+    #[test]
+    fn test_complete_suggested() {
+        let mut suggestions = Suggestions::default();
+        suggestions.ids = vec![s!("3")];
+        suggestions.subcommands = vec!["stop", "done"];
+        let actual = candidates("taskhelper ", lookup, &suggestions).unwrap();
+        assert_eq!(vec![s!("3"), s!("stop"), s!("done")], actual[..3]);
+        let actual = candidates("taskhelper 3 d", lookup, &suggestions).unwrap();
+        assert_eq!(s!("done"), actual[0]);
+    }
+}
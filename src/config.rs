@@ -0,0 +1,535 @@
+use crate::args::Injection;
+use crate::SUPPORTED_TASKWARRIOR_VERSIONS;
+use color_eyre::eyre::bail;
+use color_eyre::Result;
+use directories::ProjectDirs;
+use log::{debug, LevelFilter};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const CONFIG_FILE: &str = "config.toml";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Taskwarrior binary used instead of searching the $PATH
+    pub task: Option<PathBuf>,
+    /// Semver range of taskwarrior versions considered compatible, like `>=3.0, <4`
+    pub supported_versions: Option<String>,
+    /// taskrc used instead of taskwarrior's default
+    pub taskrc: Option<PathBuf>,
+    /// Task data directory used instead of taskwarrior's default
+    pub taskdata: Option<PathBuf>,
+    /// rc overrides passed by `--yes`
+    pub yes: Option<Vec<String>>,
+    /// Report run when no subcommand is given, `next` by default
+    pub report: Option<String>,
+    /// Named filters, usable on the command line as `@name`
+    pub filters: BTreeMap<String, String>,
+    /// Filters added to a subcommand unless the user already filters on the same attribute or tag
+    pub defaults: BTreeMap<String, String>,
+    /// Tags and attributes by template name, for `add --template`
+    pub template: BTreeMap<String, TemplateConfig>,
+    /// Commands by name, expanded to the filters, subcommand and modifiers they're defined as
+    /// when the name is used in place of a subcommand
+    pub aliases: BTreeMap<String, String>,
+    pub project: ProjectConfig,
+    /// Additional task databases, by name, included in merged views
+    pub databases: BTreeMap<String, PathBuf>,
+    /// Run taskwarrior on another machine over ssh
+    pub remote: Option<RemoteConfig>,
+    /// Run taskwarrior in a container
+    pub container: Option<ContainerConfig>,
+    pub sync: SyncConfig,
+    pub hooks: HooksConfig,
+    pub priority: PriorityConfig,
+    /// Language of taskhelper's own messages, e.g. `de`. Defaults to the locale from the
+    /// environment
+    pub locale: Option<String>,
+    pub links: LinksConfig,
+    pub table: TableConfig,
+    pub log: LogConfig,
+    pub toolchain: ToolchainConfig,
+    pub timew: TimewConfig,
+    pub notify: NotifyConfig,
+    pub gitlab: GitlabConfig,
+    pub jira: JiraConfig,
+}
+
+/// When the log file is rotated
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Rotation {
+    /// Once it grows past `max_size`
+    #[default]
+    Size,
+    /// On the first run of a new day, or once it grows past `max_size`
+    Daily,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    /// Write logs to a file under the state directory
+    pub file: bool,
+    /// Lowest level written to the console, unless overridden by `RUST_LOG`
+    pub console_level: LevelFilter,
+    /// Lowest level written to the log file
+    pub file_level: LevelFilter,
+    pub rotation: Rotation,
+    /// Size in bytes the log file may grow to before it is rotated
+    pub max_size: u64,
+    /// Rotated log files to keep
+    pub keep: usize,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        LogConfig {
+            file: true,
+            console_level: LevelFilter::Error,
+            file_level: LevelFilter::Debug,
+            rotation: Rotation::Size,
+            max_size: 1024 * 1024,
+            keep: 3,
+        }
+    }
+}
+
+/// When native output contains hyperlinks
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HyperlinkMode {
+    /// When the terminal is known to support them
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LinksConfig {
+    pub mode: HyperlinkMode,
+    /// URL tasks link to, with `{uuid}` replaced by the task's UUID
+    pub task_url: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TableConfig {
+    /// Attributes shown by `--output table`, in order. Defaults to id, project, tags, due,
+    /// description and urgency
+    pub columns: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ToolchainConfig {
+    /// URL of a `.tar.gz` or `.tar.xz` archive with a prebuilt taskwarrior, with `{version}` and
+    /// `{target}` replaced by the version to install and this platform's target triple. A
+    /// checksum in `sha256sum` format is fetched from the same URL with `.sha256` appended
+    pub url: Option<String>,
+}
+
+/// Starting point for tasks added with `add --template`
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct TemplateConfig {
+    /// Tags added to the task
+    pub tags: Vec<String>,
+    /// Attributes set on the task, like `priority = "H"`
+    #[serde(flatten)]
+    pub attributes: BTreeMap<String, String>,
+}
+
+impl TemplateConfig {
+    /// The template as modifiers, like `+bug` and `priority:H`
+    pub fn modifiers(&self) -> Vec<String> {
+        let tags = self.tags.iter().map(|tag| format!("+{}", tag));
+        let attributes = self
+            .attributes
+            .iter()
+            .map(|(name, value)| format!("{}:{}", name, value));
+        tags.chain(attributes).collect()
+    }
+}
+
+/// Shell commands run around taskwarrior, by subcommand
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HooksConfig {
+    /// Run before taskwarrior. Taskwarrior isn't run if one fails
+    pub pre: BTreeMap<String, String>,
+    /// Run after taskwarrior, whether or not it succeeded
+    pub post: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TimewConfig {
+    /// Track time in timewarrior when tasks are started and stopped
+    pub track: bool,
+    /// Timewarrior binary used instead of searching the $PATH
+    pub bin: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NotifyConfig {
+    /// notify-send compatible binary used instead of searching the $PATH
+    pub bin: Option<PathBuf>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GitlabConfig {
+    /// Base URL of the GitLab instance, by default the host of the `origin` remote
+    pub url: Option<String>,
+    /// Environment variable holding the access token
+    pub token_var: String,
+}
+
+impl Default for GitlabConfig {
+    fn default() -> Self {
+        GitlabConfig {
+            url: None,
+            token_var: String::from("GITLAB_TOKEN"),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct JiraConfig {
+    /// Base URL of the Jira site
+    pub url: Option<String>,
+    /// Account the token belongs to, for Jira Cloud. Without it the token is used as a personal
+    /// access token
+    pub user: Option<String>,
+    /// Environment variable holding the API or personal access token
+    pub token_var: String,
+    /// JQL selecting the issues imported when the command line doesn't give one
+    pub jql: Option<String>,
+}
+
+impl Default for JiraConfig {
+    fn default() -> Self {
+        JiraConfig {
+            url: None,
+            user: None,
+            token_var: String::from("JIRA_TOKEN"),
+            jql: None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PriorityConfig {
+    /// Niceness for low priority commands
+    pub nice: i32,
+    /// Subcommands that always run at low priority
+    pub commands: Vec<String>,
+}
+
+impl Default for PriorityConfig {
+    fn default() -> Self {
+        PriorityConfig {
+            nice: 10,
+            commands: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SyncConfig {
+    /// Synchronize after every command that changes task data
+    pub auto: bool,
+    /// Times to retry a sync that failed with a transient error
+    pub retries: u32,
+    /// Delay before the first retry, doubled for each one after it
+    pub backoff_ms: u64,
+}
+
+impl Default for SyncConfig {
+    fn default() -> Self {
+        SyncConfig {
+            auto: false,
+            retries: 3,
+            backoff_ms: 1000,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ContainerConfig {
+    /// Container runtime, e.g. podman or docker
+    #[serde(default = "default_container_runtime")]
+    pub runtime: String,
+    /// Running container with taskwarrior installed, to exec taskwarrior in
+    pub name: String,
+    /// Bind mounts of the container, from the path on this machine to the path in the container.
+    /// Must include the task data directory, and the taskrc if there is one
+    #[serde(default)]
+    pub mounts: BTreeMap<PathBuf, PathBuf>,
+    /// Taskwarrior in the container
+    #[serde(default = "default_remote_task")]
+    pub task: String,
+}
+
+fn default_container_runtime() -> String {
+    String::from("podman")
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RemoteConfig {
+    /// Host to ssh to
+    pub host: String,
+    /// Taskwarrior on the remote host
+    #[serde(default = "default_remote_task")]
+    pub task: String,
+}
+
+fn default_remote_task() -> String {
+    String::from("task")
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProjectConfig {
+    /// Scope commands to the project detected from the working directory
+    pub auto: bool,
+    /// How the detected project is passed to taskwarrior, unless `--inject` is given
+    pub injection: Option<Injection>,
+    /// Files or directories marking a project root. The closest directory above the working
+    /// directory containing one of them is the root
+    pub markers: Vec<String>,
+    /// Tag tasks with the top level subdirectory of the project root the command is run from
+    pub subdir_tag: bool,
+    /// Tag tasks with the git branch checked out at the project root, like `+feature-login` on
+    /// `feature/login`
+    pub branch_tag: bool,
+    /// Add the path from the project root to the working directory to the project name, as a
+    /// hierarchy like `repo.frontend.components`
+    pub hierarchy: bool,
+    /// Print a note whenever the detected project is injected into the command
+    pub audit: bool,
+    /// Give each detected project its own task database under the data directory
+    pub isolate: bool,
+}
+
+impl Default for ProjectConfig {
+    fn default() -> Self {
+        ProjectConfig {
+            auto: true,
+            injection: None,
+            markers: [".git", ".hg", ".jj", ".svn", ".project"]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            subdir_tag: false,
+            branch_tag: false,
+            hierarchy: false,
+            audit: false,
+            isolate: false,
+        }
+    }
+}
+
+/// Per-repository settings from a `.taskhelper.toml` at the project root
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RepoConfig {
+    /// Project name used instead of the root directory's name
+    pub project: Option<String>,
+    /// Tags added to the scope of every command in the repository
+    pub tags: Vec<String>,
+    /// Scope commands to the project. Set to false to never inject it in this repository
+    pub auto: bool,
+}
+
+impl Default for RepoConfig {
+    fn default() -> Self {
+        RepoConfig {
+            project: None,
+            tags: Vec::new(),
+            auto: true,
+        }
+    }
+}
+
+impl RepoConfig {
+    pub fn from_file(path: &Path) -> Result<Self> {
+        debug!("Loading repository config from '{}'", path.display());
+        let contents = fs::read_to_string(path)?;
+        match toml::from_str(&contents) {
+            Ok(config) => Ok(config),
+            Err(e) => bail!("Unable to parse '{}': {}", path.display(), e),
+        }
+    }
+}
+
+pub fn project_dirs() -> Option<ProjectDirs> {
+    ProjectDirs::from("", "", env!("CARGO_PKG_NAME"))
+}
+
+/// Directory for state such as logs and crash reports, the XDG state directory where the platform
+/// has one
+pub fn state_dir() -> Option<PathBuf> {
+    project_dirs().map(|dirs| {
+        dirs.state_dir()
+            .unwrap_or_else(|| dirs.data_local_dir())
+            .to_path_buf()
+    })
+}
+
+impl Config {
+    /// Range of taskwarrior versions considered compatible
+    pub fn supported_versions(&self) -> &str {
+        self.supported_versions
+            .as_deref()
+            .unwrap_or(SUPPORTED_TASKWARRIOR_VERSIONS)
+    }
+
+    /// Path to the user's config file, if a home directory could be found
+    pub fn path() -> Option<PathBuf> {
+        project_dirs().map(|dirs| dirs.config_dir().join(CONFIG_FILE))
+    }
+
+    /// Load the user's config file, falling back to defaults if it does not exist
+    pub fn load() -> Result<Self> {
+        match Self::path() {
+            Some(path) if path.is_file() => Self::from_file(&path),
+            _ => Ok(Config::default()),
+        }
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self> {
+        debug!("Loading config from '{}'", path.display());
+        let contents = fs::read_to_string(path)?;
+        match toml::from_str(&contents) {
+            Ok(config) => Ok(config),
+            Err(e) => bail!("Unable to parse config '{}': {}", path.display(), e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_sections() {
+        // Each section deserialised on its own, with the defaults of the keys it leaves out.
+        // What the settings do is tested where they're used
+        type Check = fn(&Config);
+        let cases: &[(&str, Check)] = &[
+            ("", |config| {
+                assert!(config.filters.is_empty());
+                assert!(config.defaults.is_empty());
+                assert!(config.remote.is_none());
+            }),
+            ("[filters]\nurgent = 'priority:H'", |config| {
+                assert_eq!("priority:H", config.filters["urgent"]);
+            }),
+            ("[defaults]\nnext = 'status:pending'", |config| {
+                assert_eq!("status:pending", config.defaults["next"]);
+            }),
+            ("[template.bug]\ntags = ['bug']\npriority = 'H'", |config| {
+                assert_eq!(vec!["bug"], config.template["bug"].tags);
+                assert_eq!("H", config.template["bug"].attributes["priority"]);
+            }),
+            ("[aliases]\nbug = 'add +bug'", |config| {
+                assert_eq!("add +bug", config.aliases["bug"]);
+            }),
+            ("[hooks.post]\ndone = 'notify-send done'", |config| {
+                assert!(config.hooks.pre.is_empty());
+                assert_eq!("notify-send done", config.hooks.post["done"]);
+            }),
+            ("[project]\nsubdir_tag = true", |config| {
+                assert!(config.project.subdir_tag);
+                assert!(config.project.auto);
+                assert_eq!(None, config.project.injection);
+                assert!(config.project.markers.contains(&String::from(".git")));
+            }),
+            (
+                "task = '/opt/task'\n[project]\nauto = false\ninjection = 'context'",
+                |config| {
+                    assert_eq!(Some(PathBuf::from("/opt/task")), config.task);
+                    assert!(!config.project.auto);
+                    assert_eq!(Some(Injection::Context), config.project.injection);
+                },
+            ),
+            ("[remote]\nhost = 'server'", |config| {
+                let remote = config.remote.as_ref().unwrap();
+                assert_eq!("server", remote.host);
+                assert_eq!("task", remote.task);
+            }),
+            (
+                "[container]\nname = 'tw'\n[container.mounts]\n'/home/me/.task' = '/taskdata'",
+                |config| {
+                    let container = config.container.as_ref().unwrap();
+                    assert_eq!("podman", container.runtime);
+                    assert_eq!("task", container.task);
+                    assert_eq!(
+                        Path::new("/taskdata"),
+                        container.mounts[Path::new("/home/me/.task")]
+                    );
+                },
+            ),
+            ("[sync]\nauto = true", |config| {
+                assert!(config.sync.auto);
+                assert_eq!(3, config.sync.retries);
+            }),
+            ("[priority]\ncommands = ['export']", |config| {
+                assert_eq!(10, config.priority.nice);
+                assert_eq!(vec!["export"], config.priority.commands);
+            }),
+            ("[links]\nmode = 'never'", |config| {
+                assert_eq!(HyperlinkMode::Never, config.links.mode);
+                assert_eq!(None, config.links.task_url);
+            }),
+            (
+                "[log]\nfile_level = 'trace'\nrotation = 'daily'",
+                |config| {
+                    assert_eq!(LevelFilter::Trace, config.log.file_level);
+                    assert_eq!(Rotation::Daily, config.log.rotation);
+                    assert_eq!(LevelFilter::Error, config.log.console_level);
+                },
+            ),
+            ("[toolchain]\nurl = 'https://x/{version}'", |config| {
+                assert_eq!(Some("https://x/{version}"), config.toolchain.url.as_deref());
+            }),
+            ("[timew]\ntrack = true", |config| {
+                assert!(config.timew.track);
+                assert_eq!(None, config.timew.bin);
+            }),
+        ];
+        for (toml, check) in cases {
+            let config: Config =
+                toml::from_str(toml).unwrap_or_else(|e| panic!("{:?}: {}", toml, e));
+            check(&config);
+        }
+    }
+
+    #[test]
+    fn test_repo_config() {
+        let config: RepoConfig = toml::from_str(
+            r#"
+            project = "website"
+            tags = ["frontend"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(Some(String::from("website")), config.project);
+        assert_eq!(vec!["frontend"], config.tags);
+        assert!(config.auto);
+    }
+}
@@ -0,0 +1,77 @@
+use crate::table::cell;
+use crate::task::Task;
+use std::time::SystemTime;
+
+/// `field` quoted if it holds a character that would otherwise end it
+fn quote(field: &str) -> String {
+    match field.contains([',', '"', '\n', '\r']) {
+        true => format!("\"{}\"", field.replace('"', "\"\"")),
+        false => field.to_string(),
+    }
+}
+
+/// Full RFC 3339 timestamp, rather than the day the table shows
+fn timestamp(time: Option<SystemTime>) -> String {
+    time.map(|time| humantime::format_rfc3339_seconds(time).to_string())
+        .unwrap_or_default()
+}
+
+/// Value of `column` for `task`. Dates are given in full, other columns as the table shows them
+fn field(task: &Task, column: &str) -> String {
+    match column {
+        "entry" => timestamp(task.entry),
+        "modified" => timestamp(task.modified),
+        "start" => timestamp(task.start),
+        "end" => timestamp(task.end),
+        "due" => timestamp(task.due),
+        "scheduled" => timestamp(task.scheduled),
+        "wait" => timestamp(task.wait),
+        "urgency" => task.urgency.to_string(),
+        column => cell(task, column),
+    }
+}
+
+/// `tasks` as CSV with a header row naming `columns`
+pub fn render(tasks: &[Task], columns: &[String]) -> String {
+    let mut out = String::new();
+    let header: Vec<String> = columns.iter().map(|column| quote(column)).collect();
+    out.push_str(&header.join(","));
+    out.push('\n');
+    for task in tasks {
+        let row: Vec<String> = columns
+            .iter()
+            .map(|column| quote(&field(task, column)))
+            .collect();
+        out.push_str(&row.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_render() {
+        let tasks: Vec<Task> = serde_json::from_str(
+            r#"[
+                {"id": 1, "uuid": "a1", "description": "Say \"hi\", then leave",
+                 "status": "pending", "due": "20240501T120000Z", "urgency": 2.5, "estimate": "2h"},
+                {"uuid": "b2", "description": "Done", "status": "completed", "project": "repo"}
+            ]"#,
+        )
+        .unwrap();
+        let columns: Vec<String> = "id,description,project,due,urgency,estimate"
+            .split(',')
+            .map(String::from)
+            .collect();
+        let expected = "\
+id,description,project,due,urgency,estimate
+1,\"Say \"\"hi\"\", then leave\",,2024-05-01T12:00:00Z,2.5,2h
+-,Done,repo,,0,
+";
+        assert_eq!(expected, render(&tasks, &columns));
+    }
+}
@@ -0,0 +1,309 @@
+use crate::config::{project_dirs, ProjectConfig, RepoConfig};
+use crate::git;
+use crate::task_args::project::Project;
+use color_eyre::eyre::bail;
+use color_eyre::Result;
+use log::debug;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// What a command gets scoped to when the user doesn't provide a project
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Scope {
+    project: Project,
+    tags: Vec<String>,
+}
+
+impl Scope {
+    pub fn with_project(project: Project) -> Self {
+        Self {
+            project,
+            tags: Vec::new(),
+        }
+    }
+
+    pub fn project(&self) -> &Project {
+        &self.project
+    }
+
+    pub fn add_tag(&mut self, tag: &str) {
+        self.tags.push(tag.to_string());
+    }
+
+    /// Filter/modifier tokens selecting this scope
+    pub fn tokens(&self) -> Vec<String> {
+        let mut tokens = vec![self.project.to_string()];
+        tokens.extend(self.tags.iter().map(|tag| format!("+{}", tag)));
+        tokens
+    }
+}
+
+impl fmt::Display for Scope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.tokens().join(" "))
+    }
+}
+
+/// Directory the isolated project databases are created in
+pub fn isolated_data_root() -> Result<PathBuf> {
+    let Some(dirs) = project_dirs() else {
+        bail!("Unable to find a data directory for isolated project databases");
+    };
+    Ok(dirs.data_dir().join("projects"))
+}
+
+/// Whether `name` stays a single directory under the root it's joined to. Names come from
+/// cloned repositories' config, so can't be trusted
+fn is_plain_dir_name(name: &str) -> bool {
+    !name.is_empty() && !name.starts_with('.') && !name.contains(['/', '\\'])
+}
+
+/// Task database used for `project` when projects are isolated, created if it doesn't exist
+pub fn isolated_data_dir(project: &Project) -> Result<PathBuf> {
+    if !is_plain_dir_name(project.name()) {
+        bail!(
+            "Project name '{}' can't name an isolated task database",
+            project.name()
+        );
+    }
+    let dir = isolated_data_root()?.join(project.name());
+    if !dir.is_dir() {
+        debug!("Creating task database '{}'", dir.display());
+        fs::create_dir_all(&dir)?;
+    }
+    Ok(dir)
+}
+
+/// Names and locations of the isolated project databases that have been created
+pub fn isolated_data_dirs() -> Result<Vec<(String, PathBuf)>> {
+    let root = isolated_data_root()?;
+    if !root.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut dirs = Vec::new();
+    for entry in fs::read_dir(root)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            dirs.push((project_name_from_path(&path), path));
+        }
+    }
+    dirs.sort();
+    Ok(dirs)
+}
+
+/// Per-repository config file, which also marks the directory it's in as a project root
+const REPO_CONFIG_FILE: &str = ".taskhelper.toml";
+
+fn project_name_from_path(path: &Path) -> String {
+    path.file_name().unwrap().to_str().unwrap().to_string()
+}
+
+/// Walk up from `start` to the first directory containing one of `markers` or a
+/// `.taskhelper.toml`
+fn find_root(start: &Path, markers: &[String]) -> Option<PathBuf> {
+    let mut dir = start.to_path_buf();
+    loop {
+        let is_root = dir.join(REPO_CONFIG_FILE).is_file()
+            || markers.iter().any(|marker| dir.join(marker).exists());
+        if is_root {
+            return Some(dir);
+        }
+
+        let parent = dir.parent()?;
+        dir = parent.to_path_buf();
+    }
+}
+
+/// Make a directory name usable as a taskwarrior tag or project name component
+pub(crate) fn sanitize_tag(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+/// `name` followed by each directory from `root` down to `cwd`, as a taskwarrior project
+/// hierarchy, e.g. `repo.frontend.components`
+fn hierarchical_name(name: &str, root: &Path, cwd: &Path) -> String {
+    let Ok(relative) = cwd.strip_prefix(root) else {
+        return name.to_string();
+    };
+    let mut hierarchy = vec![name.to_string()];
+    hierarchy.extend(
+        relative
+            .components()
+            .map(|component| sanitize_tag(&component.as_os_str().to_string_lossy())),
+    );
+    hierarchy.join(".")
+}
+
+/// Tag for the top level subdirectory of `root` that `cwd` is in
+fn subdir_tag(root: &Path, cwd: &Path) -> Option<String> {
+    let relative = cwd.strip_prefix(root).ok()?;
+    let first = relative.components().next()?;
+    let name = first.as_os_str().to_str()?;
+    Some(sanitize_tag(name))
+}
+
+/// Scope for commands run in `cwd`, from the project root above it and its `.taskhelper.toml`
+fn scope_for(config: &ProjectConfig, cwd: &Path) -> Result<Option<Scope>> {
+    if !config.auto {
+        return Ok(None);
+    }
+    let Some(root) = find_root(cwd, &config.markers) else {
+        return Ok(None);
+    };
+    let repo_config_path = root.join(REPO_CONFIG_FILE);
+    let repo = if repo_config_path.is_file() {
+        RepoConfig::from_file(&repo_config_path)?
+    } else {
+        RepoConfig::default()
+    };
+    if !repo.auto {
+        debug!(
+            "Project injection is disabled in '{}'",
+            repo_config_path.display()
+        );
+        return Ok(None);
+    }
+
+    let mut name = repo
+        .project
+        .unwrap_or_else(|| project_name_from_path(&root));
+    if config.hierarchy {
+        name = hierarchical_name(&name, &root, cwd);
+    }
+    let mut scope = Scope::with_project(Project::with_name(&name));
+    for tag in &repo.tags {
+        scope.add_tag(tag);
+    }
+    if config.subdir_tag {
+        if let Some(tag) = subdir_tag(&root, cwd) {
+            scope.add_tag(&tag);
+        }
+    }
+    if config.branch_tag {
+        if let Some(branch) = git::branch(&root) {
+            scope.add_tag(&sanitize_tag(&branch));
+        }
+    }
+    Ok(Some(scope))
+}
+
+pub fn find_scope(config: &ProjectConfig) -> Result<Option<Scope>> {
+    scope_for(config, &env::current_dir()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_subdir_tag() {
+        let root = Path::new("/src/repo");
+        assert_eq!(
+            Some(String::from("frontend")),
+            subdir_tag(root, Path::new("/src/repo/frontend/components"))
+        );
+        assert_eq!(None, subdir_tag(root, root));
+    }
+
+    #[test]
+    fn test_hierarchical_name() {
+        let root = Path::new("/src/repo");
+        assert_eq!(
+            "repo.frontend.v1-2",
+            hierarchical_name("repo", root, Path::new("/src/repo/frontend/v1.2"))
+        );
+        assert_eq!("repo", hierarchical_name("repo", root, root));
+    }
+
+    #[test]
+    fn test_is_plain_dir_name() {
+        assert!(is_plain_dir_name("repo"));
+        assert!(is_plain_dir_name("repo.frontend"));
+        for name in ["", "..", "../../x", ".hidden", "a/b", r"a\b"] {
+            assert!(!is_plain_dir_name(name), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_sanitize_tag() {
+        assert_eq!("my-app_2", sanitize_tag("my app_2"));
+    }
+
+    #[test]
+    fn test_find_root() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path().join("repo");
+        let crate_dir = root.join("crates").join("cli");
+        fs::create_dir_all(root.join(".hg")).unwrap();
+        fs::create_dir_all(&crate_dir).unwrap();
+        fs::write(crate_dir.join("Cargo.toml"), "").unwrap();
+
+        let markers = ProjectConfig::default().markers;
+        assert_eq!(Some(root.clone()), find_root(&crate_dir, &markers));
+        let markers = vec![String::from("Cargo.toml")];
+        assert_eq!(Some(crate_dir.clone()), find_root(&crate_dir, &markers));
+        assert_eq!(None, find_root(&crate_dir, &[]));
+    }
+
+    #[test]
+    fn test_repo_config() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path().join("repo");
+        let cwd = root.join("src");
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::create_dir_all(&cwd).unwrap();
+        let config = ProjectConfig::default();
+
+        let scope = scope_for(&config, &cwd).unwrap().unwrap();
+        assert_eq!(vec!["project:repo"], scope.tokens());
+
+        fs::write(
+            root.join(REPO_CONFIG_FILE),
+            "project = \"website\"\ntags = [\"web\"]\n",
+        )
+        .unwrap();
+        let scope = scope_for(&config, &cwd).unwrap().unwrap();
+        assert_eq!(vec!["project:website", "+web"], scope.tokens());
+
+        fs::write(root.join(REPO_CONFIG_FILE), "auto = false\n").unwrap();
+        assert_eq!(None, scope_for(&config, &cwd).unwrap());
+    }
+
+    #[test]
+    fn test_branch_tag() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let root = dir.path().join("repo");
+        fs::create_dir_all(&root).unwrap();
+        git::git(Some(&root), &["init", "--quiet"]).unwrap();
+        git::git(
+            Some(&root),
+            &["checkout", "--quiet", "-b", "feature/issue-123"],
+        )
+        .unwrap();
+        let mut config = ProjectConfig::default();
+        let scope = scope_for(&config, &root).unwrap().unwrap();
+        assert_eq!(vec!["project:repo"], scope.tokens());
+
+        config.branch_tag = true;
+        let scope = scope_for(&config, &root).unwrap().unwrap();
+        assert_eq!(vec!["project:repo", "+feature-issue-123"], scope.tokens());
+    }
+
+    #[test]
+    fn test_scope_tokens() {
+        let mut scope = Scope::with_project(Project::with_name("repo"));
+        scope.add_tag("frontend");
+        assert_eq!(vec!["project:repo", "+frontend"], scope.tokens());
+    }
+}
@@ -0,0 +1,232 @@
+use crate::{NAME, VERSION};
+use color_eyre::eyre::{bail, eyre};
+use color_eyre::Result;
+use log::debug;
+use minisign_verify::{PublicKey, Signature};
+use sha2::{Digest, Sha256};
+use std::env;
+use std::io::{self, Cursor, Read};
+use std::path::Path;
+
+/// Target triple prebuilt binaries are built for on this platform
+pub fn target() -> Option<String> {
+    let platform = match env::consts::OS {
+        "linux" => "unknown-linux-gnu",
+        "macos" => "apple-darwin",
+        "windows" => "pc-windows-msvc",
+        _ => return None,
+    };
+    Some(format!("{}-{}", env::consts::ARCH, platform))
+}
+
+/// GET request for `url` identifying us, for callers that need to add headers
+pub fn request(url: &str) -> ureq::Request {
+    debug!("Fetching {}", url);
+    let user_agent = format!("{}/{}", NAME, VERSION);
+    ureq::get(url).set("User-Agent", &user_agent)
+}
+
+pub fn get(url: &str) -> Result<ureq::Response> {
+    Ok(request(url).call()?)
+}
+
+pub fn download(url: &str) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    get(url)?.into_reader().read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Standard padded base64 of `bytes`, as HTTP basic authentication takes credentials
+pub(crate) fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            match i <= chunk.len() {
+                true => out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char),
+                false => out.push('='),
+            }
+        }
+    }
+    out
+}
+
+/// Check `bytes` against a checksum file in `sha256sum` format
+pub fn verify_checksum(bytes: &[u8], checksum_file: &str) -> Result<()> {
+    let Some(expected) = checksum_file.split_whitespace().next() else {
+        bail!("Empty checksum file");
+    };
+    let actual = hex(&Sha256::digest(bytes));
+    if !actual.eq_ignore_ascii_case(expected) {
+        bail!(
+            "Checksum mismatch: expected {}, downloaded {}",
+            expected,
+            actual
+        );
+    }
+    Ok(())
+}
+
+/// Check `bytes` against a detached minisign signature made with the secret key of `public_key`
+pub fn verify_signature(bytes: &[u8], signature: &str, public_key: &str) -> Result<()> {
+    let public_key = PublicKey::from_base64(public_key)
+        .map_err(|e| eyre!("Unable to read the public key: {}", e))?;
+    let signature =
+        Signature::decode(signature).map_err(|e| eyre!("Unable to read the signature: {}", e))?;
+    public_key
+        .verify(bytes, &signature, false)
+        .map_err(|e| eyre!("Signature verification failed: {}", e))
+}
+
+/// Whether `path` is a file named one of `names`
+fn named(path: &Path, names: &[&str]) -> bool {
+    path.file_name()
+        .is_some_and(|name| names.iter().any(|n| name == *n))
+}
+
+/// Pull the first file named one of `names` out of a `.zip` archive, in any directory
+fn unzip(archive: &[u8], names: &[&str]) -> Result<Option<Vec<u8>>> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(archive))?;
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i)?;
+        if entry.is_file()
+            && entry
+                .enclosed_name()
+                .is_some_and(|path| named(&path, names))
+        {
+            let mut file = Vec::new();
+            entry.read_to_end(&mut file)?;
+            return Ok(Some(file));
+        }
+    }
+    Ok(None)
+}
+
+/// Decompress a `.tar.xz` or `.tar.gz` archive, picked by the extension of `archive_name`
+fn untar(archive_name: &str, archive: &[u8]) -> Result<Vec<u8>> {
+    let mut tar = Vec::new();
+    if archive_name.ends_with(".tar.xz") {
+        lzma_rs::xz_decompress(&mut io::BufReader::new(archive), &mut tar)
+            .map_err(|e| eyre!("Unable to decompress '{}': {}", archive_name, e))?;
+    } else if archive_name.ends_with(".tar.gz") || archive_name.ends_with(".tgz") {
+        flate2::read::GzDecoder::new(archive).read_to_end(&mut tar)?;
+    } else {
+        bail!("Unsupported archive '{}'", archive_name);
+    }
+    Ok(tar)
+}
+
+/// Pull the first file named one of `names` out of a `.zip` or compressed tar archive, in any
+/// directory
+pub fn extract(archive_name: &str, archive: &[u8], names: &[&str]) -> Result<Vec<u8>> {
+    if archive_name.ends_with(".zip") {
+        if let Some(file) = unzip(archive, names)? {
+            return Ok(file);
+        }
+        bail!(
+            "'{}' does not contain '{}'",
+            archive_name,
+            names.join("' or '")
+        );
+    }
+    let tar = untar(archive_name, archive)?;
+    let mut archive = tar::Archive::new(tar.as_slice());
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if named(&entry.path()?, names) {
+            let mut file = Vec::new();
+            entry.read_to_end(&mut file)?;
+            return Ok(file);
+        }
+    }
+    bail!(
+        "'{}' does not contain '{}'",
+        archive_name,
+        names.join("' or '")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::io::Write;
+
+    #[test]
+    fn test_verify_checksum() {
+        let checksum =
+            "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824  hello.tar.xz";
+        assert!(verify_checksum(b"hello", checksum).is_ok());
+        assert!(verify_checksum(b"goodbye", checksum).is_err());
+    }
+
+    #[test]
+    fn test_base64() {
+        assert_eq!("", base64(b""));
+        assert_eq!("bWU=", base64(b"me"));
+        assert_eq!("bWU6dG9rZW4=", base64(b"me:token"));
+        assert_eq!("YWJj", base64(b"abc"));
+    }
+
+    #[test]
+    fn test_extract() {
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(4);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "release/bin/task", &b"task"[..])
+            .unwrap();
+        let tar = builder.into_inner().unwrap();
+        let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gz.write_all(&tar).unwrap();
+        let archive = gz.finish().unwrap();
+
+        assert_eq!(
+            b"task".to_vec(),
+            extract("release.tar.gz", &archive, &["task"]).unwrap()
+        );
+        assert!(extract("release.tar.gz", &archive, &["missing"]).is_err());
+        assert!(extract("release.zip", &archive, &["task"]).is_err());
+        assert!(extract("release.7z", &archive, &["task"]).is_err());
+    }
+
+    #[test]
+    fn test_extract_zip() {
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        zip.start_file("release/task.exe", zip::write::SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(b"task").unwrap();
+        let archive = zip.finish().unwrap().into_inner();
+
+        assert_eq!(
+            b"task".to_vec(),
+            extract("release.zip", &archive, &["task", "task.exe"]).unwrap()
+        );
+        assert!(extract("release.zip", &archive, &["missing"]).is_err());
+    }
+
+    #[test]
+    fn test_verify_signature() {
+        let public_key = "RWQHxoDS+bCwZI6KpzEOJi2/Bb1IRSN60O+yFxwt/DmafdKSjpdbuRJc";
+        let signature = "\
+untrusted comment: signature from minisign secret key
+RUQHxoDS+bCwZHe+Lgh4SdDSaCS73hoQhe6eg7AwjpmqKaMK9qWUa42aFe+9Z7PV0iZu4A2WhYl5nySKUVba+65cd41E7Q2pWgo=
+trusted comment: timestamp:1700000000\tfile:taskhelper-x86_64-unknown-linux-gnu.tar.xz
+p2ZlFSpONsYsbwlEr7+jI3vt2jNPI+RTqStsfPs97RIc8hHhp/Bjip+u6WIynAqESLWzHk0XAbbsEz5VgmLnBQ==
+";
+        assert!(verify_signature(b"taskhelper", signature, public_key).is_ok());
+        assert!(verify_signature(b"tampered", signature, public_key).is_err());
+        let other_key = "RWQf6LRCGA9i53mlYecO4IzT51TGPpvWucNSCh1CBM0QTaLn73Y7GFO3";
+        assert!(verify_signature(b"taskhelper", signature, other_key).is_err());
+        assert!(verify_signature(b"taskhelper", "not a signature", public_key).is_err());
+    }
+}
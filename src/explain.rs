@@ -0,0 +1,152 @@
+use crate::args::Cli;
+use crate::detect::Scope;
+use crate::taskwarrior::shell_quote;
+use std::fmt::Write;
+use std::process::Command;
+
+const NONE: &str = "(none)";
+
+fn or_none(items: Vec<String>) -> String {
+    if items.is_empty() {
+        String::from(NONE)
+    } else {
+        items.join(" ")
+    }
+}
+
+/// The program and arguments of `cmd`, quoted for a shell
+fn argv(cmd: &Command) -> String {
+    std::iter::once(cmd.get_program())
+        .chain(cmd.get_args())
+        .map(|arg| shell_quote(&arg.to_string_lossy()))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// `cmd` as a line that can be pasted into a shell, with the environment it sets in front
+pub fn command_line(cmd: &Command) -> String {
+    let mut line = String::new();
+    for (k, v) in cmd.get_envs() {
+        if let Some(v) = v {
+            let _ = write!(
+                line,
+                "{}={} ",
+                k.to_string_lossy(),
+                shell_quote(&v.to_string_lossy())
+            );
+        }
+    }
+    line.push_str(&argv(cmd));
+    line
+}
+
+/// Describe how `args` was interpreted: the filters, subcommand and modifiers it was parsed into,
+/// the project detected from the working directory and what was added for it, and the command
+/// `cmd` that taskwarrior would be run with
+pub fn explain(
+    args: &Cli,
+    scope: Option<&Scope>,
+    injected: Option<&str>,
+    task_args: &[String],
+    cmd: &Command,
+) -> String {
+    let filters = args
+        .filter
+        .iter()
+        .flat_map(|filters| filters.args())
+        .collect();
+    let subcommand = args
+        .command
+        .as_ref()
+        .map(|command| command.to_string())
+        .unwrap_or_else(|| String::from(NONE));
+    let modifiers = args
+        .command
+        .iter()
+        .flat_map(|command| command.modifiers())
+        .map(|modifier| modifier.to_string())
+        .collect();
+    let detected = scope
+        .map(|scope| scope.to_string())
+        .unwrap_or_else(|| String::from(NONE));
+    let injected = injected.unwrap_or(NONE).trim_start_matches("+ ");
+    let overrides = task_args
+        .iter()
+        .filter(|arg| arg.starts_with("rc."))
+        .cloned()
+        .collect();
+    let environment = cmd
+        .get_envs()
+        .filter_map(|(k, v)| Some(format!("{}={}", k.to_str()?, v?.to_str()?)))
+        .collect();
+
+    let mut out = String::new();
+    let rows = [
+        ("filters", or_none(filters)),
+        ("subcommand", subcommand),
+        ("modifiers", or_none(modifiers)),
+        ("detected project", detected),
+        ("injected", injected.to_string()),
+        ("rc overrides", or_none(overrides)),
+        ("environment", or_none(environment)),
+        ("command", argv(cmd)),
+    ];
+    for (label, value) in rows {
+        let _ = writeln!(out, "{:<17} {}", format!("{}:", label), value);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task_args::project::Project;
+    use clap::Parser;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_explain() {
+        let args = Cli::parse_from(["taskhelper", "status:pending", "modify", "priority:H"]);
+        let scope = Scope::with_project(Project::with_name("repo"));
+        let task_args = vec![
+            String::from("rc.data.location=/data"),
+            String::from("status:pending"),
+            String::from("modify"),
+            String::from("priority:H"),
+            String::from("project:repo"),
+        ];
+        let mut cmd = Command::new("task");
+        cmd.env("TASKRC", "/home/me/.taskrc").args(&task_args);
+        let expected = "\
+filters:          status:pending
+subcommand:       modify
+modifiers:        priority:H
+detected project: project:repo
+injected:         injected project:repo as last modifier
+rc overrides:     rc.data.location=/data
+environment:      TASKRC=/home/me/.taskrc
+command:          task rc.data.location=/data status:pending modify priority:H project:repo
+";
+        assert_eq!(
+            expected,
+            explain(
+                &args,
+                Some(&scope),
+                Some("+ injected project:repo as last modifier"),
+                &task_args,
+                &cmd
+            )
+        );
+    }
+
+    #[test]
+    fn test_command_line() {
+        let mut cmd = Command::new("task");
+        cmd.env("TASKRC", "/home/me/my taskrc")
+            .args(["project:repo", "add", "write docs"]);
+        assert_eq!(
+            "TASKRC='/home/me/my taskrc' task project:repo add 'write docs'",
+            command_line(&cmd)
+        );
+    }
+}
@@ -0,0 +1,48 @@
+use std::path::Path;
+use std::process::Command;
+use std::str;
+
+/// Trimmed output of git run with `args` in `dir`, or the current directory. None if git fails
+pub fn git(dir: Option<&Path>, args: &[&str]) -> Option<String> {
+    let mut cmd = Command::new("git");
+    if let Some(dir) = dir {
+        cmd.arg("-C").arg(dir);
+    }
+    let output = cmd.args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(str::from_utf8(&output.stdout).ok()?.trim().to_string())
+}
+
+/// Branch checked out in the repository at `dir`, None when HEAD is detached or it isn't a
+/// repository
+pub fn branch(dir: &Path) -> Option<String> {
+    git(Some(dir), &["symbolic-ref", "--quiet", "--short", "HEAD"])
+}
+
+/// Branch and abbreviated commit checked out in the current directory
+pub fn head() -> Option<(String, String)> {
+    let commit = git(None, &["rev-parse", "--short", "HEAD"])?;
+    let branch = git(None, &["rev-parse", "--abbrev-ref", "HEAD"])?;
+    Some((branch, commit))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_branch() {
+        let dir = tempfile::TempDir::new().unwrap();
+        assert_eq!(None, branch(dir.path()));
+        git(Some(dir.path()), &["init", "--quiet"]).unwrap();
+        git(
+            Some(dir.path()),
+            &["checkout", "--quiet", "-b", "feature/x"],
+        )
+        .unwrap();
+        assert_eq!(Some(String::from("feature/x")), branch(dir.path()));
+    }
+}
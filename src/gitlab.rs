@@ -0,0 +1,154 @@
+use crate::config::GitlabConfig;
+use crate::detect::Scope;
+use crate::download;
+use crate::git;
+use crate::issues::{mirror, Issue};
+use crate::task::{format_date, parse_day};
+use crate::taskwarrior::Taskwarrior;
+use color_eyre::eyre::{bail, eyre};
+use color_eyre::Result;
+use serde::Deserialize;
+use std::env;
+
+const TRACKER: &str = "gitlab";
+
+const PER_PAGE: usize = 100;
+
+/// An issue as the GitLab API describes it
+#[derive(Debug, Deserialize)]
+struct GitlabIssue {
+    /// Number of the issue in its project
+    iid: u64,
+    title: String,
+    web_url: String,
+    #[serde(default)]
+    labels: Vec<String>,
+    due_date: Option<String>,
+}
+
+impl From<GitlabIssue> for Issue {
+    fn from(issue: GitlabIssue) -> Self {
+        Issue {
+            url: issue.web_url,
+            key: Some(format!("#{}", issue.iid)),
+            title: issue.title,
+            labels: issue.labels,
+            priority: None,
+            due: issue
+                .due_date
+                .as_deref()
+                .and_then(parse_day)
+                .map(format_date),
+        }
+    }
+}
+
+/// Web URL of the host and project path of a git remote URL, for ssh (`git@host:group/repo.git`,
+/// `ssh://git@host:22/group/repo.git`) and http remotes. Hosts of ssh remotes are assumed to
+/// serve https
+fn remote_project(remote: &str) -> Option<(String, String)> {
+    let (scheme, host, path) = match remote.split_once("://") {
+        Some((scheme, rest)) => {
+            let (authority, path) = rest.split_once('/')?;
+            let host = authority.rsplit('@').next()?;
+            match scheme {
+                "http" | "https" => (scheme, host, path),
+                // The ssh port isn't the one the web interface is served on
+                _ => ("https", host.split(':').next()?, path),
+            }
+        }
+        None => {
+            let (authority, path) = remote.split_once(':')?;
+            ("https", authority.rsplit('@').next()?, path)
+        }
+    };
+    let path = path.trim_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path);
+    if host.is_empty() || !path.contains('/') {
+        return None;
+    }
+    Some((format!("{}://{}", scheme, host), path.to_string()))
+}
+
+/// Open issues of the project at `path` on the GitLab instance at `base_url`
+fn open_issues(base_url: &str, path: &str, token: Option<&str>) -> Result<Vec<Issue>> {
+    let mut issues = Vec::new();
+    let mut page = String::from("1");
+    while !page.is_empty() {
+        let url = format!(
+            "{}/api/v4/projects/{}/issues?state=opened&per_page={}&page={}",
+            base_url.trim_end_matches('/'),
+            path.replace('/', "%2F"),
+            PER_PAGE,
+            page
+        );
+        let mut request = download::request(&url);
+        if let Some(token) = token {
+            request = request.set("PRIVATE-TOKEN", token);
+        }
+        let response = match request.call() {
+            Ok(response) => response,
+            Err(ureq::Error::Status(code @ (401 | 403 | 404), _)) => bail!(
+                "GitLab answered {} for '{}', a private project needs a token with read_api access",
+                code,
+                path
+            ),
+            Err(e) => return Err(e.into()),
+        };
+        page = response
+            .header("x-next-page")
+            .unwrap_or_default()
+            .to_string();
+        let batch: Vec<GitlabIssue> = serde_json::from_reader(response.into_reader())?;
+        issues.extend(batch.into_iter().map(Issue::from));
+    }
+    Ok(issues)
+}
+
+/// Mirror the open issues of the GitLab project the `origin` remote points at into the project
+/// of `scope`. `rc` is prepended to each taskwarrior command
+pub fn import(
+    task: &Taskwarrior,
+    rc: &[String],
+    scope: &Scope,
+    config: &GitlabConfig,
+    dry_run: bool,
+) -> Result<()> {
+    let remote = git::git(None, &["remote", "get-url", "origin"])
+        .ok_or_else(|| eyre!("No 'origin' remote to find the GitLab project from"))?;
+    let (remote_url, path) = remote_project(&remote)
+        .ok_or_else(|| eyre!("Unable to find the GitLab project in '{}'", remote))?;
+    let base_url = config.url.clone().unwrap_or(remote_url);
+    let token = env::var(&config.token_var).ok().filter(|t| !t.is_empty());
+    let issues = open_issues(&base_url, &path, token.as_deref())?;
+    mirror(task, rc, scope, TRACKER, &issues, true, dry_run)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use s_string::s;
+
+    #[test]
+    fn test_remote_project() {
+        let expected = Some((s!("https://gitlab.example.com"), s!("group/sub/repo")));
+        assert_eq!(
+            expected,
+            remote_project("git@gitlab.example.com:group/sub/repo.git")
+        );
+        assert_eq!(
+            expected,
+            remote_project("ssh://git@gitlab.example.com:2222/group/sub/repo.git")
+        );
+        assert_eq!(
+            expected,
+            remote_project("https://gitlab.example.com/group/sub/repo")
+        );
+        assert_eq!(
+            Some((s!("http://localhost:8080"), s!("me/repo"))),
+            remote_project("http://localhost:8080/me/repo.git")
+        );
+        assert_eq!(None, remote_project("/srv/git/repo.git"));
+    }
+}
@@ -0,0 +1,565 @@
+use crate::args::{BundledHook, HookHandler};
+use crate::detect::Scope;
+use crate::git;
+use crate::task::{export_tasks, format_date};
+use crate::taskwarrior::{shell_quote, Taskwarrior};
+use clap::ValueEnum;
+use color_eyre::eyre::{bail, eyre};
+use color_eyre::Result;
+use directories::BaseDirs;
+use serde_json::{json, Value};
+use std::fmt::{self, Write as _};
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::str;
+use std::time::SystemTime;
+
+/// Events taskwarrior runs hooks for. A hook script's file name must start with one of them
+const EVENTS: &[&str] = &["on-add", "on-exit", "on-launch", "on-modify"];
+
+/// Line marking scripts written by `hooks install`, so ones written by the user aren't replaced
+const MARKER: &str = "# Installed by taskhelper";
+
+/// Why taskwarrior won't run a hook script
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Problem {
+    UnknownEvent,
+    // Only checked where files have an executable bit
+    #[cfg_attr(not(unix), allow(dead_code))]
+    NotExecutable,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::UnknownEvent => write!(
+                f,
+                "ignored by taskwarrior, the name must start with {}",
+                EVENTS.join(", ")
+            ),
+            Problem::NotExecutable => write!(f, "not executable"),
+        }
+    }
+}
+
+/// Scripts installed for `hook`, by file name, and the taskhelper subcommand each runs
+fn scripts(hook: BundledHook) -> &'static [(&'static str, &'static str)] {
+    match hook {
+        BundledHook::Dispatcher => &[
+            ("on-add.taskhelper", "_hook on-add"),
+            ("on-modify.taskhelper", "_hook on-modify"),
+        ],
+        BundledHook::GitAnnotate => &[("on-modify.git-annotate", "_hook git-annotate")],
+        BundledHook::CommitAnnotate => &[("post-commit", "_post-commit")],
+        BundledHook::CommitClose => &[("post-commit", "_commit-close")],
+    }
+}
+
+/// Script running `program`, already quoted for the shell, with each of `subcommands` in turn
+fn script(program: &str, subcommands: &[&str]) -> String {
+    let mut script = format!("#!/bin/sh\n{}\n", MARKER);
+    for subcommand in subcommands {
+        let _ = match subcommands.len() {
+            1 => writeln!(script, "exec {} {}", program, subcommand),
+            _ => writeln!(script, "{} {}", program, subcommand),
+        };
+    }
+    script
+}
+
+fn installed_by_us(path: &Path) -> bool {
+    fs::read_to_string(path).is_ok_and(|script| script.contains(MARKER))
+}
+
+/// Line of the script at `path` running `subcommand`
+fn subcommand_line(path: &Path, subcommand: &str) -> Option<String> {
+    let script = fs::read_to_string(path).ok()?;
+    let suffix = format!(" {}", subcommand);
+    let line = script.lines().find(|line| line.ends_with(&suffix))?;
+    Some(line.to_string())
+}
+
+/// Subcommands of the bundled hooks installing a script named `name`. Git runs one script per
+/// hook, so the commit hooks share theirs, which runs them in this order
+fn sharing(name: &str) -> Vec<&'static str> {
+    BundledHook::value_variants()
+        .iter()
+        .flat_map(|hook| scripts(*hook))
+        .filter(|(script_name, _)| *script_name == name)
+        .map(|(_, subcommand)| *subcommand)
+        .collect()
+}
+
+/// Hooks directory of the git repository in the current directory, honoring `core.hooksPath`
+fn git_hooks_dir() -> Result<PathBuf> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--git-path", "hooks"])
+        .output()?;
+    if !output.status.success() {
+        bail!("Not in a git repository");
+    }
+    Ok(PathBuf::from(str::from_utf8(&output.stdout)?.trim()))
+}
+
+/// Directory the bundled hook `hook` is installed to. Scripts not bundled are taskwarrior hooks
+pub fn install_dir(task: &Taskwarrior, hook: Option<BundledHook>) -> Result<PathBuf> {
+    match hook {
+        Some(BundledHook::CommitAnnotate | BundledHook::CommitClose) => git_hooks_dir(),
+        _ => hooks_dir(task),
+    }
+}
+
+/// Directory taskwarrior runs hook scripts from, `rc.hooks.location` or `~/.task/hooks`
+pub fn hooks_dir(task: &Taskwarrior) -> Result<PathBuf> {
+    if !task.is_local() {
+        bail!("Hooks can only be managed for taskwarrior running on this machine");
+    }
+    let home = BaseDirs::new().map(|dirs| dirs.home_dir().to_path_buf());
+    let output = task.command(&["_get", "rc.hooks.location"]).output()?;
+    let location = match output.status.success() {
+        true => str::from_utf8(&output.stdout)?.trim(),
+        false => "",
+    };
+    match (location, home) {
+        ("", Some(home)) => Ok(home.join(".task").join("hooks")),
+        ("", None) => bail!("Unable to find the hooks directory"),
+        (location, Some(home)) if location.starts_with("~/") => Ok(home.join(&location[2..])),
+        (location, _) => Ok(PathBuf::from(location)),
+    }
+}
+
+fn problems(path: &Path) -> Result<Vec<Problem>> {
+    let mut problems = Vec::new();
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    if !EVENTS.iter().any(|event| name.starts_with(event)) {
+        problems.push(Problem::UnknownEvent);
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if fs::metadata(path)?.permissions().mode() & 0o111 == 0 {
+            problems.push(Problem::NotExecutable);
+        }
+    }
+    Ok(problems)
+}
+
+/// Hook scripts in `dir`, by file name, with anything stopping taskwarrior from running them
+pub fn check(dir: &Path) -> Result<Vec<(String, Vec<Problem>)>> {
+    let mut hooks = Vec::new();
+    if !dir.is_dir() {
+        return Ok(hooks);
+    }
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().to_string();
+        hooks.push((name, problems(&entry.path())?));
+    }
+    hooks.sort();
+    Ok(hooks)
+}
+
+pub fn list(dir: &Path) -> Result<String> {
+    let mut out = String::new();
+    for (name, problems) in check(dir)? {
+        if problems.is_empty() {
+            let _ = writeln!(out, "{}: ok", name);
+        }
+        for problem in problems {
+            let _ = writeln!(out, "{}: problem: {}", name, problem);
+        }
+    }
+    Ok(out)
+}
+
+/// Write the scripts for `hook` to `dir`, running `this_program`
+pub fn install(dir: &Path, hook: BundledHook, this_program: &Path) -> Result<Vec<PathBuf>> {
+    fs::create_dir_all(dir)?;
+    let program = shell_quote(&this_program.display().to_string());
+    let mut installed = Vec::new();
+    for (name, subcommand) in scripts(hook) {
+        let path = dir.join(name);
+        if path.exists() && !installed_by_us(&path) {
+            bail!(
+                "'{}' already exists and wasn't installed by taskhelper",
+                path.display()
+            );
+        }
+        // Keep running the other bundled hooks sharing the script
+        let subcommands: Vec<&str> = sharing(name)
+            .into_iter()
+            .filter(|s| s == subcommand || subcommand_line(&path, s).is_some())
+            .collect();
+        fs::write(&path, script(&program, &subcommands))?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o755))?;
+        }
+        installed.push(path);
+    }
+    Ok(installed)
+}
+
+/// Remove the scripts of the bundled hook named `hook`, or the hook script with that file name
+pub fn remove(dir: &Path, hook: &str) -> Result<Vec<PathBuf>> {
+    // Scripts of a bundled hook are only removed if we installed them to run its subcommand
+    let names: Vec<(&str, Option<&str>)> = match BundledHook::from_str(hook, false) {
+        Ok(bundled) => scripts(bundled)
+            .iter()
+            .map(|(name, subcommand)| (*name, Some(*subcommand)))
+            .collect(),
+        Err(_) if hook.contains(['/', '\\']) => bail!("'{}' is not a hook script name", hook),
+        Err(_) => vec![(hook, None)],
+    };
+    let mut removed = Vec::new();
+    for (name, subcommand) in names {
+        let path = dir.join(name);
+        if !path.is_file() {
+            continue;
+        }
+        let Some(subcommand) = subcommand else {
+            fs::remove_file(&path)?;
+            removed.push(path);
+            continue;
+        };
+        let line = match installed_by_us(&path) {
+            true => subcommand_line(&path, subcommand),
+            false => None,
+        };
+        let Some(line) = line else {
+            continue;
+        };
+        // A script shared with other bundled hooks is kept running them
+        let others: Vec<&str> = sharing(name)
+            .into_iter()
+            .filter(|s| *s != subcommand && subcommand_line(&path, s).is_some())
+            .collect();
+        if others.is_empty() {
+            fs::remove_file(&path)?;
+        } else {
+            let program = line
+                .strip_prefix("exec ")
+                .unwrap_or(&line)
+                .strip_suffix(&format!(" {}", subcommand))
+                .unwrap_or_default();
+            fs::write(&path, script(program, &others))?;
+        }
+        removed.push(path);
+    }
+    if removed.is_empty() {
+        bail!("No hook '{}' is installed in '{}'", hook, dir.display());
+    }
+    Ok(removed)
+}
+
+fn started(original: &Value, modified: &Value) -> bool {
+    original.get("start").is_none() && modified.get("start").is_some()
+}
+
+fn annotate(task: &mut Value, description: String, now: SystemTime) -> Result<()> {
+    let Some(task) = task.as_object_mut() else {
+        bail!("Hook input is not a task");
+    };
+    let annotation = json!({"entry": format_date(now), "description": description});
+    match task.get_mut("annotations").and_then(Value::as_array_mut) {
+        Some(annotations) => annotations.push(annotation),
+        None => {
+            task.insert(String::from("annotations"), json!([annotation]));
+        }
+    }
+    Ok(())
+}
+
+/// Output for a hook event: the added or modified task as taskwarrior expects it back
+fn handle(
+    handler: HookHandler,
+    input: &str,
+    head: impl FnOnce() -> Option<(String, String)>,
+    now: SystemTime,
+) -> Result<String> {
+    let mut lines = input.lines();
+    let mut next_task = || -> Result<Value> {
+        let line = lines
+            .next()
+            .ok_or_else(|| eyre!("Hook input is missing a task"))?;
+        Ok(serde_json::from_str(line)?)
+    };
+    let task = match handler {
+        HookHandler::OnAdd => next_task()?,
+        HookHandler::OnModify => {
+            next_task()?;
+            next_task()?
+        }
+        HookHandler::GitAnnotate => {
+            let original = next_task()?;
+            let mut modified = next_task()?;
+            if started(&original, &modified) {
+                if let Some((branch, commit)) = head() {
+                    let description = match branch.as_str() {
+                        "HEAD" => format!("Started at {}", commit),
+                        branch => format!("Started on {} at {}", branch, commit),
+                    };
+                    annotate(&mut modified, description, now)?;
+                }
+            }
+            modified
+        }
+    };
+    Ok(format!("{}\n", task))
+}
+
+/// Abbreviated hash and subject of the commit at HEAD, as an annotation
+fn commit_annotation() -> Result<String> {
+    let output = Command::new("git")
+        .args(["log", "-1", "--format=Commit %h: %s"])
+        .output()?;
+    if !output.status.success() {
+        bail!("Unable to read the commit at HEAD");
+    }
+    Ok(str::from_utf8(&output.stdout)?.trim().to_string())
+}
+
+/// Annotate the tasks active in `scope` with the commit just made. `rc` is prepended to each
+/// taskwarrior command
+pub fn annotate_commit(task: &Taskwarrior, scope: &Scope, rc: &[String]) -> Result<()> {
+    let mut args = rc.to_vec();
+    args.push(String::from("+ACTIVE"));
+    args.extend(scope.tokens());
+    let active =
+        export_tasks(task, &args).map_err(|e| eyre!("Unable to find active tasks: {}", e))?;
+    if active.is_empty() {
+        return Ok(());
+    }
+
+    let annotation = commit_annotation()?;
+    for active in active {
+        let mut args = rc.to_vec();
+        args.extend([
+            String::from("rc.verbose=nothing"),
+            active.uuid,
+            String::from("annotate"),
+            String::from("--"),
+            annotation.clone(),
+        ]);
+        let status = task.command(&args).status()?;
+        if !status.success() {
+            bail!("Unable to annotate the active task");
+        }
+    }
+    Ok(())
+}
+
+/// Whether `reference` looks like a task ID or a UUID, or the start of one
+fn is_task_reference(reference: &str) -> bool {
+    let is_id = !reference.is_empty() && reference.bytes().all(|b| b.is_ascii_digit());
+    let is_uuid = reference.len() >= 8
+        && reference
+            .bytes()
+            .all(|b| b.is_ascii_hexdigit() || b == b'-');
+    is_id || is_uuid
+}
+
+/// Tasks a commit message references with `task:<id|uuid>` or `closes #<id>`, in the order
+/// they're given
+fn task_references(message: &str) -> Vec<String> {
+    let mut references: Vec<String> = Vec::new();
+    let mut words = message.split_whitespace().peekable();
+    while let Some(word) = words.next() {
+        let reference = match word.get(..5) {
+            Some(prefix) if prefix.eq_ignore_ascii_case("task:") => &word[5..],
+            _ if word.eq_ignore_ascii_case("closes") => {
+                match words.peek().and_then(|next| next.strip_prefix('#')) {
+                    Some(id) => id,
+                    None => continue,
+                }
+            }
+            _ => continue,
+        };
+        let reference = reference.trim_end_matches(|c: char| c.is_ascii_punctuation());
+        if is_task_reference(reference) && !references.iter().any(|r| r == reference) {
+            references.push(reference.to_string());
+        }
+    }
+    references
+}
+
+/// Complete the tasks referenced by the message of the commit just made, annotating each with
+/// the commit. `rc` is prepended to each taskwarrior command
+pub fn close_referenced(task: &Taskwarrior, rc: &[String]) -> Result<()> {
+    let message = git::git(None, &["log", "-1", "--format=%B"])
+        .ok_or_else(|| eyre!("Unable to read the commit at HEAD"))?;
+    let references = task_references(&message);
+    if references.is_empty() {
+        return Ok(());
+    }
+
+    let annotation = commit_annotation()?;
+    for reference in references {
+        let mut args = rc.to_vec();
+        args.extend([reference.clone(), String::from("status:pending")]);
+        let referenced = export_tasks(task, &args)
+            .map_err(|e| eyre!("Unable to find the task '{}': {}", reference, e))?;
+        let [referenced] = referenced.as_slice() else {
+            eprintln!(
+                "Warning: No single pending task matches '{}', not completing it",
+                reference
+            );
+            continue;
+        };
+        for action in [vec!["annotate", "--", annotation.as_str()], vec!["done"]] {
+            let mut args = rc.to_vec();
+            args.extend([String::from("rc.verbose=nothing"), referenced.uuid.clone()]);
+            args.extend(action.iter().map(|arg| arg.to_string()));
+            let status = task.command(&args).status()?;
+            if !status.success() {
+                bail!("Unable to complete the task '{}'", reference);
+            }
+        }
+        println!("Completed task '{}'", referenced.description);
+    }
+    Ok(())
+}
+
+/// Handle a hook event taskwarrior passed on stdin
+pub fn run_hook(handler: HookHandler) -> Result<()> {
+    let mut input = String::new();
+    io::stdin().read_to_string(&mut input)?;
+    print!("{}", handle(handler, &input, git::head, SystemTime::now())?);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use s_string::s;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    const ORIGINAL: &str = r#"{"description":"write docs","status":"pending","uuid":"a"}"#;
+    const STARTED: &str =
+        r#"{"description":"write docs","start":"20240101T120000Z","status":"pending","uuid":"a"}"#;
+
+    fn head() -> Option<(String, String)> {
+        Some((String::from("main"), String::from("abc1234")))
+    }
+
+    #[test]
+    fn test_handle_passthrough() {
+        let now = SystemTime::now();
+        assert_eq!(
+            format!("{}\n", ORIGINAL),
+            handle(HookHandler::OnAdd, ORIGINAL, head, now).unwrap()
+        );
+        let input = format!("{}\n{}\n", ORIGINAL, STARTED);
+        assert_eq!(
+            format!("{}\n", STARTED),
+            handle(HookHandler::OnModify, &input, head, now).unwrap()
+        );
+        assert!(handle(HookHandler::OnModify, ORIGINAL, head, now).is_err());
+    }
+
+    #[test]
+    fn test_handle_git_annotate() {
+        let now = UNIX_EPOCH + Duration::from_secs(1704110400);
+        let input = format!("{}\n{}\n", ORIGINAL, STARTED);
+        let output = handle(HookHandler::GitAnnotate, &input, head, now).unwrap();
+        let task: Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(
+            json!([{"entry": "20240101T120000Z", "description": "Started on main at abc1234"}]),
+            task["annotations"]
+        );
+
+        let input = format!("{}\n{}\n", STARTED, STARTED);
+        let output = handle(HookHandler::GitAnnotate, &input, head, now).unwrap();
+        assert_eq!(format!("{}\n", STARTED), output);
+    }
+
+    #[test]
+    fn test_install_check_remove() {
+        let dir = tempfile::TempDir::new().unwrap();
+        install(
+            dir.path(),
+            BundledHook::Dispatcher,
+            Path::new("/bin/taskhelper"),
+        )
+        .unwrap();
+        fs::write(dir.path().join("notes.txt"), "").unwrap();
+        let hooks = check(dir.path()).unwrap();
+        let names: Vec<&str> = hooks.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(
+            vec!["notes.txt", "on-add.taskhelper", "on-modify.taskhelper"],
+            names
+        );
+        assert!(hooks[0].1.contains(&Problem::UnknownEvent));
+        assert!(hooks[1].1.is_empty());
+
+        assert_eq!(2, remove(dir.path(), "dispatcher").unwrap().len());
+        assert_eq!(1, remove(dir.path(), "notes.txt").unwrap().len());
+        assert!(remove(dir.path(), "notes.txt").is_err());
+        assert!(remove(dir.path(), "../notes.txt").is_err());
+    }
+
+    #[test]
+    fn test_keep_user_scripts() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("post-commit");
+        fs::write(&path, "#!/bin/sh\nmake lint\n").unwrap();
+        let this_program = Path::new("/bin/taskhelper");
+        assert!(install(dir.path(), BundledHook::CommitAnnotate, this_program).is_err());
+        assert!(remove(dir.path(), "commit-annotate").is_err());
+        assert!(path.exists());
+
+        fs::remove_file(&path).unwrap();
+        install(dir.path(), BundledHook::CommitAnnotate, this_program).unwrap();
+        assert_eq!(
+            "#!/bin/sh\n# Installed by taskhelper\nexec /bin/taskhelper _post-commit\n",
+            fs::read_to_string(&path).unwrap()
+        );
+        assert!(remove(dir.path(), "commit-close").is_err());
+        assert_eq!(1, remove(dir.path(), "commit-annotate").unwrap().len());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_share_post_commit() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("post-commit");
+        let this_program = Path::new("/bin/taskhelper");
+        install(dir.path(), BundledHook::CommitClose, this_program).unwrap();
+        install(dir.path(), BundledHook::CommitAnnotate, this_program).unwrap();
+        install(dir.path(), BundledHook::CommitClose, this_program).unwrap();
+        assert_eq!(
+            "#!/bin/sh\n# Installed by taskhelper\n\
+             /bin/taskhelper _post-commit\n/bin/taskhelper _commit-close\n",
+            fs::read_to_string(&path).unwrap()
+        );
+
+        assert_eq!(1, remove(dir.path(), "commit-annotate").unwrap().len());
+        assert_eq!(
+            "#!/bin/sh\n# Installed by taskhelper\nexec /bin/taskhelper _commit-close\n",
+            fs::read_to_string(&path).unwrap()
+        );
+        assert!(remove(dir.path(), "commit-annotate").is_err());
+        assert_eq!(1, remove(dir.path(), "commit-close").unwrap().len());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_task_references() {
+        let message = "Fix the parser\n\nCloses #12. See task:8ad2e3db and TASK:4,\ncloses #12 task: closes 5";
+        assert_eq!(
+            vec![s!("12"), s!("8ad2e3db"), s!("4")],
+            task_references(message)
+        );
+        assert!(task_references("task:docs and closes the gap").is_empty());
+    }
+}
@@ -0,0 +1,162 @@
+use crate::config::{HyperlinkMode, LinksConfig};
+use crate::task::Task;
+use std::env;
+use std::io::{self, IsTerminal};
+use std::path::Path;
+
+/// Terminals known to support OSC 8, by `$TERM_PROGRAM`
+const TERM_PROGRAMS: &[&str] = &["iTerm.app", "WezTerm", "vscode", "ghostty", "Hyper"];
+
+/// Variables only set by terminals known to support OSC 8
+const TERM_VARIABLES: &[&str] = &[
+    "WT_SESSION",
+    "KITTY_WINDOW_ID",
+    "WEZTERM_EXECUTABLE",
+    "KONSOLE_VERSION",
+];
+
+/// Values of `$TERM` for terminals known to support OSC 8
+const TERMS: &[&str] = &["xterm-kitty", "alacritty", "foot", "xterm-ghostty"];
+
+/// First VTE version supporting OSC 8 (0.50)
+const VTE_HYPERLINKS: u32 = 5000;
+
+/// Whether the terminal described by `var` supports hyperlinks
+fn detect<F: Fn(&str) -> Option<String>>(var: F) -> bool {
+    if let Some(force) = var("FORCE_HYPERLINK") {
+        return force != "0";
+    }
+    if var("TERM").is_some_and(|term| term == "dumb") {
+        return false;
+    }
+    if TERM_VARIABLES.iter().any(|name| var(name).is_some()) {
+        return true;
+    }
+    if var("TERM_PROGRAM").is_some_and(|program| TERM_PROGRAMS.contains(&program.as_str())) {
+        return true;
+    }
+    if var("TERM").is_some_and(|term| TERMS.contains(&term.as_str())) {
+        return true;
+    }
+    var("VTE_VERSION")
+        .and_then(|version| version.parse::<u32>().ok())
+        .is_some_and(|version| version >= VTE_HYPERLINKS)
+}
+
+/// Whether native output should contain hyperlinks
+pub fn enabled(config: &LinksConfig) -> bool {
+    match config.mode {
+        HyperlinkMode::Always => true,
+        HyperlinkMode::Never => false,
+        HyperlinkMode::Auto => io::stdout().is_terminal() && detect(|name| env::var(name).ok()),
+    }
+}
+
+/// `text` as a hyperlink to `url`
+pub fn link(url: &str, text: &str) -> String {
+    format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", url, text)
+}
+
+/// Link to the task with `uuid`, if a URL for tasks is configured
+pub fn task_link(config: &LinksConfig, uuid: &str, text: &str) -> String {
+    match &config.task_url {
+        Some(url) => link(&url.replace("{uuid}", uuid), text),
+        None => text.to_string(),
+    }
+}
+
+/// Description of `task` with its URLs and files linked, followed by the ones in its
+/// annotations and, with a URL for tasks configured, a link to the task itself
+pub fn task_description(config: &LinksConfig, task: &Task) -> String {
+    let mut text = linkify(&task.description);
+    let annotated: Vec<String> = task
+        .annotations
+        .iter()
+        .flat_map(|annotation| annotation.description.split(' '))
+        .map(linkify)
+        .filter(|word| word.starts_with("\x1b]8;"))
+        .collect();
+    for link in annotated {
+        text.push(' ');
+        text.push_str(&link);
+    }
+    if config.task_url.is_some() {
+        let short = task.uuid.get(..8).unwrap_or(&task.uuid);
+        text.push_str(&format!(" ({})", task_link(config, &task.uuid, short)));
+    }
+    text
+}
+
+/// Turn URLs and absolute paths to existing files in `text` into hyperlinks
+pub fn linkify(text: &str) -> String {
+    text.split(' ')
+        .map(|word| {
+            if word.starts_with("https://") || word.starts_with("http://") {
+                link(word, word)
+            } else if word.starts_with('/') && Path::new(word).exists() {
+                link(&format!("file://{}", word), word)
+            } else {
+                word.to_string()
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn vars(pairs: &'static [(&'static str, &'static str)]) -> impl Fn(&str) -> Option<String> {
+        move |name| {
+            pairs
+                .iter()
+                .find(|(k, _)| *k == name)
+                .map(|(_, v)| v.to_string())
+        }
+    }
+
+    #[test]
+    fn test_detect() {
+        assert!(detect(vars(&[("TERM_PROGRAM", "WezTerm")])));
+        assert!(detect(vars(&[("VTE_VERSION", "7600")])));
+        assert!(!detect(vars(&[("VTE_VERSION", "4800")])));
+        assert!(!detect(vars(&[("TERM", "dumb"), ("WT_SESSION", "1")])));
+        assert!(!detect(vars(&[
+            ("FORCE_HYPERLINK", "0"),
+            ("WT_SESSION", "1")
+        ])));
+        assert!(!detect(vars(&[])));
+    }
+
+    #[test]
+    fn test_linkify() {
+        assert_eq!(
+            "read \x1b]8;;https://example.com\x1b\\https://example.com\x1b]8;;\x1b\\ today",
+            linkify("read https://example.com today")
+        );
+        assert_eq!("no links here", linkify("no links here"));
+    }
+
+    #[test]
+    fn test_task_description() {
+        let task: Task = serde_json::from_str(
+            r#"{"uuid": "0123456789ab", "description": "Fix it", "status": "pending",
+                "annotations": [{"entry": "20240101T000000Z", "description": "see https://x.org/1"}]}"#,
+        )
+        .unwrap();
+        let config = LinksConfig {
+            task_url: Some(String::from("https://tasks/{uuid}")),
+            ..Default::default()
+        };
+        assert_eq!(
+            "Fix it \x1b]8;;https://x.org/1\x1b\\https://x.org/1\x1b]8;;\x1b\\ (\x1b]8;;https://tasks/0123456789ab\x1b\\01234567\x1b]8;;\x1b\\)",
+            task_description(&config, &task)
+        );
+        assert_eq!(
+            "Fix it \x1b]8;;https://x.org/1\x1b\\https://x.org/1\x1b]8;;\x1b\\",
+            task_description(&LinksConfig::default(), &task)
+        );
+    }
+}
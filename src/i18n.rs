@@ -0,0 +1,133 @@
+use fluent_bundle::concurrent::FluentBundle;
+use fluent_bundle::{FluentArgs, FluentResource};
+use log::{debug, warn};
+use std::env;
+use std::fmt;
+use std::sync::OnceLock;
+use unic_langid::LanguageIdentifier;
+
+const DEFAULT_LOCALE: &str = "en";
+
+/// Translations compiled into the binary, by language
+const LOCALES: &[(&str, &str)] = &[
+    ("en", include_str!("../locales/en.ftl")),
+    ("de", include_str!("../locales/de.ftl")),
+];
+
+static BUNDLE: OnceLock<FluentBundle<FluentResource>> = OnceLock::new();
+
+fn bundle(language: &str) -> FluentBundle<FluentResource> {
+    let (language, source) = LOCALES
+        .iter()
+        .find(|(lang, _)| *lang == language)
+        .unwrap_or(&LOCALES[0]);
+    let langid: LanguageIdentifier = language.parse().expect("Invalid built-in locale");
+    let mut bundle = FluentBundle::new_concurrent(vec![langid]);
+    // Unicode isolation marks around arguments end up in terminals and tests as garbage
+    bundle.set_use_isolating(false);
+    let resource = FluentResource::try_new(source.to_string()).expect("Invalid built-in messages");
+    bundle
+        .add_resource(resource)
+        .expect("Duplicate built-in message");
+    bundle
+}
+
+/// Language of a POSIX locale such as `de_DE.UTF-8`
+fn language(locale: &str) -> Option<String> {
+    let name = locale.split(['.', '@']).next()?.replace('_', "-");
+    let langid: LanguageIdentifier = name.parse().ok()?;
+    Some(langid.language.as_str().to_string())
+}
+
+/// The user's locale, from the config or else the environment
+fn locale(configured: Option<&str>) -> Option<String> {
+    if let Some(locale) = configured {
+        return Some(locale.to_string());
+    }
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+        .iter()
+        .filter_map(|var| env::var(var).ok())
+        .find(|value| !value.is_empty())
+}
+
+/// Select the language of taskhelper's own messages. Messages are in English until this is called
+pub fn init(configured: Option<&str>) {
+    let language = locale(configured)
+        .as_deref()
+        .and_then(language)
+        .filter(|lang| LOCALES.iter().any(|(l, _)| l == lang))
+        .unwrap_or_else(|| String::from(DEFAULT_LOCALE));
+    debug!("Using locale '{}'", language);
+    let _ = BUNDLE.set(bundle(&language));
+}
+
+/// Translate message `id`, filling in `args`
+pub fn tr(id: &str, args: &[(&str, &dyn fmt::Display)]) -> String {
+    let bundle = BUNDLE.get_or_init(|| bundle(DEFAULT_LOCALE));
+    let Some(pattern) = bundle.get_message(id).and_then(|message| message.value()) else {
+        warn!("Missing message '{}'", id);
+        return id.to_string();
+    };
+    let mut fluent_args = FluentArgs::new();
+    for (name, value) in args {
+        fluent_args.set(*name, value.to_string());
+    }
+    let mut errors = Vec::new();
+    let message = bundle.format_pattern(pattern, Some(&fluent_args), &mut errors);
+    for error in errors {
+        warn!("Unable to format message '{}': {}", id, error);
+    }
+    message.into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_language() {
+        assert_eq!(Some(String::from("de")), language("de_DE.UTF-8"));
+        assert_eq!(Some(String::from("en")), language("en"));
+        assert_eq!(None, language(""));
+    }
+
+    #[test]
+    fn test_locales_complete() {
+        let english = bundle(DEFAULT_LOCALE);
+        let ids: Vec<&str> = LOCALES[0]
+            .1
+            .lines()
+            .filter_map(|line| line.split_once(" = "))
+            .map(|(id, _)| id)
+            .filter(|id| !id.starts_with(char::is_whitespace))
+            .collect();
+        assert!(!ids.is_empty());
+        for (language, _) in LOCALES {
+            let translated = bundle(language);
+            for id in &ids {
+                assert!(english.has_message(id));
+                assert!(
+                    translated.has_message(id),
+                    "'{}' missing '{}'",
+                    language,
+                    id
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_select_expression() {
+        let bundle = bundle("de");
+        let pattern = bundle.get_message("injected").unwrap().value().unwrap();
+        let mut args = FluentArgs::new();
+        args.set("scope", "project:x");
+        args.set("placement", "last-modifier");
+        let mut errors = Vec::new();
+        assert_eq!(
+            "+ project:x eingefügt als letzter Modifikator",
+            bundle.format_pattern(pattern, Some(&args), &mut errors)
+        );
+    }
+}
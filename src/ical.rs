@@ -0,0 +1,128 @@
+use crate::task::{export_tasks, format_date, Task};
+use crate::taskwarrior::Taskwarrior;
+use crate::{NAME, VERSION};
+use color_eyre::Result;
+use std::time::SystemTime;
+
+/// Longest line allowed in a calendar file, in bytes, before it's folded onto the next
+const LINE_LIMIT: usize = 75;
+
+/// Pending tasks with a date to put in the calendar
+const DATED_FILTER: &[&str] = &[
+    "status:pending",
+    "(",
+    "due.any:",
+    "or",
+    "scheduled.any:",
+    ")",
+];
+
+/// Pending tasks matching `args`, which may also hold rc overrides, that are due or scheduled
+pub fn query(task: &Taskwarrior, args: &[String]) -> Result<Vec<Task>> {
+    let mut export_args = args.to_vec();
+    export_args.extend(DATED_FILTER.iter().map(|arg| arg.to_string()));
+    export_tasks(task, &export_args)
+}
+
+/// `text` as a calendar property value, with its special characters escaped
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// `line` split into lines no longer than the limit, continuations starting with a space, each
+/// ending with CRLF
+fn fold(line: &str) -> String {
+    let mut out = String::new();
+    let mut len = 0;
+    for c in line.chars() {
+        if len + c.len_utf8() > LINE_LIMIT {
+            out.push_str("\r\n ");
+            len = 1;
+        }
+        out.push(c);
+        len += c.len_utf8();
+    }
+    out.push_str("\r\n");
+    out
+}
+
+/// An event at `time` for the `kind` of date of `task`
+fn event(task: &Task, kind: &str, time: SystemTime, stamp: SystemTime) -> String {
+    let mut lines = vec![
+        String::from("BEGIN:VEVENT"),
+        format!("UID:{}-{}@{}", task.uuid, kind.to_lowercase(), NAME),
+        format!("DTSTAMP:{}", format_date(stamp)),
+        format!("DTSTART:{}", format_date(time)),
+        format!("SUMMARY:{}: {}", kind, escape(&task.description)),
+    ];
+    if let Some(project) = &task.project {
+        lines.push(format!("CATEGORIES:{}", escape(project)));
+    }
+    lines.push(String::from("END:VEVENT"));
+    lines.iter().map(|line| fold(line)).collect()
+}
+
+/// `tasks` as an iCalendar feed with an event for each due and scheduled date. `now` stamps
+/// events of tasks that were never modified
+pub fn render(tasks: &[Task], now: SystemTime) -> String {
+    let mut out = String::new();
+    for line in [
+        String::from("BEGIN:VCALENDAR"),
+        String::from("VERSION:2.0"),
+        format!("PRODID:-//{}//{}//EN", NAME, VERSION),
+        String::from("CALSCALE:GREGORIAN"),
+    ] {
+        out.push_str(&fold(&line));
+    }
+    for task in tasks {
+        let stamp = task.modified.or(task.entry).unwrap_or(now);
+        for (kind, time) in [("Due", task.due), ("Scheduled", task.scheduled)] {
+            if let Some(time) = time {
+                out.push_str(&event(task, kind, time, stamp));
+            }
+        }
+    }
+    out.push_str(&fold("END:VCALENDAR"));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_render() {
+        let tasks: Vec<Task> = serde_json::from_str(
+            r#"[{"uuid": "a1", "description": "Release, finally", "status": "pending",
+                 "project": "repo", "modified": "20240101T120000Z", "due": "20240501T120000Z"}]"#,
+        )
+        .unwrap();
+        let ics = render(&tasks, SystemTime::UNIX_EPOCH);
+        let expected = "\
+BEGIN:VEVENT\r
+UID:a1-due@taskhelper\r
+DTSTAMP:20240101T120000Z\r
+DTSTART:20240501T120000Z\r
+SUMMARY:Due: Release\\, finally\r
+CATEGORIES:repo\r
+END:VEVENT\r
+";
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(ics.contains(expected));
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+    }
+
+    #[test]
+    fn test_fold() {
+        let line = format!("SUMMARY:{}", "é".repeat(40));
+        let folded = fold(&line);
+        let lines: Vec<&str> = folded.trim_end().split("\r\n").collect();
+        assert_eq!(2, lines.len());
+        assert!(lines.iter().all(|line| line.len() <= LINE_LIMIT));
+        assert_eq!(line, folded.replace("\r\n ", "").trim_end());
+    }
+}
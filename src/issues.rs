@@ -0,0 +1,130 @@
+use crate::detect::{sanitize_tag, Scope};
+use crate::task::{export_tasks, format_date, import_tasks};
+use crate::taskwarrior::Taskwarrior;
+use color_eyre::eyre::bail;
+use color_eyre::Result;
+use serde_json::json;
+use std::collections::HashSet;
+use std::time::SystemTime;
+
+/// Defines the UDAs mirrored tasks record their issue in, so the user's taskrc doesn't need them
+const ISSUE_OVERRIDES: &[&str] = &[
+    "rc.uda.issue.type=string",
+    "rc.uda.issue.label=Issue",
+    "rc.uda.issuekey.type=string",
+    "rc.uda.issuekey.label=Issue key",
+];
+
+/// An open issue in an issue tracker
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    /// Web page of the issue, identifying it across imports
+    pub url: String,
+    /// Key the tracker shows the issue by, e.g. `PROJ-12`
+    pub key: Option<String>,
+    pub title: String,
+    pub labels: Vec<String>,
+    /// Taskwarrior priority, H, M or L
+    pub priority: Option<String>,
+    /// Due date in the format taskwarrior uses in JSON
+    pub due: Option<String>,
+}
+
+/// Mirror the open `issues` of `tracker` into the project of `scope`: a task tagged with the
+/// tracker's name and annotated with the issue's web page, which `open` follows, is added for each
+/// issue not imported before. When `complete_closed` is set, `issues` are all the open ones and
+/// pending tasks whose issue isn't among them are completed. `rc` is prepended to each
+/// taskwarrior command
+pub fn mirror(
+    task: &Taskwarrior,
+    rc: &[String],
+    scope: &Scope,
+    tracker: &str,
+    issues: &[Issue],
+    complete_closed: bool,
+    dry_run: bool,
+) -> Result<()> {
+    let mut args: Vec<String> = ISSUE_OVERRIDES.iter().map(|o| o.to_string()).collect();
+    args.extend_from_slice(rc);
+    let mut export_args = args.clone();
+    export_args.extend([
+        scope.project().to_string(),
+        format!("+{}", tracker),
+        String::from("issue.any:"),
+    ]);
+    let mirrored = export_tasks(task, &export_args)?;
+    let imported: HashSet<&str> = mirrored
+        .iter()
+        .filter_map(|task| task.udas.get("issue")?.as_str())
+        .collect();
+    let open: HashSet<&str> = issues.iter().map(|issue| issue.url.as_str()).collect();
+
+    let now = SystemTime::now();
+    let mut new = Vec::new();
+    for issue in issues.iter().filter(|i| !imported.contains(i.url.as_str())) {
+        if dry_run {
+            println!("Would add '{}' ({})", issue.title, issue.url);
+        }
+        let mut tags = vec![tracker.to_string()];
+        tags.extend(issue.labels.iter().map(|label| sanitize_tag(label)));
+        let mut new_task = json!({
+            "description": issue.title,
+            "status": "pending",
+            "project": scope.project().name(),
+            "tags": tags,
+            "issue": issue.url,
+            "annotations": [{"entry": format_date(now), "description": issue.url}],
+        });
+        if let Some(key) = &issue.key {
+            new_task["issuekey"] = json!(key);
+        }
+        if let Some(priority) = &issue.priority {
+            new_task["priority"] = json!(priority);
+        }
+        if let Some(due) = &issue.due {
+            new_task["due"] = json!(due);
+        }
+        new.push(new_task);
+    }
+    let closed: Vec<_> = mirrored
+        .iter()
+        .filter(|task| complete_closed && task.status == "pending")
+        .filter(|task| {
+            let url = task.udas.get("issue").and_then(|url| url.as_str());
+            url.is_some_and(|url| !open.contains(url))
+        })
+        .collect();
+    if dry_run {
+        for task in &closed {
+            println!(
+                "Would complete '{}', its issue was closed",
+                task.description
+            );
+        }
+        return Ok(());
+    }
+
+    if !new.is_empty() {
+        import_tasks(task, &args, &new)?;
+    }
+    for closed in &closed {
+        let mut done_args = args.clone();
+        done_args.extend([
+            String::from("rc.verbose=nothing"),
+            closed.uuid.clone(),
+            String::from("done"),
+        ]);
+        if !task.command(&done_args).status()?.success() {
+            bail!("Unable to complete '{}'", closed.description);
+        }
+    }
+    match complete_closed {
+        true => println!(
+            "Added {} tasks for new issues, completed {} whose issues were closed",
+            new.len(),
+            closed.len()
+        ),
+        false => println!("Added {} tasks for new issues", new.len()),
+    }
+    Ok(())
+}
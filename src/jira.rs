@@ -0,0 +1,201 @@
+use crate::config::JiraConfig;
+use crate::detect::Scope;
+use crate::download::{self, base64};
+use crate::issues::{mirror, Issue};
+use crate::task::{format_date, parse_day};
+use crate::taskwarrior::Taskwarrior;
+use color_eyre::eyre::{bail, eyre};
+use color_eyre::Result;
+use serde::Deserialize;
+use std::env;
+
+const TRACKER: &str = "jira";
+
+/// Issues imported when neither the command line nor the config gives a query
+const DEFAULT_JQL: &str = "assignee = currentUser() AND resolution = Unresolved";
+
+const FIELDS: &str = "summary,priority,labels,duedate";
+
+const PER_PAGE: usize = 100;
+
+/// A page of search results. Jira Cloud pages with a token, Jira Data Center with an offset
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SearchPage {
+    issues: Vec<JiraIssue>,
+    total: Option<usize>,
+    next_page_token: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JiraIssue {
+    key: String,
+    fields: Fields,
+}
+
+#[derive(Debug, Deserialize)]
+struct Fields {
+    summary: String,
+    priority: Option<Named>,
+    #[serde(default)]
+    labels: Vec<String>,
+    duedate: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Named {
+    name: String,
+}
+
+/// Taskwarrior priority for the name of a Jira priority, None for ones it has no equivalent of
+fn priority(name: &str) -> Option<String> {
+    let priority = match name.to_lowercase().as_str() {
+        "highest" | "high" | "blocker" | "critical" => "H",
+        "medium" | "major" => "M",
+        "low" | "lowest" | "minor" | "trivial" => "L",
+        _ => return None,
+    };
+    Some(priority.to_string())
+}
+
+fn issue(base_url: &str, issue: JiraIssue) -> Issue {
+    Issue {
+        url: format!("{}/browse/{}", base_url, issue.key),
+        key: Some(issue.key),
+        title: issue.fields.summary,
+        labels: issue.fields.labels,
+        priority: issue.fields.priority.and_then(|p| priority(&p.name)),
+        due: issue
+            .fields
+            .duedate
+            .as_deref()
+            .and_then(parse_day)
+            .map(format_date),
+    }
+}
+
+/// Whether `base_url` is a Jira Cloud site, which searches with a different API
+fn is_cloud(base_url: &str) -> bool {
+    let host = base_url.split("://").last().unwrap_or(base_url);
+    let host = host.split(['/', ':']).next().unwrap_or(host);
+    host.ends_with(".atlassian.net")
+}
+
+/// Authorization header for the token in the environment: basic with the user for Jira Cloud,
+/// a bearer personal access token for Jira Data Center
+fn authorization(config: &JiraConfig) -> Option<String> {
+    let token = env::var(&config.token_var).ok().filter(|t| !t.is_empty())?;
+    match &config.user {
+        Some(user) => Some(format!(
+            "Basic {}",
+            base64(format!("{}:{}", user, token).as_bytes())
+        )),
+        None => Some(format!("Bearer {}", token)),
+    }
+}
+
+/// Issues matching `jql` on the Jira site at `base_url`
+fn search(base_url: &str, jql: &str, authorization: Option<&str>) -> Result<Vec<Issue>> {
+    let cloud = is_cloud(base_url);
+    let endpoint = match cloud {
+        true => "search/jql",
+        false => "search",
+    };
+    let url = format!("{}/rest/api/2/{}", base_url, endpoint);
+    let mut issues = Vec::new();
+    let mut next_page_token: Option<String> = None;
+    loop {
+        let per_page = PER_PAGE.to_string();
+        let start_at = issues.len().to_string();
+        let mut request = download::request(&url)
+            .query("jql", jql)
+            .query("fields", FIELDS)
+            .query("maxResults", &per_page);
+        request = match &next_page_token {
+            Some(token) => request.query("nextPageToken", token),
+            None if !cloud => request.query("startAt", &start_at),
+            None => request,
+        };
+        if let Some(authorization) = authorization {
+            request = request.set("Authorization", authorization);
+        }
+        let page: SearchPage = match request.call() {
+            Ok(response) => serde_json::from_reader(response.into_reader())?,
+            Err(ureq::Error::Status(code @ (400 | 401 | 403), _)) => bail!(
+                "Jira answered {} searching for '{}', check the query and the token",
+                code,
+                jql
+            ),
+            Err(e) => return Err(e.into()),
+        };
+        let fetched = page.issues.len();
+        issues.extend(page.issues.into_iter().map(|i| issue(base_url, i)));
+        next_page_token = page.next_page_token;
+        let done = match cloud {
+            true => next_page_token.is_none(),
+            false => fetched == 0 || page.total.is_none_or(|total| issues.len() >= total),
+        };
+        if done {
+            return Ok(issues);
+        }
+    }
+}
+
+/// Add a task to the project of `scope` for each issue `jql` matches, by default the query of the
+/// config, that wasn't imported before. `rc` is prepended to each taskwarrior command
+pub fn import(
+    task: &Taskwarrior,
+    rc: &[String],
+    scope: &Scope,
+    config: &JiraConfig,
+    jql: Option<&str>,
+    dry_run: bool,
+) -> Result<()> {
+    let base_url = config
+        .url
+        .as_deref()
+        .ok_or_else(|| eyre!("Set the url of the Jira site in the [jira] section of the config"))?
+        .trim_end_matches('/');
+    let jql = jql.or(config.jql.as_deref()).unwrap_or(DEFAULT_JQL);
+    let issues = search(base_url, jql, authorization(config).as_deref())?;
+    // The query needn't select every open issue, so tasks of the ones it leaves out are kept
+    mirror(task, rc, scope, TRACKER, &issues, false, dry_run)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use s_string::s;
+
+    #[test]
+    fn test_issue() {
+        let page: SearchPage = serde_json::from_str(
+            r#"{"issues": [{"key": "PROJ-12", "fields": {"summary": "Fix login",
+            "priority": {"name": "Highest"}, "labels": ["auth"], "duedate": "2024-05-01"}}]}"#,
+        )
+        .unwrap();
+        let issues: Vec<Issue> = page
+            .issues
+            .into_iter()
+            .map(|i| issue("https://jira.example.com", i))
+            .collect();
+        assert_eq!(
+            vec![Issue {
+                url: s!("https://jira.example.com/browse/PROJ-12"),
+                key: Some(s!("PROJ-12")),
+                title: s!("Fix login"),
+                labels: vec![s!("auth")],
+                priority: Some(s!("H")),
+                due: Some(s!("20240501T000000Z")),
+            }],
+            issues
+        );
+    }
+
+    #[test]
+    fn test_is_cloud() {
+        assert!(is_cloud("https://me.atlassian.net"));
+        assert!(!is_cloud("https://jira.example.com/jira"));
+    }
+}
@@ -0,0 +1,410 @@
+//! Alternative frontend for taskwarrior.
+//!
+//! The `taskhelper` binary is a thin wrapper around [`cli::main`]. The pieces it is built from
+//! are usable on their own: [`task_args`] parses taskwarrior filters and modifiers,
+//! [`detect`] finds the project a directory belongs to, and [`run`] runs taskwarrior the way
+//! the command line does.
+
+mod alias;
+mod args;
+mod branch;
+mod capabilities;
+pub mod cli;
+mod command_hooks;
+mod complete;
+pub mod config;
+mod csv;
+pub mod detect;
+mod download;
+mod explain;
+mod git;
+mod gitlab;
+mod hooks;
+mod hyperlink;
+mod i18n;
+mod ical;
+mod issues;
+mod jira;
+mod lock;
+mod logging;
+mod mangen;
+mod markdown;
+mod merged;
+mod notifications;
+mod picker;
+mod pipe;
+mod porcelain;
+mod prompt;
+#[cfg(unix)]
+mod pty;
+mod recall;
+mod repl;
+mod report;
+mod review;
+mod sandbox;
+mod scan;
+mod setup;
+mod shell_init;
+#[cfg(unix)]
+mod signals;
+mod standup;
+mod sync;
+mod table;
+pub mod task;
+pub mod task_args;
+mod taskopen;
+pub mod taskwarrior;
+mod timew;
+mod todotxt;
+mod toolchain;
+mod tui;
+mod update;
+mod watch;
+
+use color_eyre::eyre::bail;
+use color_eyre::Result;
+use config::{project_dirs, Config};
+use lock::{default_data_dir, default_taskrc};
+#[cfg(unix)]
+use log::warn;
+use log::{debug, trace};
+#[cfg(unix)]
+use nix::pty::openpty;
+use semver::{Version, VersionReq};
+use sha2::{Digest, Sha256};
+use std::env;
+use std::ffi::OsStr;
+use std::fmt;
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::str;
+use std::time::Duration;
+pub use taskwarrior::Taskwarrior;
+
+const TASK_BIN: &str = "task";
+/// Environment variable naming the taskwarrior binary, skipping the search of the $PATH
+const TASK_BIN_VAR: &str = "TASKHELPER_TASK_BIN";
+/// File under the cache directory remembering the taskwarrior found on the $PATH
+const TASK_BIN_CACHE: &str = "task-bin";
+/// Release suggested to `toolchain install` when no supported taskwarrior is found
+const RECOMMENDED_TASKWARRIOR_VERSION: &str = "3.1.0";
+/// Range of taskwarrior versions this program works with, unless the config overrides it
+const SUPPORTED_TASKWARRIOR_VERSIONS: &str = ">=3.0, <4";
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+const NAME: &str = env!("CARGO_PKG_NAME");
+
+// TODO: page long outputs (maybe make this a config option to enable/disable and set pager?)
+
+/// Resources used by a child process
+#[derive(Debug, Clone, Copy)]
+pub struct ResourceUsage {
+    /// Peak resident set size, in kilobytes
+    pub max_rss: i64,
+    pub user: Duration,
+    pub system: Duration,
+    pub wall: Duration,
+}
+
+impl fmt::Display for ResourceUsage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "wall {:.3}s, user {:.3}s, sys {:.3}s, max rss {} KiB",
+            self.wall.as_secs_f64(),
+            self.user.as_secs_f64(),
+            self.system.as_secs_f64(),
+            self.max_rss
+        )
+    }
+}
+
+/// How a child process ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exit {
+    /// Exited on its own with this code
+    Code(i32),
+    /// Killed by this signal
+    Signaled(i32),
+    /// Stopped by this signal
+    Stopped(i32),
+}
+
+impl Exit {
+    /// Exit code to report, following the shell convention of 128 + the signal number
+    pub fn code(self) -> i32 {
+        match self {
+            Exit::Code(code) => code,
+            Exit::Signaled(signal) | Exit::Stopped(signal) => 128 + signal,
+        }
+    }
+
+    pub fn success(self) -> bool {
+        self == Exit::Code(0)
+    }
+}
+
+impl fmt::Display for Exit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Exit::Code(code) => write!(f, "code {}", code),
+            Exit::Signaled(signal) => write!(f, "signal {}", signal),
+            Exit::Stopped(signal) => write!(f, "stop signal {}", signal),
+        }
+    }
+}
+
+/// Outcome of running taskwarrior
+#[derive(Debug)]
+pub struct CommandResult {
+    pub stdout: String,
+    pub stderr: String,
+    pub exit: Exit,
+    pub usage: ResourceUsage,
+}
+
+/// Run taskwarrior with `args`, attached to a pseudo terminal where the platform has them so it
+/// formats its output like it would for the user's terminal. When our stdout isn't a terminal,
+/// as when piped to another program, plain pipes are used so the output is formatted like
+/// taskwarrior's own would be. The output is passed on as it's written and also returned
+pub fn run(task: &Taskwarrior, args: &[String]) -> Result<CommandResult> {
+    if !io::stdout().is_terminal() {
+        debug!("stdout is not a terminal, running taskwarrior through pipes");
+        return pipe::run(task, args);
+    }
+    #[cfg(unix)]
+    {
+        // One pseudo terminal per stream, so stderr can be told apart while both look like a
+        // terminal
+        let winsize = Some(pty::winsize());
+        match openpty(&winsize, None).and_then(|out| Ok((out, openpty(&winsize, None)?))) {
+            Ok((out, err)) => return pty::run(task, args, out, err),
+            Err(e) => warn!(
+                "Unable to open a pseudo terminal, falling back to pipes: {}",
+                e
+            ),
+        }
+    }
+    pipe::run(task, args)
+}
+
+/// Run taskwarrior with `args` without showing its output, which is only returned
+pub fn run_quiet(task: &Taskwarrior, args: &[String]) -> Result<CommandResult> {
+    pipe::run_quiet(task, args)
+}
+
+/// Run taskwarrior with `args`, handing it our terminal for full screen programs like an editor.
+/// Without a terminal it runs like any other command
+pub fn run_interactive(task: &Taskwarrior, args: &[String]) -> Result<CommandResult> {
+    #[cfg(unix)]
+    if io::stdin().is_terminal() {
+        match openpty(&Some(pty::winsize()), None) {
+            Ok(pty) => return pty::run_interactive(task, args, pty),
+            Err(e) => warn!("Unable to open a pseudo terminal: {}", e),
+        }
+    }
+    run(task, args)
+}
+
+/// Version reported by `task --version`
+pub fn task_version(task: &Taskwarrior) -> Result<String> {
+    let output = task.command(&["--version"]).output()?.stdout;
+    let s = str::from_utf8(&output)?;
+    Ok(s.trim().to_string())
+}
+
+/// Parse the version `task --version` prints. Missing minor and patch numbers count as zero and
+/// anything after the numbers, like `-dev`, is ignored
+pub(crate) fn parse_version(version: &str) -> Result<Version> {
+    let numbers: Vec<&str> = version
+        .trim()
+        .split(|c: char| !c.is_ascii_digit() && c != '.')
+        .next()
+        .unwrap_or_default()
+        .split('.')
+        .filter(|n| !n.is_empty())
+        .collect();
+    let number = |i: usize| numbers.get(i).map_or(Ok(0), |n| n.parse::<u64>());
+    match numbers.len() {
+        1..=3 => Ok(Version::new(number(0)?, number(1)?, number(2)?)),
+        _ => bail!("Unable to parse taskwarrior version '{}'", version),
+    }
+}
+
+/// Whether taskwarrior `version` is in the semver range `supported`, like `>=3.0, <4`
+pub fn version_supported(version: &str, supported: &str) -> Result<bool> {
+    let Ok(range) = VersionReq::parse(supported) else {
+        bail!("Invalid taskwarrior version range '{}'", supported);
+    };
+    Ok(range.matches(&parse_version(version)?))
+}
+
+/// Find task bin on the path, make sure it isn't this program (this program can be invoked under the name 'task')
+fn find_taskwarrior(this_program: &Path) -> Result<PathBuf> {
+    let Ok(matches) = which::which_all(TASK_BIN) else {
+        bail!("Unable to find taskwarrior ('task') on the $PATH");
+    };
+
+    // This program is a multicall binary which mimics taskwarrior if called under the name 'task'.
+    // It is likely the first bin nammed 'task' on the $PATH is this program, so loop until we find another that isn't this program
+    for m in matches {
+        let m = fs::canonicalize(m)?;
+        trace!("Checking if '{}' is taskwarrior", m.display());
+        if m != this_program {
+            trace!("Using '{}' as taskwarrior", m.display());
+            return Ok(m);
+        } else {
+            trace!("Found ourself in the path. Skipping");
+        }
+    }
+    bail!("Unable to find taskwarrior ('task') on the $PATH");
+}
+
+/// Identifies the search for taskwarrior: the $PATH searched and the program skipped in it
+fn search_key(path: &OsStr, this_program: &Path) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(path.as_encoded_bytes());
+    hasher.update([0]);
+    hasher.update(this_program.as_os_str().as_encoded_bytes());
+    download::hex(&hasher.finalize())
+}
+
+/// Taskwarrior cached in `cache` by a search with `key`, if it's still there
+fn read_task_bin_cache(cache: &Path, key: &str) -> Option<PathBuf> {
+    let contents = fs::read_to_string(cache).ok()?;
+    let (cached_key, bin) = contents.trim_end().split_once('\n')?;
+    let bin = PathBuf::from(bin);
+    (cached_key == key && bin.is_file()).then_some(bin)
+}
+
+fn write_task_bin_cache(cache: &Path, key: &str, bin: &Path) -> io::Result<()> {
+    if let Some(parent) = cache.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(cache, format!("{}\n{}\n", key, bin.display()))
+}
+
+/// `find_taskwarrior`, remembering the result until the $PATH changes
+fn find_taskwarrior_cached(this_program: &Path) -> Result<PathBuf> {
+    let key = search_key(&env::var_os("PATH").unwrap_or_default(), this_program);
+    let cache = project_dirs().map(|dirs| dirs.cache_dir().join(TASK_BIN_CACHE));
+    if let Some(bin) = cache
+        .as_deref()
+        .and_then(|cache| read_task_bin_cache(cache, &key))
+    {
+        trace!("Using cached taskwarrior '{}'", bin.display());
+        return Ok(bin);
+    }
+    let bin = find_taskwarrior(this_program)?;
+    if let Some(cache) = cache {
+        if let Err(e) = write_task_bin_cache(&cache, &key, &bin) {
+            debug!("Unable to cache the taskwarrior path: {}", e);
+        }
+    }
+    Ok(bin)
+}
+
+/// Taskwarrior run by the backend `config` selects
+pub fn taskwarrior_for(config: &Config, this_program: &Path) -> Result<Taskwarrior> {
+    let task = match (&config.remote, &config.container) {
+        (Some(_), Some(_)) => bail!("Config cannot set both 'remote' and 'container'"),
+        (Some(remote), None) => {
+            debug!("Running taskwarrior on '{}' over ssh", remote.host);
+            Taskwarrior::ssh(&remote.host, &remote.task)
+        }
+        (None, Some(container)) => {
+            debug!("Running taskwarrior in container '{}'", container.name);
+            let mounts = container
+                .mounts
+                .iter()
+                .map(|(host, path)| (host.clone(), path.clone()))
+                .collect();
+            let mut task = Taskwarrior::container(
+                &container.runtime,
+                &container.name,
+                mounts,
+                &container.task,
+            );
+            // Point taskwarrior in the container at the user's own data and taskrc, not the image's
+            if let Some(dir) = default_data_dir() {
+                task = task.env("TASKDATA", dir);
+            }
+            if let Some(taskrc) = default_taskrc().filter(|taskrc| taskrc.is_file()) {
+                task = task.env("TASKRC", taskrc);
+            }
+            task
+        }
+        (None, None) => {
+            let env_bin = env::var_os(TASK_BIN_VAR).filter(|bin| !bin.is_empty());
+            match (env_bin, &config.task) {
+                (Some(bin), _) => {
+                    let bin = PathBuf::from(bin);
+                    debug!(
+                        "Using taskwarrior '{}' from ${}",
+                        bin.display(),
+                        TASK_BIN_VAR
+                    );
+                    Taskwarrior::new(bin)
+                }
+                (None, Some(bin)) => {
+                    debug!("Using taskwarrior '{}' from the config", bin.display());
+                    Taskwarrior::new(bin.clone())
+                }
+                (None, None) => match toolchain::preferred() {
+                    Some(bin) => {
+                        debug!("Using taskwarrior installed by 'toolchain install'");
+                        Taskwarrior::new(bin)
+                    }
+                    None => Taskwarrior::new(find_taskwarrior_cached(this_program)?),
+                },
+            }
+        }
+    };
+    Ok(task)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_exit_code() {
+        assert_eq!(3, Exit::Code(3).code());
+        assert_eq!(130, Exit::Signaled(2).code());
+        assert_eq!(147, Exit::Stopped(19).code());
+        assert!(Exit::Code(0).success());
+        assert!(!Exit::Signaled(15).success());
+    }
+
+    #[test]
+    fn test_task_bin_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = dir.path().join("cache").join(TASK_BIN_CACHE);
+        let bin = dir.path().join("task");
+        fs::write(&bin, "").unwrap();
+        let key = search_key(
+            OsStr::new("/usr/bin:/bin"),
+            Path::new("/usr/bin/taskhelper"),
+        );
+        assert_eq!(None, read_task_bin_cache(&cache, &key));
+        write_task_bin_cache(&cache, &key, &bin).unwrap();
+        assert_eq!(Some(bin.clone()), read_task_bin_cache(&cache, &key));
+        let moved = search_key(OsStr::new("/bin"), Path::new("/usr/bin/taskhelper"));
+        assert_eq!(None, read_task_bin_cache(&cache, &moved));
+        fs::remove_file(&bin).unwrap();
+        assert_eq!(None, read_task_bin_cache(&cache, &key));
+    }
+
+    #[test]
+    fn test_version_supported() {
+        assert!(version_supported("3.1.0", ">=3.0, <4").unwrap());
+        assert!(version_supported("3.4.1\n", ">=3.0, <4").unwrap());
+        assert!(version_supported("3.2.0-dev", ">=3.0, <4").unwrap());
+        assert!(!version_supported("2.6.2", ">=3.0, <4").unwrap());
+        assert!(!version_supported("4.0.0", ">=3.0, <4").unwrap());
+        assert!(version_supported("2.6", "~2.6").unwrap());
+        assert!(version_supported("garbage", ">=3.0").is_err());
+        assert!(version_supported("3.1.0", "three").is_err());
+    }
+}
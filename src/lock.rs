@@ -0,0 +1,126 @@
+use color_eyre::eyre::bail;
+use color_eyre::Result;
+use directories::BaseDirs;
+use log::{debug, info};
+#[cfg(unix)]
+use nix::errno::Errno;
+#[cfg(unix)]
+use nix::fcntl::{Flock, FlockArg};
+use std::env;
+use std::fs::{File, OpenOptions};
+use std::path::{Path, PathBuf};
+
+const LOCK_FILE: &str = "taskhelper.lock";
+
+/// Taskwarrior's data directory when it isn't overridden on the command line
+pub fn default_data_dir() -> Option<PathBuf> {
+    if let Some(dir) = env::var_os("TASKDATA") {
+        return Some(PathBuf::from(dir));
+    }
+    BaseDirs::new().map(|dirs| dirs.home_dir().join(".task"))
+}
+
+/// Taskwarrior's taskrc when it isn't overridden on the command line
+pub fn default_taskrc() -> Option<PathBuf> {
+    if let Some(taskrc) = env::var_os("TASKRC") {
+        return Some(PathBuf::from(taskrc));
+    }
+    BaseDirs::new().map(|dirs| dirs.home_dir().join(".taskrc"))
+}
+
+/// Lock file for `data_dir`, next to it so the data directory holds only taskwarrior's files
+fn lock_path(data_dir: &Path) -> PathBuf {
+    let mut name = data_dir.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(LOCK_FILE);
+    data_dir.with_file_name(name)
+}
+
+fn open_lock_file(path: &Path) -> Result<File> {
+    Ok(OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path)?)
+}
+
+fn waiting(data_dir: &Path) {
+    info!(
+        "Waiting for another taskhelper using '{}'",
+        data_dir.display()
+    );
+}
+
+/// Take an exclusive advisory lock for `data_dir`, waiting for other taskhelper processes to
+/// release theirs. The lock is held until the returned guard is dropped
+#[cfg(unix)]
+pub fn lock_data_dir(data_dir: &Path) -> Result<Flock<File>> {
+    let path = lock_path(data_dir);
+    let file = open_lock_file(&path)?;
+
+    let file = match Flock::lock(file, FlockArg::LockExclusiveNonblock) {
+        Ok(lock) => return Ok(lock),
+        Err((file, Errno::EWOULDBLOCK)) => file,
+        Err((_, e)) => bail!("Unable to lock '{}': {}", path.display(), e),
+    };
+
+    waiting(data_dir);
+    match Flock::lock(file, FlockArg::LockExclusive) {
+        Ok(lock) => {
+            debug!("Acquired lock '{}'", path.display());
+            Ok(lock)
+        }
+        Err((_, e)) => bail!("Unable to lock '{}': {}", path.display(), e),
+    }
+}
+
+/// Take an exclusive lock for `data_dir`, waiting for other taskhelper processes to release
+/// theirs. The lock is held until the returned file is dropped
+#[cfg(not(unix))]
+pub fn lock_data_dir(data_dir: &Path) -> Result<File> {
+    use std::fs::TryLockError;
+
+    let path = lock_path(data_dir);
+    let file = open_lock_file(&path)?;
+    match file.try_lock() {
+        Ok(()) => return Ok(file),
+        Err(TryLockError::WouldBlock) => {}
+        Err(TryLockError::Error(e)) => bail!("Unable to lock '{}': {}", path.display(), e),
+    }
+
+    waiting(data_dir);
+    match file.lock() {
+        Ok(()) => {
+            debug!("Acquired lock '{}'", path.display());
+            Ok(file)
+        }
+        Err(e) => bail!("Unable to lock '{}': {}", path.display(), e),
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_lock_path() {
+        assert_eq!(
+            PathBuf::from("/home/me/.task.taskhelper.lock"),
+            lock_path(Path::new("/home/me/.task"))
+        );
+    }
+
+    #[test]
+    fn test_lock_data_dir() {
+        let dir = TempDir::new().unwrap();
+        let data = dir.path().join("data");
+        let lock = lock_data_dir(&data).unwrap();
+        let path = dir.path().join("data.taskhelper.lock");
+        let file = File::open(&path).unwrap();
+        assert!(Flock::lock(file, FlockArg::LockExclusiveNonblock).is_err());
+        drop(lock);
+        let file = File::open(&path).unwrap();
+        assert!(Flock::lock(file, FlockArg::LockExclusiveNonblock).is_ok());
+    }
+}
@@ -0,0 +1,239 @@
+use crate::config::{state_dir, LogConfig, Rotation};
+use color_eyre::Result;
+use log::kv::{self, Key, Value, VisitSource};
+use log::{LevelFilter, Log, Record};
+use serde_json::{Map, Number};
+use std::fs::{self, Metadata};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Lowest level written to the JSON log
+const JSON_LEVEL: LevelFilter = LevelFilter::Info;
+
+const LOG_JSON_FLAG: &str = "--log-json";
+const LOG_FILE: &str = "taskhelper.log";
+const SECS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Where the log file is written
+pub fn log_file_path() -> Option<PathBuf> {
+    state_dir().map(|dir| dir.join(LOG_FILE))
+}
+
+/// `path` with `.n` appended, e.g. `taskhelper.log.1`
+fn rotated(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(format!(".{}", n));
+    PathBuf::from(name)
+}
+
+fn days_since_epoch(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() / SECS_PER_DAY)
+        .unwrap_or_default()
+}
+
+/// Whether the existing log file described by `metadata` should be rotated before logging to it
+fn needs_rotation(metadata: &Metadata, config: &LogConfig, now: SystemTime) -> bool {
+    if metadata.len() >= config.max_size {
+        return true;
+    }
+    match config.rotation {
+        Rotation::Size => false,
+        Rotation::Daily => metadata
+            .modified()
+            .is_ok_and(|modified| days_since_epoch(modified) != days_since_epoch(now)),
+    }
+}
+
+/// Move `path` to `path.1`, shifting older logs up and dropping any past `keep`
+fn rotate(path: &Path, keep: usize) -> io::Result<()> {
+    if keep == 0 {
+        return fs::remove_file(path);
+    }
+    let oldest = rotated(path, keep);
+    if oldest.exists() {
+        fs::remove_file(oldest)?;
+    }
+    for n in (1..keep).rev() {
+        let from = rotated(path, n);
+        if from.exists() {
+            fs::rename(from, rotated(path, n + 1))?;
+        }
+    }
+    fs::rename(path, rotated(path, 1))
+}
+
+/// Open the log file at `path` for appending, rotating it first if it's due
+fn open_log_file(path: &Path, config: &LogConfig) -> Result<fs::File> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if let Ok(metadata) = fs::metadata(path) {
+        if needs_rotation(&metadata, config, SystemTime::now()) {
+            rotate(path, config.keep)?;
+        }
+    }
+    Ok(fern::log_file(path)?)
+}
+
+/// Collects a record's key-values into a JSON object
+struct JsonFields<'a>(&'a mut Map<String, serde_json::Value>);
+
+impl<'kvs> VisitSource<'kvs> for JsonFields<'_> {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), kv::Error> {
+        let value = if let Some(n) = value.to_i64() {
+            serde_json::Value::Number(n.into())
+        } else if let Some(n) = value.to_u64() {
+            serde_json::Value::Number(n.into())
+        } else if let Some(n) = value.to_f64().and_then(Number::from_f64) {
+            serde_json::Value::Number(n)
+        } else if let Some(b) = value.to_bool() {
+            serde_json::Value::Bool(b)
+        } else {
+            serde_json::Value::String(value.to_string())
+        };
+        self.0.insert(key.to_string(), value);
+        Ok(())
+    }
+}
+
+/// `record` as one line of JSON, with its key-values as fields
+fn json_line(record: &Record, timestamp: f64) -> String {
+    let mut object = Map::new();
+    if let Some(ts) = Number::from_f64(timestamp) {
+        object.insert(String::from("ts"), serde_json::Value::Number(ts));
+    }
+    object.insert(String::from("level"), record.level().as_str().into());
+    object.insert(String::from("target"), record.target().into());
+    object.insert(String::from("message"), record.args().to_string().into());
+    let _ = record.key_values().visit(&mut JsonFields(&mut object));
+    serde_json::Value::Object(object).to_string()
+}
+
+/// Value of `--log-json`, found before the command line is parsed so logging is set up from the
+/// start
+pub fn log_json_path(args: &[String]) -> Option<PathBuf> {
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == LOG_JSON_FLAG {
+            return iter.next().map(PathBuf::from);
+        }
+        if let Some(path) = arg.strip_prefix("--log-json=") {
+            return Some(PathBuf::from(path));
+        }
+    }
+    None
+}
+
+/// Log to the console and the log file as `config` says and, if `json` is given, append
+/// structured events to that file
+pub fn init(json: Option<&Path>, config: &LogConfig) -> Result<()> {
+    let console = env_logger::Builder::new()
+        .filter_level(config.console_level)
+        .parse_env(env_logger::Env::default())
+        .build();
+    let mut level = console.filter();
+    let mut dispatch = fern::Dispatch::new().chain(Box::new(console) as Box<dyn Log>);
+
+    if let Some(path) = log_file_path().filter(|_| config.file) {
+        level = level.max(config.file_level);
+        let file = fern::Dispatch::new()
+            .level(config.file_level)
+            .format(|out, message, record| {
+                out.finish(format_args!(
+                    "[{} {} {}] {}",
+                    humantime::format_rfc3339_seconds(SystemTime::now()),
+                    record.level(),
+                    record.target(),
+                    message
+                ))
+            })
+            .chain(open_log_file(&path, config)?);
+        dispatch = dispatch.chain(file);
+    }
+
+    if let Some(path) = json {
+        level = level.max(JSON_LEVEL);
+        let json = fern::Dispatch::new()
+            .level(JSON_LEVEL)
+            .format(|out, _, record| {
+                let timestamp = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map(|d| d.as_secs_f64())
+                    .unwrap_or_default();
+                out.finish(format_args!("{}", json_line(record, timestamp)))
+            })
+            .chain(fern::log_file(path)?);
+        dispatch = dispatch.chain(json);
+    }
+
+    dispatch.level(level).apply()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_json_line() {
+        let kvs: &[(&str, Value)] = &[("code", Value::from(2)), ("event", Value::from("exit"))];
+        let record = Record::builder()
+            .args(format_args!("taskwarrior exited"))
+            .level(log::Level::Info)
+            .target("taskhelper")
+            .key_values(&kvs)
+            .build();
+        assert_eq!(
+            r#"{"code":2,"event":"exit","level":"INFO","message":"taskwarrior exited","target":"taskhelper","ts":1.5}"#,
+            json_line(&record, 1.5)
+        );
+    }
+
+    #[test]
+    fn test_rotate() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join(LOG_FILE);
+        for contents in ["first", "second", "third"] {
+            fs::write(&path, contents).unwrap();
+            rotate(&path, 2).unwrap();
+        }
+        assert!(!path.exists());
+        assert_eq!("third", fs::read_to_string(rotated(&path, 1)).unwrap());
+        assert_eq!("second", fs::read_to_string(rotated(&path, 2)).unwrap());
+        assert!(!rotated(&path, 3).exists());
+    }
+
+    #[test]
+    fn test_needs_rotation() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join(LOG_FILE);
+        fs::write(&path, "0123456789").unwrap();
+        let metadata = fs::metadata(&path).unwrap();
+        let mut config = LogConfig::default();
+        let now = SystemTime::now();
+        assert!(!needs_rotation(&metadata, &config, now));
+        config.rotation = Rotation::Daily;
+        assert!(!needs_rotation(&metadata, &config, now));
+        let tomorrow = now + std::time::Duration::from_secs(SECS_PER_DAY);
+        assert!(needs_rotation(&metadata, &config, tomorrow));
+        config.max_size = 10;
+        assert!(needs_rotation(&metadata, &config, now));
+    }
+
+    #[test]
+    fn test_log_json_path() {
+        let args = |a: &[&str]| a.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            Some(PathBuf::from("/tmp/log")),
+            log_json_path(&args(&["taskhelper", "--log-json", "/tmp/log", "list"]))
+        );
+        assert_eq!(
+            Some(PathBuf::from("/tmp/log")),
+            log_json_path(&args(&["taskhelper", "--log-json=/tmp/log"]))
+        );
+        assert_eq!(None, log_json_path(&args(&["taskhelper", "list"])));
+    }
+}
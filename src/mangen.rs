@@ -0,0 +1,183 @@
+use crate::args::Cli;
+use crate::{NAME, VERSION};
+use clap::CommandFactory;
+use clap_mangen::roff::{bold, italic, roman, Roff};
+use clap_mangen::Man;
+use color_eyre::Result;
+use log::info;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+
+/// A man page section, as `(title, paragraphs)`. Words in `*stars*` are set in bold
+type Section = (&'static str, &'static [&'static str]);
+
+const SECTIONS: &[Section] = &[
+    (
+        "PROJECT INJECTION",
+        &[
+            "When run inside a repository, the name of the repository's root directory is used \
+             as the project. The root is the closest directory containing one of the \
+             *project.markers*, by default *.git*, *.hg*, *.jj*, *.svn* or *.project*. Unless the command already names a project, it is passed to \
+             taskwarrior as *project:<name>*: as the first filter for reports, after the \
+             subcommand for add and log, and for commands that modify tasks as the first \
+             filter when they're given one, else as the last modifier. A filter may be several \
+             words ahead of the subcommand, as in *taskhelper +urgent project:foo start*.",
+            "*--project* <name> or *$TASKHELPER_PROJECT* names the project instead, and \
+             *-g*, *--global*, *--no-project* or an empty *$TASKHELPER_PROJECT* turns injection \
+             off for any subcommand. The flags take precedence over the variable.",
+            "A *.taskhelper.toml* file also marks a project root. It can set the *project* name, \
+             *tags* added to every command, and *auto = false* to never inject a project in that \
+             repository.",
+            "An explicit empty project, *project:*, opts out of injection. The *all* subcommand \
+             never injects a project.",
+            "With *--inject context* the project is passed as a temporary context named \
+             *taskhelper* instead, defined with *rc.context* overrides.",
+        ],
+    ),
+    (
+        "CONFIGURATION",
+        &[
+            "The config file is read from *$XDG_CONFIG_HOME/taskhelper/config.toml*. Command \
+             line flags take precedence over it.",
+            "*task*: taskwarrior binary to use instead of searching the $PATH. \
+             *$TASKHELPER_TASK_BIN* takes precedence over it. The binary found on the $PATH is \
+             cached until the $PATH changes.",
+            "*supported_versions*: semver range of taskwarrior versions that don't produce a \
+             warning, *>=3.0, <4* by default.",
+            "*taskrc*, *taskdata*: taskrc and data directory to use instead of taskwarrior's \
+             defaults.",
+            "*yes*: rc overrides *--yes* passes to answer taskwarrior's confirmations, by default \
+             *rc.confirmation=off* and *rc.recurrence.confirmation=yes*.",
+            "*report*: report run, scoped to the project, when no subcommand is given. Defaults \
+             to *next*.",
+            "*[filters]*: named filters, usable on the command line as *@name*.",
+            "*[defaults]*: filters added to a subcommand unless it already filters on the same \
+             attribute.",
+            "*[aliases]*: commands by name, such as *bug = \"add +bug priority:H\"*, expanded \
+             when the name is used in place of a subcommand. Unlike taskwarrior's aliases, the \
+             detected project is still injected.",
+            "*[template.NAME]*: *tags* and attributes, such as *priority = \"H\"*, given to \
+             tasks added with *add --template NAME*. Modifiers on the command line take \
+             precedence.",
+            "*[project]*: *auto* scopes commands to the detected project (on by default), \
+             *markers* lists files marking a project root, such as *Cargo.toml* or \
+             *package.json*, *injection* is the default for *--inject*, *subdir_tag* tags tasks \
+             with the subdirectory of the repository, *branch_tag* tags them with the git branch \
+             checked out, *hierarchy* appends the path from the \
+             root to the working directory to the project name, as in *repo.frontend*, *audit* \
+             prints a note whenever a project is injected, *isolate* gives each project its own \
+             task database.",
+            "*[databases]*: additional task databases, by name, included in merged views.",
+            "*[remote]*, *[container]*: run taskwarrior over ssh or in a container.",
+            "*[hooks.pre]*, *[hooks.post]*: shell commands run before and after taskwarrior, by \
+             subcommand, such as *done = \"notify-send done\"*. They run in the working \
+             directory, with *$TASKHELPER_COMMAND*, *$TASKHELPER_FILTER*, \
+             *$TASKHELPER_MODIFIERS*, *$TASKHELPER_PROJECT* (the detected project) and \
+             *$TASKHELPER_ARGS* (the taskwarrior command line) set, and *$TASKHELPER_EXIT* after \
+             taskwarrior. A failing pre hook stops the command.",
+            "*[table]*: *columns* shown by *--output table*, any attribute or UDA.",
+            "*[sync]*: *auto* synchronizes after every command that changes task data, *retries* \
+             and *backoff_ms* for failed syncs.",
+            "*[priority]*: *nice* level and *commands* always run at low priority.",
+            "*[timew]*: *track* starts and stops timewarrior tracking along with *start* and \
+             *stop*, *bin* is the timew binary to use instead of searching the $PATH. Leave \
+             *track* off when taskwarrior's on-modify.timewarrior hook is installed.",
+            "*[notify]*: *bin* is the notify-send compatible binary *notify* uses instead of \
+             searching the $PATH.",
+            "*[gitlab]*: *url* of a self-hosted instance *gitlab* imports issues from, by default \
+             the host of the *origin* remote, *token_var* is the environment variable holding an \
+             access token, *GITLAB_TOKEN* by default.",
+            "*[jira]*: *url* of the Jira site *import-jira* imports issues from, *user* the token \
+             belongs to on Jira Cloud (leave it out for a personal access token), *token_var* is \
+             the environment variable holding the token, *JIRA_TOKEN* by default, *jql* the query \
+             used when none is given.",
+            "*[log]*: *console_level* and *file_level* set how verbose logging is, *file*, \
+             *rotation*, *max_size* and *keep* control the log file.",
+        ],
+    ),
+    (
+        "MULTICALL",
+        &[
+            "When invoked as *task*, for example through a symlink, taskhelper passes its \
+             arguments to taskwarrior unchanged. This makes it possible to put taskhelper first \
+             on the $PATH without changing how scripts calling task behave.",
+        ],
+    ),
+];
+
+/// Render `paragraph` with the words in `*stars*` set in bold
+fn render_paragraph(roff: &mut Roff, paragraph: &str) {
+    let inlines: Vec<_> = paragraph
+        .split('*')
+        .enumerate()
+        .map(
+            |(i, text)| {
+                if i % 2 == 1 {
+                    bold(text)
+                } else {
+                    roman(text)
+                }
+            },
+        )
+        .collect();
+    roff.control("PP", []).text(inlines);
+}
+
+fn render_sections(w: &mut dyn Write) -> Result<()> {
+    let mut roff = Roff::new();
+    for (title, paragraphs) in SECTIONS {
+        roff.control("SH", [*title]);
+        for paragraph in *paragraphs {
+            render_paragraph(&mut roff, paragraph);
+        }
+    }
+    roff.control("SH", ["SEE ALSO"]).text([
+        bold("task"),
+        roman("(1), "),
+        italic("taskrc"),
+        roman("(5)"),
+    ]);
+    roff.to_writer(w)?;
+    Ok(())
+}
+
+/// Write man pages for taskhelper and each of its subcommands to `dir`
+pub fn generate(dir: &Path) -> Result<()> {
+    fs::create_dir_all(dir)?;
+    // The version is only set for the man page, `--version` is handled by taskhelper itself
+    let cmd = Cli::command()
+        .name(NAME)
+        .version(VERSION)
+        .disable_version_flag(true);
+    clap_mangen::generate_to(cmd.clone(), dir)?;
+
+    // Replace the generated top level page with one including the hand-written sections
+    let man = Man::new(cmd);
+    let path = dir.join(man.get_filename());
+    let mut file = File::create(&path)?;
+    man.render_title(&mut file)?;
+    man.render_name_section(&mut file)?;
+    man.render_synopsis_section(&mut file)?;
+    man.render_description_section(&mut file)?;
+    man.render_options_section(&mut file)?;
+    man.render_subcommands_section(&mut file)?;
+    render_sections(&mut file)?;
+    man.render_version_section(&mut file)?;
+    info!("Wrote man pages to '{}'", dir.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_sections() {
+        let mut out = Vec::new();
+        render_sections(&mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains(".SH \"PROJECT INJECTION\""));
+        assert!(out.contains("\\fBproject:<name>\\fR"));
+    }
+}
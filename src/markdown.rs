@@ -0,0 +1,139 @@
+use crate::args::GroupBy;
+use crate::table::date;
+use crate::task::{export_tasks, Task};
+use crate::taskwarrior::Taskwarrior;
+use color_eyre::Result;
+use std::collections::BTreeMap;
+
+/// Heading of the tasks without tags when grouping by tag
+const UNTAGGED: &str = "Untagged";
+
+/// Pending and completed tasks matching `args`, which may also hold rc overrides. Pending tasks
+/// come first, most urgent first, then completed ones, most recently completed first
+pub fn query(task: &Taskwarrior, args: &[String]) -> Result<Vec<Task>> {
+    let mut export_args = args.to_vec();
+    export_args.extend(["(", "status:pending", "or", "status:completed", ")"].map(String::from));
+    let mut tasks = export_tasks(task, &export_args)?;
+    tasks.sort_by(|a, b| {
+        let completed = |task: &Task| task.status == "completed";
+        completed(a)
+            .cmp(&completed(b))
+            .then(b.end.cmp(&a.end))
+            .then(b.urgency.total_cmp(&a.urgency))
+    });
+    Ok(tasks)
+}
+
+/// Checklist item for `task`, ticked when it's completed
+fn item(task: &Task) -> String {
+    let check = match task.status.as_str() {
+        "completed" => 'x',
+        _ => ' ',
+    };
+    let mut item = format!("- [{}] {}", check, task.description);
+    if task.due.is_some() && task.status != "completed" {
+        item.push_str(&format!(" (due {})", date(task.due)));
+    }
+    item
+}
+
+/// `tasks` as a Markdown checklist under headings by `group_by`, titled with `project` if given.
+/// Grouped by tag, a task is listed under each of its tags
+pub fn render(tasks: &[Task], group_by: GroupBy, project: Option<&str>) -> String {
+    let mut out = match project {
+        Some(project) => format!("# {}\n", project),
+        None => String::from("# Tasks\n"),
+    };
+    let groups: Vec<(String, Vec<&Task>)> = match group_by {
+        GroupBy::Status => [("Pending", "pending"), ("Completed", "completed")]
+            .into_iter()
+            .map(|(heading, status)| {
+                let tasks = tasks.iter().filter(|task| task.status == status).collect();
+                (heading.to_string(), tasks)
+            })
+            .collect(),
+        GroupBy::Tag => {
+            let mut by_tag: BTreeMap<&str, Vec<&Task>> = BTreeMap::new();
+            let mut untagged = Vec::new();
+            for task in tasks {
+                if task.tags.is_empty() {
+                    untagged.push(task);
+                }
+                for tag in &task.tags {
+                    by_tag.entry(tag.as_str()).or_default().push(task);
+                }
+            }
+            let mut groups: Vec<(String, Vec<&Task>)> = by_tag
+                .into_iter()
+                .map(|(tag, tasks)| (format!("+{}", tag), tasks))
+                .collect();
+            groups.push((UNTAGGED.to_string(), untagged));
+            groups
+        }
+    };
+    for (heading, tasks) in groups.iter().filter(|(_, tasks)| !tasks.is_empty()) {
+        out.push_str(&format!("\n## {}\n\n", heading));
+        for task in tasks {
+            out.push_str(&item(task));
+            out.push('\n');
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn tasks() -> Vec<Task> {
+        serde_json::from_str(
+            r#"[
+                {"id": 1, "uuid": "a", "description": "Write docs", "status": "pending",
+                 "tags": ["docs"], "due": "20240501T120000Z"},
+                {"id": 2, "uuid": "b", "description": "Release", "status": "pending"},
+                {"uuid": "c", "description": "Fix the build", "status": "completed",
+                 "tags": ["ci", "docs"]}
+            ]"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_render_by_status() {
+        let expected = "\
+# repo
+
+## Pending
+
+- [ ] Write docs (due 2024-05-01)
+- [ ] Release
+
+## Completed
+
+- [x] Fix the build
+";
+        assert_eq!(expected, render(&tasks(), GroupBy::Status, Some("repo")));
+    }
+
+    #[test]
+    fn test_render_by_tag() {
+        let expected = "\
+# Tasks
+
+## +ci
+
+- [x] Fix the build
+
+## +docs
+
+- [ ] Write docs (due 2024-05-01)
+- [x] Fix the build
+
+## Untagged
+
+- [ ] Release
+";
+        assert_eq!(expected, render(&tasks(), GroupBy::Tag, None));
+    }
+}
@@ -0,0 +1,245 @@
+use crate::config::{Config, LinksConfig};
+use crate::detect::isolated_data_dirs;
+use crate::hyperlink;
+use crate::i18n::tr;
+use crate::table;
+use crate::task::{export_report, Task};
+use crate::taskwarrior::Taskwarrior;
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
+use std::path::PathBuf;
+use std::thread;
+
+const DEFAULT_DATABASE: &str = "default";
+
+/// A task database a merged view reads from
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Database {
+    name: String,
+    /// Data location, or `None` for taskwarrior's own default
+    location: Option<PathBuf>,
+}
+
+/// Every database known to taskhelper: taskwarrior's default, the ones listed in the config and
+/// any isolated project databases
+pub fn databases(config: &Config) -> Result<Vec<Database>> {
+    let mut databases = vec![Database {
+        name: String::from(DEFAULT_DATABASE),
+        location: None,
+    }];
+    databases.extend(config.databases.iter().map(|(name, location)| Database {
+        name: name.clone(),
+        location: Some(location.clone()),
+    }));
+    if config.project.isolate {
+        databases.extend(
+            isolated_data_dirs()?
+                .into_iter()
+                .map(|(name, location)| Database {
+                    name,
+                    location: Some(location),
+                }),
+        );
+    }
+    Ok(databases)
+}
+
+fn export(
+    task: &Taskwarrior,
+    database: &Database,
+    args: &[String],
+    report: Option<&str>,
+) -> Result<Vec<Task>> {
+    let mut export_args = Vec::new();
+    if let Some(location) = &database.location {
+        task.reach(location)?;
+        export_args.push(format!("rc.data.location={}", location.display()));
+    }
+    export_args.extend_from_slice(args);
+    export_report(task, &export_args, report)
+        .map_err(|e| eyre!("Database '{}': {}", database.name, e))
+}
+
+/// Render `rows` as one `label: value` line per field, with a blank line between tasks
+fn render_plain(rows: &[(&str, Task)]) -> String {
+    let labels = [
+        "label-source",
+        "label-id",
+        "label-project",
+        "label-description",
+    ]
+    .map(|id| tr(id, &[]));
+    let records: Vec<Vec<(String, String)>> = rows
+        .iter()
+        .map(|(source, task)| {
+            let id = if task.id == 0 {
+                String::new()
+            } else {
+                task.id.to_string()
+            };
+            let values = [
+                source.to_string(),
+                id,
+                task.project.clone().unwrap_or_default(),
+                task.description.clone(),
+            ];
+            labels.iter().cloned().zip(values).collect()
+        })
+        .collect();
+    table::render_plain(&records)
+}
+
+/// Render `rows` as a table, with hyperlinks if `links` is given
+fn render(rows: &[(&str, Task)], links: Option<&LinksConfig>) -> String {
+    let header = [
+        "column-source",
+        "column-id",
+        "column-project",
+        "column-description",
+    ];
+    let header = header.map(|id| tr(id, &[]));
+    // Cells are padded by their plain text, escape sequences in hyperlinks take up no space
+    let mut table: Vec<[(String, String); 4]> = vec![header.map(|cell| (cell.clone(), cell))];
+    for (source, task) in rows {
+        let id = if task.id == 0 {
+            String::from("-")
+        } else {
+            task.id.to_string()
+        };
+        let project = task.project.clone().unwrap_or_default();
+        let (id_shown, description_shown) = match links {
+            Some(links) => (
+                hyperlink::task_link(links, &task.uuid, &id),
+                hyperlink::linkify(&task.description),
+            ),
+            None => (id.clone(), task.description.clone()),
+        };
+        table.push([
+            (source.to_string(), source.to_string()),
+            (id, id_shown),
+            (project.clone(), project),
+            (task.description.clone(), description_shown),
+        ]);
+    }
+
+    let mut widths = [0; 4];
+    for row in &table {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.0.chars().count());
+        }
+    }
+
+    let mut out = String::new();
+    for row in &table {
+        let line: Vec<String> = row
+            .iter()
+            .zip(widths)
+            .map(|((plain, shown), width)| {
+                let padding = width - plain.chars().count();
+                format!("{}{}", shown, " ".repeat(padding))
+            })
+            .collect();
+        out.push_str(line.join(" ").trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+/// Run the export for `filters` and `report` against each database concurrently and print the
+/// merged results with the database each task came from
+pub fn merged_view(
+    task: &Taskwarrior,
+    databases: &[Database],
+    filters: Vec<String>,
+    report: Option<&str>,
+    links: Option<&LinksConfig>,
+    plain: bool,
+) -> Result<()> {
+    let results: Vec<Result<Vec<Task>>> = thread::scope(|s| {
+        let handles: Vec<_> = databases
+            .iter()
+            .map(|database| s.spawn(|| export(task, database, &filters, report)))
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("Export thread panicked"))
+            .collect()
+    });
+
+    let mut rows = Vec::new();
+    for (database, tasks) in databases.iter().zip(results) {
+        for task in tasks? {
+            rows.push((database.name.as_str(), task));
+        }
+    }
+    if plain {
+        print!("{}", render_plain(&rows));
+    } else {
+        print!("{}", render(&rows, links));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_render() {
+        let tasks: Vec<Task> = serde_json::from_str(
+            r#"[
+                {"id": 1, "uuid": "a", "description": "first", "project": "home", "status": "pending"},
+                {"id": 0, "uuid": "b", "description": "done already", "status": "completed"}
+            ]"#,
+        )
+        .unwrap();
+        let rows: Vec<(&str, Task)> = ["default", "work"].into_iter().zip(tasks).collect();
+        let expected = "\
+SOURCE  ID PROJECT DESCRIPTION
+default 1  home    first
+work    -          done already
+";
+        assert_eq!(expected, render(&rows, None));
+    }
+
+    #[test]
+    fn test_render_plain() {
+        let tasks: Vec<Task> = serde_json::from_str(
+            r#"[
+                {"id": 1, "uuid": "a", "description": "first", "project": "home", "status": "pending"},
+                {"id": 0, "uuid": "b", "description": "done already", "status": "completed"}
+            ]"#,
+        )
+        .unwrap();
+        let rows: Vec<(&str, Task)> = ["default", "work"].into_iter().zip(tasks).collect();
+        let expected = "\
+Source: default
+ID: 1
+Project: home
+Description: first
+
+Source: work
+Description: done already
+";
+        assert_eq!(expected, render_plain(&rows));
+    }
+
+    #[test]
+    fn test_render_links() {
+        let tasks: Vec<Task> = serde_json::from_str(
+            r#"[{"id": 1, "uuid": "a", "description": "see https://x.org", "status": "pending"}]"#,
+        )
+        .unwrap();
+        let rows: Vec<(&str, Task)> = ["default"].into_iter().zip(tasks).collect();
+        let links = LinksConfig {
+            task_url: Some(String::from("https://tasks/{uuid}")),
+            ..Default::default()
+        };
+        let expected = "\
+SOURCE  ID PROJECT DESCRIPTION
+default \x1b]8;;https://tasks/a\x1b\\1\x1b]8;;\x1b\\          see \x1b]8;;https://x.org\x1b\\https://x.org\x1b]8;;\x1b\\
+";
+        assert_eq!(expected, render(&rows, Some(&links)));
+    }
+}
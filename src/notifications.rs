@@ -0,0 +1,112 @@
+use crate::config::NotifyConfig;
+use crate::task::{export_tasks, Task};
+use crate::taskwarrior::Taskwarrior;
+use crate::NAME;
+use color_eyre::eyre::bail;
+use color_eyre::Result;
+use log::debug;
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::SystemTime;
+
+const NOTIFY_SEND_BIN: &str = "notify-send";
+
+/// Why a task is notified about
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Due {
+    Overdue,
+    Today,
+}
+
+/// Summary and body of the notification for `task`
+fn message(task: &Task, due: Due) -> (String, String) {
+    let summary = match due {
+        Due::Overdue => format!("Overdue: {}", task.description),
+        Due::Today => format!("Due today: {}", task.description),
+    };
+    let mut body = Vec::new();
+    if let Some(project) = &task.project {
+        body.push(format!("project:{}", project));
+    }
+    if task.id != 0 {
+        body.push(format!("id {}", task.id));
+    }
+    (summary, body.join(", "))
+}
+
+/// Overdue tasks and tasks due today among the pending tasks matching `args`, soonest due first.
+/// Tasks due earlier today are only listed as overdue
+fn due_tasks(task: &Taskwarrior, args: &[String]) -> Result<Vec<(Task, Due)>> {
+    let mut found = Vec::new();
+    for (tag, due) in [("+OVERDUE", Due::Overdue), ("+DUETODAY", Due::Today)] {
+        let mut export_args = args.to_vec();
+        export_args.extend([String::from("status:pending"), String::from(tag)]);
+        for task in export_tasks(task, &export_args)? {
+            if !found.iter().any(|(t, _): &(Task, Due)| t.uuid == task.uuid) {
+                found.push((task, due));
+            }
+        }
+    }
+    found.sort_by_key(|(task, _)| task.due.unwrap_or(SystemTime::UNIX_EPOCH));
+    Ok(found)
+}
+
+fn send(bin: &PathBuf, summary: &str, body: &str, due: Due) -> Result<()> {
+    let urgency = match due {
+        Due::Overdue => "critical",
+        Due::Today => "normal",
+    };
+    let mut cmd = Command::new(bin);
+    cmd.args([
+        &format!("--app-name={}", NAME),
+        &format!("--urgency={}", urgency),
+        summary,
+        body,
+    ]);
+    debug!("Running command {:?}", cmd);
+    if !cmd.status()?.success() {
+        bail!("'{}' failed", bin.display());
+    }
+    Ok(())
+}
+
+/// Send a desktop notification for each overdue task and task due today among those matching
+/// `args`, which may also hold rc overrides. Returns how many were sent
+pub fn notify_due(task: &Taskwarrior, args: &[String], config: &NotifyConfig) -> Result<usize> {
+    let tasks = due_tasks(task, args)?;
+    if tasks.is_empty() {
+        return Ok(0);
+    }
+    let bin = match &config.bin {
+        Some(bin) => bin.clone(),
+        None => match which::which(NOTIFY_SEND_BIN) {
+            Ok(bin) => bin,
+            Err(_) => bail!("Unable to find '{}' on the $PATH", NOTIFY_SEND_BIN),
+        },
+    };
+    for (task, due) in &tasks {
+        let (summary, body) = message(task, *due);
+        send(&bin, &summary, &body, *due)?;
+    }
+    Ok(tasks.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use s_string::s;
+
+    #[test]
+    fn test_message() {
+        let task: Task = serde_json::from_str(
+            r#"{"id": 3, "uuid": "a", "description": "Renew the cert", "status": "pending",
+                "project": "infra", "due": "20240101T120000Z"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            (s!("Overdue: Renew the cert"), s!("project:infra, id 3")),
+            message(&task, Due::Overdue)
+        );
+    }
+}
@@ -0,0 +1,256 @@
+use crate::recall::Suggestions;
+use crate::task::Task;
+use color_eyre::Result;
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{List, ListDirection, ListState};
+use ratatui::{DefaultTerminal, Frame};
+
+/// Text a task is matched and listed by
+fn line(task: &Task) -> String {
+    let mut line = format!("{:>3} {}", task.id, task.description);
+    if let Some(project) = &task.project {
+        line.push_str(&format!(" project:{}", project));
+    }
+    for tag in &task.tags {
+        line.push_str(&format!(" +{}", tag));
+    }
+    line
+}
+
+/// Indices of `tasks` in the order they're offered for `command`. Tasks the previous command
+/// worked on come first when `command` usually follows it, then the ones most used recently
+fn rank(tasks: &[Task], command: &str, suggestions: &Suggestions) -> Vec<usize> {
+    let follows = suggestions.subcommands.contains(&command);
+    let rank = |task: &Task| {
+        let id = task.id.to_string();
+        let suggested = follows
+            && suggestions
+                .ids
+                .iter()
+                .any(|ids| ids.split(',').any(|i| i == id));
+        let score = suggestions.score(&id) + suggestions.score(&task.uuid);
+        (!suggested, -score)
+    };
+    let mut order: Vec<usize> = (0..tasks.len()).collect();
+    order.sort_by(|&a, &b| {
+        rank(&tasks[a])
+            .partial_cmp(&rank(&tasks[b]))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    order
+}
+
+/// Indices of the `lines` matching `query`, best match first. Equal matches keep their order
+fn matches(matcher: &SkimMatcherV2, lines: &[String], query: &str) -> Vec<usize> {
+    let mut scored: Vec<(i64, usize)> = lines
+        .iter()
+        .enumerate()
+        .filter_map(|(i, line)| matcher.fuzzy_match(line, query).map(|score| (score, i)))
+        .collect();
+    scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+    scored.into_iter().map(|(_, i)| i).collect()
+}
+
+/// Outcome of a key press
+#[derive(Debug, PartialEq, Eq)]
+enum Key {
+    Continue,
+    Picked(usize),
+    Cancelled,
+}
+
+/// The query typed so far and the tasks matching it
+struct Picker {
+    matcher: SkimMatcherV2,
+    /// Index of the task on each line
+    order: Vec<usize>,
+    lines: Vec<String>,
+    query: String,
+    matches: Vec<usize>,
+    list: ListState,
+}
+
+impl Picker {
+    fn new(tasks: &[Task], order: Vec<usize>) -> Self {
+        let mut picker = Picker {
+            matcher: SkimMatcherV2::default().smart_case(),
+            lines: order.iter().map(|&i| line(&tasks[i])).collect(),
+            order,
+            query: String::new(),
+            matches: Vec::new(),
+            list: ListState::default(),
+        };
+        picker.update();
+        picker
+    }
+
+    fn update(&mut self) {
+        self.matches = matches(&self.matcher, &self.lines, &self.query);
+        self.list.select((!self.matches.is_empty()).then_some(0));
+    }
+
+    fn on_key(&mut self, key: KeyEvent) -> Key {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => return Key::Cancelled,
+            KeyCode::Char('c') if ctrl => return Key::Cancelled,
+            KeyCode::Enter => {
+                return match self.list.selected().and_then(|i| self.matches.get(i)) {
+                    Some(&i) => Key::Picked(self.order[i]),
+                    None => Key::Continue,
+                };
+            }
+            // The best match is at the bottom, next to the query
+            KeyCode::Up => self.list.select_next(),
+            KeyCode::Char('p') if ctrl => self.list.select_next(),
+            KeyCode::Down => self.list.select_previous(),
+            KeyCode::Char('n') if ctrl => self.list.select_previous(),
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.update();
+            }
+            KeyCode::Char(c) => {
+                self.query.push(c);
+                self.update();
+            }
+            _ => {}
+        }
+        Key::Continue
+    }
+
+    fn draw(&mut self, frame: &mut Frame, prompt: &str) {
+        let [list_area, count_area, query_area] = Layout::vertical([
+            Constraint::Min(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let items = self.matches.iter().map(|&i| self.lines[i].as_str());
+        let list = List::new(items)
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+            .direction(ListDirection::BottomToTop);
+        frame.render_stateful_widget(list, list_area, &mut self.list);
+        let count = format!("  {}/{}", self.matches.len(), self.lines.len());
+        frame.render_widget(Line::raw(count), count_area);
+        let query = format!("{} > {}", prompt, self.query);
+        frame.set_cursor_position((query.chars().count() as u16, query_area.y));
+        frame.render_widget(Line::raw(query), query_area);
+    }
+}
+
+fn run(
+    terminal: &mut DefaultTerminal,
+    tasks: &[Task],
+    prompt: &str,
+    suggestions: &Suggestions,
+) -> Result<Option<usize>> {
+    let mut picker = Picker::new(tasks, rank(tasks, prompt, suggestions));
+    loop {
+        terminal.draw(|frame| picker.draw(frame, prompt))?;
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match picker.on_key(key) {
+            Key::Continue => {}
+            Key::Picked(i) => return Ok(Some(i)),
+            Key::Cancelled => return Ok(None),
+        }
+    }
+}
+
+/// Let the user pick one of `tasks` for the subcommand `command` by typing part of it, the
+/// `suggestions` for it selected first. None if they cancel
+pub fn pick<'a>(
+    tasks: &'a [Task],
+    command: &str,
+    suggestions: &Suggestions,
+) -> Result<Option<&'a Task>> {
+    let mut terminal = ratatui::init();
+    let res = run(&mut terminal, tasks, command, suggestions);
+    ratatui::restore();
+    Ok(res?.map(|i| &tasks[i]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use s_string::s;
+
+    fn tasks() -> Vec<Task> {
+        serde_json::from_str(
+            r#"[
+                {"id": 1, "uuid": "a", "description": "Write docs", "status": "pending",
+                 "project": "repo"},
+                {"id": 2, "uuid": "b", "description": "Fix the docs build", "status": "pending",
+                 "tags": ["ci"]},
+                {"id": 3, "uuid": "c", "description": "Release", "status": "pending"}
+            ]"#,
+        )
+        .unwrap()
+    }
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn test_line() {
+        let tasks = tasks();
+        assert_eq!("  1 Write docs project:repo", line(&tasks[0]));
+        assert_eq!("  2 Fix the docs build +ci", line(&tasks[1]));
+    }
+
+    #[test]
+    fn test_matches() {
+        let matcher = SkimMatcherV2::default().smart_case();
+        let lines: Vec<String> = tasks().iter().map(line).collect();
+        assert_eq!(vec![0, 1, 2], matches(&matcher, &lines, ""));
+        assert_eq!(vec![1], matches(&matcher, &lines, "ci"));
+        assert_eq!(Vec::<usize>::new(), matches(&matcher, &lines, "Docs"));
+    }
+
+    #[test]
+    fn test_rank() {
+        let tasks = tasks();
+        assert_eq!(vec![0, 1, 2], rank(&tasks, "done", &Suggestions::default()));
+        let mut suggestions = Suggestions::with_scores(&[("c", 2.0), ("2", 0.5)]);
+        assert_eq!(vec![2, 1, 0], rank(&tasks, "done", &suggestions));
+        suggestions.ids = vec![s!("1")];
+        suggestions.subcommands = vec!["stop", "done"];
+        assert_eq!(vec![0, 2, 1], rank(&tasks, "done", &suggestions));
+        assert_eq!(vec![2, 1, 0], rank(&tasks, "annotate", &suggestions));
+
+        let mut picker = Picker::new(&tasks, rank(&tasks, "done", &suggestions));
+        assert_eq!(Key::Picked(0), picker.on_key(key(KeyCode::Enter)));
+        picker.on_key(key(KeyCode::Up));
+        assert_eq!(Key::Picked(2), picker.on_key(key(KeyCode::Enter)));
+    }
+
+    #[test]
+    fn test_keys() {
+        let tasks = tasks();
+        let mut picker = Picker::new(&tasks, vec![0, 1, 2]);
+        for c in "rel".chars() {
+            assert_eq!(Key::Continue, picker.on_key(key(KeyCode::Char(c))));
+        }
+        assert_eq!(Key::Picked(2), picker.on_key(key(KeyCode::Enter)));
+        for _ in 0..3 {
+            picker.on_key(key(KeyCode::Backspace));
+        }
+        picker.on_key(key(KeyCode::Up));
+        assert_eq!(Key::Picked(1), picker.on_key(key(KeyCode::Enter)));
+        picker.on_key(key(KeyCode::Char('x')));
+        picker.on_key(key(KeyCode::Char('x')));
+        assert_eq!(Key::Continue, picker.on_key(key(KeyCode::Enter)));
+        assert_eq!(Key::Cancelled, picker.on_key(key(KeyCode::Esc)));
+    }
+}
@@ -0,0 +1,130 @@
+use crate::{CommandResult, Exit, ResourceUsage, Taskwarrior};
+use color_eyre::eyre::bail;
+use color_eyre::Result;
+use log::debug;
+use std::io::{self, Read, Write};
+use std::process::{Child, Stdio};
+use std::thread;
+use std::time::Instant;
+
+/// Wait for `child` to finish, returning how it ended and its resource usage. Only unix reports
+/// the resources used, elsewhere they're left at zero
+fn wait(child: &mut Child, started: Instant) -> Result<(Exit, ResourceUsage)> {
+    #[cfg(unix)]
+    {
+        crate::pty::wait_for(nix::unistd::Pid::from_raw(child.id() as i32), started)
+    }
+    #[cfg(not(unix))]
+    {
+        let status = child.wait()?;
+        let Some(code) = status.code() else {
+            bail!("Unexpected exit status: {:?}", status)
+        };
+        let usage = ResourceUsage {
+            max_rss: 0,
+            user: std::time::Duration::ZERO,
+            system: std::time::Duration::ZERO,
+            wall: started.elapsed(),
+        };
+        debug!("Child used {}", usage);
+        Ok((Exit::Code(code), usage))
+    }
+}
+
+/// Copy everything from `reader` to `writer` as it arrives, returning what was copied. A reader
+/// erroring counts as the end of the output. Once `writer` fails the rest is still read, so the
+/// child doesn't block on a full pipe
+pub fn tee<R: Read, W: Write>(mut reader: R, mut writer: W) -> Vec<u8> {
+    let mut copied = Vec::new();
+    let mut buffer = [0; 8192];
+    let mut writable = true;
+    loop {
+        let n = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(_) => break,
+        };
+        if writable {
+            writable = writer
+                .write_all(&buffer[..n])
+                .and_then(|_| writer.flush())
+                .is_ok();
+        }
+        copied.extend_from_slice(&buffer[..n]);
+    }
+    copied
+}
+
+/// Run taskwarrior with `args` through plain pipes, passing its output on as it's written. Works
+/// anywhere, but taskwarrior sees no terminal and formats its output accordingly
+pub fn run(task: &Taskwarrior, args: &[String]) -> Result<CommandResult> {
+    run_to(task, args, io::stdout(), io::stderr())
+}
+
+/// Run taskwarrior with `args` through plain pipes, only capturing its output
+pub fn run_quiet(task: &Taskwarrior, args: &[String]) -> Result<CommandResult> {
+    run_to(task, args, io::sink(), io::sink())
+}
+
+fn run_to<O, E>(task: &Taskwarrior, args: &[String], out: O, err: E) -> Result<CommandResult>
+where
+    O: Write + Send + 'static,
+    E: Write + Send + 'static,
+{
+    let mut cmd = task.command(args);
+    cmd.stdin(Stdio::inherit())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    debug!("Running command {:?}", cmd);
+
+    // taskwarrior stays in our process group so it can read from our terminal. The terminal
+    // signals it directly, signals sent only to us are passed on
+    #[cfg(unix)]
+    let signals = crate::signals::Forwarding::install()?;
+    let started = Instant::now();
+    let mut child = cmd.spawn()?;
+    #[cfg(unix)]
+    signals.forward_to(nix::unistd::Pid::from_raw(child.id() as i32), false);
+    let (Some(stdout), Some(stderr)) = (child.stdout.take(), child.stderr.take()) else {
+        bail!("Unable to capture the output of taskwarrior");
+    };
+    let stdout = thread::spawn(move || tee(stdout, out));
+    let stderr = thread::spawn(move || tee(stderr, err));
+    let (exit, usage) = wait(&mut child, started)?;
+
+    Ok(CommandResult {
+        stdout: String::from_utf8_lossy(&stdout.join().unwrap_or_default()).into_owned(),
+        stderr: String::from_utf8_lossy(&stderr.join().unwrap_or_default()).into_owned(),
+        exit,
+        usage,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    /// Fails like a closed stdout would
+    struct Closed;
+
+    impl Write for Closed {
+        fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_tee() {
+        let mut written = Vec::new();
+        assert_eq!(b"report".to_vec(), tee(&b"report"[..], &mut written));
+        assert_eq!(b"report".to_vec(), written);
+        assert_eq!(b"report".to_vec(), tee(&b"report"[..], Closed));
+    }
+}
@@ -0,0 +1,97 @@
+use crate::detect::Scope;
+use crate::task::Task;
+use crate::taskwarrior::Taskwarrior;
+use crate::{run_quiet, CommandResult};
+use color_eyre::Result;
+use serde::Serialize;
+
+/// Version of the JSON layout, bumped when a field changes meaning or goes away
+const FORMAT_VERSION: u32 = 1;
+
+/// A report as `--output json` prints it: the tasks along with what taskhelper made of the
+/// command. Stays the same across taskwarrior versions
+#[derive(Debug, Serialize)]
+pub struct Report<'a> {
+    pub format: u32,
+    pub report: &'a str,
+    /// Project found from the working directory, or named with `--project`
+    pub project: Option<&'a str>,
+    pub tasks: Vec<Task>,
+}
+
+/// Run `args`, an export, without showing its output. If taskwarrior fails its output is passed
+/// on and no tasks are returned
+pub fn run_export(
+    task: &Taskwarrior,
+    args: &[String],
+) -> Result<(CommandResult, Option<Vec<Task>>)> {
+    let mut export_args = vec![String::from("rc.json.array=on")];
+    export_args.extend_from_slice(args);
+    let res = run_quiet(task, &export_args)?;
+    if !res.exit.success() {
+        eprint!("{}{}", res.stdout, res.stderr);
+        return Ok((res, None));
+    }
+    let tasks = serde_json::from_str(&res.stdout)?;
+    Ok((res, Some(tasks)))
+}
+
+/// Run `args`, an export of `report`, and print the tasks as a `Report`
+pub fn print_report(
+    task: &Taskwarrior,
+    args: &[String],
+    report: &str,
+    scope: Option<&Scope>,
+) -> Result<CommandResult> {
+    let (res, tasks) = run_export(task, args)?;
+    if let Some(tasks) = tasks {
+        let report = Report {
+            format: FORMAT_VERSION,
+            report,
+            project: scope.map(|scope| scope.project().name().as_str()),
+            tasks,
+        };
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    #[test]
+    fn test_report() {
+        let tasks = serde_json::from_value(json!([{
+            "id": 1,
+            "uuid": "a1b2",
+            "description": "Write docs",
+            "status": "pending",
+            "urgency": 1.5
+        }]))
+        .unwrap();
+        let report = Report {
+            format: FORMAT_VERSION,
+            report: "next",
+            project: Some("repo"),
+            tasks,
+        };
+        assert_eq!(
+            json!({
+                "format": 1,
+                "report": "next",
+                "project": "repo",
+                "tasks": [{
+                    "id": 1,
+                    "uuid": "a1b2",
+                    "description": "Write docs",
+                    "status": "pending",
+                    "urgency": 1.5
+                }]
+            }),
+            serde_json::to_value(&report).unwrap()
+        );
+    }
+}
@@ -0,0 +1,92 @@
+use color_eyre::Result;
+use std::io::{BufRead, Write};
+
+/// Asks questions on `output` and reads the answers from `input`
+pub struct Prompt<R, W> {
+    pub input: R,
+    pub output: W,
+}
+
+impl<R: BufRead, W: Write> Prompt<R, W> {
+    /// Trimmed answer to `question`, None if the input ended
+    pub fn answer(&mut self, question: &str, hint: &str) -> Result<Option<String>> {
+        write!(self.output, "{} [{}] ", question, hint)?;
+        self.output.flush()?;
+        let mut answer = String::new();
+        if self.input.read_line(&mut answer)? == 0 {
+            return Ok(None);
+        }
+        Ok(Some(answer.trim().to_string()))
+    }
+
+    /// Trimmed answer to `question`, empty if nothing was entered or the input ended
+    pub fn read_answer(&mut self, question: &str, hint: &str) -> Result<String> {
+        Ok(self.answer(question, hint)?.unwrap_or_default())
+    }
+
+    /// Answer to `question`, or `default` if nothing was entered
+    pub fn ask(&mut self, question: &str, default: &str) -> Result<String> {
+        let answer = self.read_answer(question, default)?;
+        Ok(if answer.is_empty() {
+            default.to_string()
+        } else {
+            answer
+        })
+    }
+
+    pub fn confirm(&mut self, question: &str, default: bool) -> Result<bool> {
+        let hint = if default { "Y/n" } else { "y/N" };
+        loop {
+            match self.read_answer(question, hint)?.to_lowercase().as_str() {
+                "" => return Ok(default),
+                "y" | "yes" => return Ok(true),
+                "n" | "no" => return Ok(false),
+                _ => writeln!(self.output, "Please answer y or n")?,
+            }
+        }
+    }
+
+    pub fn say(&mut self, line: &str) -> Result<()> {
+        writeln!(self.output, "{}", line)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn prompt(input: &str) -> Prompt<&[u8], Vec<u8>> {
+        Prompt {
+            input: input.as_bytes(),
+            output: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_confirm() {
+        let mut p = prompt("\ny\nmaybe\nno\n");
+        assert!(p.confirm("First?", true).unwrap());
+        assert!(p.confirm("Second?", false).unwrap());
+        assert!(!p.confirm("Third?", true).unwrap());
+        assert_eq!(
+            "First? [Y/n] Second? [y/N] Third? [Y/n] Please answer y or n\nThird? [Y/n] ",
+            String::from_utf8(p.output).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_answer() {
+        let mut p = prompt("\n");
+        assert_eq!(Some(String::new()), p.answer("Next?", "y").unwrap());
+        assert_eq!(None, p.answer("Next?", "y").unwrap());
+    }
+
+    #[test]
+    fn test_ask() {
+        let mut p = prompt("\n/opt/bin\n");
+        assert_eq!("~/bin", p.ask("Where?", "~/bin").unwrap());
+        assert_eq!("/opt/bin", p.ask("Where?", "~/bin").unwrap());
+    }
+}
@@ -0,0 +1,365 @@
+use crate::pipe::tee;
+use crate::signals::Forwarding;
+use crate::{CommandResult, Exit, ResourceUsage, Taskwarrior};
+use color_eyre::eyre::bail;
+use color_eyre::Result;
+use log::{debug, trace};
+use nix::errno::Errno;
+use nix::pty::{OpenptyResult, Winsize};
+use nix::sys::signal::{sigaction, SaFlags, SigAction, SigHandler, SigSet, Signal};
+use nix::sys::termios::{cfmakeraw, tcgetattr, tcsetattr, LocalFlags, SetArg, Termios};
+use nix::sys::wait::WaitStatus;
+use nix::unistd::{close, dup, dup2, fork, setpgid, setsid, ForkResult, Pid};
+use std::fs::File;
+use std::io::{self, IsTerminal, Write};
+use std::os::fd::{AsRawFd, BorrowedFd, FromRawFd, RawFd};
+use std::os::unix::process::CommandExt;
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use terminal_size::{terminal_size, Height, Width};
+
+const DEFAULT_TERM_SIZE: (u16, u16) = (80, 24);
+
+/// Size of the terminal taskhelper runs in, for taskwarrior to format its output to
+pub fn winsize() -> Winsize {
+    let (cols, rows) = match terminal_size() {
+        Some((Width(w), Height(h))) => (w, h),
+        None => DEFAULT_TERM_SIZE,
+    };
+    Winsize {
+        ws_row: rows,
+        ws_col: cols,
+        ws_xpixel: 0,
+        ws_ypixel: 0,
+    }
+}
+
+fn timeval_to_duration(tv: libc::timeval) -> Duration {
+    Duration::from_secs(tv.tv_sec as u64) + Duration::from_micros(tv.tv_usec as u64)
+}
+
+/// Wait for `child` to change state, collecting its resource usage like `waitpid` can't
+fn wait_with_usage(child: Pid, started: Instant) -> Result<(WaitStatus, ResourceUsage)> {
+    let mut status: libc::c_int = 0;
+    let mut rusage: libc::rusage = unsafe { std::mem::zeroed() };
+    let res = unsafe { libc::wait4(child.as_raw(), &mut status, 0, &mut rusage) };
+    Errno::result(res)?;
+
+    let usage = ResourceUsage {
+        max_rss: rusage.ru_maxrss,
+        user: timeval_to_duration(rusage.ru_utime),
+        system: timeval_to_duration(rusage.ru_stime),
+        wall: started.elapsed(),
+    };
+    Ok((WaitStatus::from_raw(child, status)?, usage))
+}
+
+/// Pseudo terminal masters resized along with our terminal, -1 when unused
+static RESIZE_TARGETS: [AtomicI32; 2] = [AtomicI32::new(-1), AtomicI32::new(-1)];
+
+/// Copy the size of our terminal to the pseudo terminals. Only async-signal-safe calls are made
+extern "C" fn on_winch(_signal: libc::c_int) {
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    let found = [libc::STDOUT_FILENO, libc::STDERR_FILENO, libc::STDIN_FILENO]
+        .into_iter()
+        .any(|fd| unsafe { libc::ioctl(fd, libc::TIOCGWINSZ, &mut size) } == 0);
+    if !found {
+        return;
+    }
+    for target in &RESIZE_TARGETS {
+        let master = target.load(Ordering::Relaxed);
+        if master >= 0 {
+            unsafe { libc::ioctl(master, libc::TIOCSWINSZ, &size) };
+        }
+    }
+}
+
+/// Resizes pseudo terminals whenever our terminal is resized, until dropped. The kernel then lets
+/// whatever runs on them know with a SIGWINCH of its own
+struct ResizeForwarding {
+    previous: SigAction,
+}
+
+impl ResizeForwarding {
+    fn install(masters: &[RawFd]) -> Result<Self> {
+        for (target, master) in RESIZE_TARGETS.iter().zip(masters) {
+            target.store(*master, Ordering::Relaxed);
+        }
+        let action = SigAction::new(
+            SigHandler::Handler(on_winch),
+            SaFlags::SA_RESTART,
+            SigSet::empty(),
+        );
+        let previous = unsafe { sigaction(Signal::SIGWINCH, &action)? };
+        Ok(ResizeForwarding { previous })
+    }
+}
+
+impl Drop for ResizeForwarding {
+    fn drop(&mut self) {
+        let _ = unsafe { sigaction(Signal::SIGWINCH, &self.previous) };
+        for target in &RESIZE_TARGETS {
+            target.store(-1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Wait for `child` to finish, returning how it ended and its resource usage
+pub(crate) fn wait_for(child: Pid, started: Instant) -> Result<(Exit, ResourceUsage)> {
+    let (exit, usage) = match wait_with_usage(child, started) {
+        Ok((status, usage)) => match status {
+            WaitStatus::Exited(_, code) => (Exit::Code(code), usage),
+            WaitStatus::Signaled(_, signal, _) => (Exit::Signaled(signal as i32), usage),
+            WaitStatus::Stopped(_, signal) => (Exit::Stopped(signal as i32), usage),
+            _ => bail!("Unexpected wait status: {:?}", status),
+        },
+        Err(e) => {
+            bail!("wait4 failed: {}", e)
+        }
+    };
+    debug!("Child used {}", usage);
+    Ok((exit, usage))
+}
+
+/// Puts our terminal in raw mode, restoring its settings when dropped
+struct RawMode {
+    original: Termios,
+}
+
+impl RawMode {
+    fn enable() -> Result<Self> {
+        let stdin = io::stdin();
+        let original = tcgetattr(&stdin)?;
+        let mut raw = original.clone();
+        cfmakeraw(&mut raw);
+        tcsetattr(&stdin, SetArg::TCSADRAIN, &raw)?;
+        Ok(RawMode { original })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = tcsetattr(io::stdin(), SetArg::TCSADRAIN, &self.original);
+    }
+}
+
+/// Pass what's written to `master` on to `writer` as it arrives, returning all of it once the
+/// child has closed its side. Reading then fails with EIO rather than returning EOF, which `tee`
+/// takes as the end
+/// See https://stackoverflow.com/a/72159292
+fn forward<W: Write + Send + 'static>(master: RawFd, writer: W) -> JoinHandle<String> {
+    let f = unsafe { File::from_raw_fd(master) };
+    thread::spawn(move || String::from_utf8_lossy(&tee(f, writer)).into_owned())
+}
+
+/// How long the stdin forwarder waits for input before checking whether the child is done
+const STDIN_POLL_MS: libc::c_int = 100;
+/// Byte the pseudo terminal's line discipline turns into end of file
+const EOF_BYTE: u8 = 0x04;
+
+/// Pass what's typed on our stdin on to `master` until `done` is set. Our terminal already echoes
+/// it, so the pseudo terminal is expected not to. End of file is passed on as the EOF character
+fn forward_stdin(master: RawFd, done: Arc<AtomicBool>) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let mut master = unsafe { File::from_raw_fd(master) };
+        let mut buffer = [0u8; 1024];
+        while !done.load(Ordering::Relaxed) {
+            let mut fds = libc::pollfd {
+                fd: libc::STDIN_FILENO,
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            match unsafe { libc::poll(&mut fds, 1, STDIN_POLL_MS) } {
+                0 => continue,
+                n if n < 0 && Errno::last() == Errno::EINTR => continue,
+                n if n < 0 => break,
+                _ => {}
+            }
+            let n = unsafe {
+                libc::read(
+                    libc::STDIN_FILENO,
+                    buffer.as_mut_ptr() as *mut libc::c_void,
+                    buffer.len(),
+                )
+            };
+            let written = match n {
+                0 => master.write_all(&[EOF_BYTE]),
+                n if n > 0 => master.write_all(&buffer[..n as usize]),
+                _ if Errno::last() == Errno::EINTR => continue,
+                _ => break,
+            };
+            if written.is_err() {
+                break;
+            }
+        }
+    })
+}
+
+/// Stop `slave` echoing input back, for when our terminal echoes it already
+fn disable_echo(slave: RawFd) -> Result<()> {
+    let slave = unsafe { BorrowedFd::borrow_raw(slave) };
+    let mut attrs = tcgetattr(slave)?;
+    attrs.local_flags.remove(LocalFlags::ECHO);
+    tcsetattr(slave, SetArg::TCSANOW, &attrs)?;
+    Ok(())
+}
+
+/// Run taskwarrior with `args`, its stdout attached to `out` and its stderr to `err`, passing its
+/// output on as it's written. When our stdin is a terminal, what's typed is passed on through
+/// `out` so taskwarrior can prompt for confirmation. Otherwise taskwarrior reads our stdin
+/// directly, so piped input like `task import -` gets there unchanged
+pub fn run(
+    task: &Taskwarrior,
+    args: &[String],
+    out: OpenptyResult,
+    err: OpenptyResult,
+) -> Result<CommandResult> {
+    let mut cmd = task.command(args);
+
+    debug!("Running command {:?}", cmd);
+
+    let master = out.master.as_raw_fd();
+    let slave = out.slave.as_raw_fd();
+    let err_master = err.master.as_raw_fd();
+    let err_slave = err.slave.as_raw_fd();
+    // Stop file descriptors from closing on drop
+    std::mem::forget(out);
+    std::mem::forget(err);
+
+    let interactive = io::stdin().is_terminal();
+    if interactive {
+        disable_echo(slave)?;
+    }
+
+    let signals = Forwarding::install()?;
+    let started = Instant::now();
+    let output = match unsafe { fork() } {
+        Ok(res) => {
+            match res {
+                ForkResult::Parent { child, .. } => {
+                    // We are the parent
+                    trace!("Parent: spawned child with PID {}", child);
+                    // Also set by the child, whichever runs first. Signals forwarded before
+                    // then would miss the group. The child may already have exec'd or exited
+                    match setpgid(child, child) {
+                        Ok(()) | Err(Errno::EACCES) | Err(Errno::ESRCH) => {}
+                        Err(e) => return Err(e.into()),
+                    }
+                    signals.forward_to(child, true);
+                    close(slave)?;
+                    close(err_slave)?;
+                    let stdout = forward(master, io::stdout());
+                    let stderr = forward(err_master, io::stderr());
+                    let done = Arc::new(AtomicBool::new(false));
+                    let stdin = match interactive {
+                        true => Some(forward_stdin(dup(master)?, done.clone())),
+                        false => None,
+                    };
+                    let resize = ResizeForwarding::install(&[master, err_master])?;
+
+                    let (exit, usage) = wait_for(child, started)?;
+                    drop(resize);
+                    done.store(true, Ordering::Relaxed);
+                    if let Some(stdin) = stdin {
+                        let _ = stdin.join();
+                    }
+
+                    CommandResult {
+                        stdout: stdout.join().unwrap_or_default(),
+                        stderr: stderr.join().unwrap_or_default(),
+                        exit,
+                        usage,
+                    }
+                }
+                ForkResult::Child => {
+                    // We are the child
+                    // Set up the child process to use the PTY
+                    let _ = close(master);
+                    let _ = close(err_master);
+                    // Our own process group, so signals can be forwarded to everything
+                    // taskwarrior starts
+                    setpgid(Pid::from_raw(0), Pid::from_raw(0))
+                        .expect("Failed to start a process group");
+                    if interactive {
+                        dup2(slave, libc::STDIN_FILENO).expect("Failed to duplicate to stdin");
+                    }
+                    dup2(slave, libc::STDOUT_FILENO).expect("Failed to duplicate to stdout");
+                    dup2(err_slave, libc::STDERR_FILENO).expect("Failed to duplicate to stderr");
+
+                    let e = cmd.exec();
+                    // If we get this far, the exec failed
+                    bail!("Exec failed: {:?}", e);
+                }
+            }
+        }
+        Err(e) => bail!("Fork failed: {:?}", e),
+    };
+
+    Ok(output)
+}
+
+/// Run taskwarrior with `args` for a full screen program like the editor `task edit` starts. Our
+/// terminal is put in raw mode and everything typed and printed is copied between it and `pty`,
+/// which becomes taskwarrior's controlling terminal. Our stdin must be a terminal
+pub fn run_interactive(
+    task: &Taskwarrior,
+    args: &[String],
+    pty: OpenptyResult,
+) -> Result<CommandResult> {
+    let mut cmd = task.command(args);
+
+    debug!("Running command {:?} interactively", cmd);
+
+    let master = pty.master.as_raw_fd();
+    let slave = pty.slave.as_raw_fd();
+    // Stop file descriptors from closing on drop
+    std::mem::forget(pty);
+
+    let signals = Forwarding::install()?;
+    let started = Instant::now();
+    match unsafe { fork() } {
+        Ok(ForkResult::Parent { child, .. }) => {
+            trace!("Parent: spawned child with PID {}", child);
+            // The child's group is only made by its setsid, which fails once the parent made
+            // the child a group leader. Until then signals go to the child alone
+            signals.forward_to(child, true);
+            close(slave)?;
+            let raw = RawMode::enable()?;
+            let stdout = forward(master, io::stdout());
+            let done = Arc::new(AtomicBool::new(false));
+            let stdin = forward_stdin(dup(master)?, done.clone());
+            let resize = ResizeForwarding::install(&[master])?;
+
+            let (exit, usage) = wait_for(child, started)?;
+            drop(resize);
+            done.store(true, Ordering::Relaxed);
+            let _ = stdin.join();
+            let stdout = stdout.join().unwrap_or_default();
+            drop(raw);
+
+            Ok(CommandResult {
+                stdout,
+                stderr: String::new(),
+                exit,
+                usage,
+            })
+        }
+        Ok(ForkResult::Child) => {
+            // Start a new session with the PTY as its controlling terminal, so the editor can
+            // take it over
+            let _ = close(master);
+            setsid().expect("Failed to start a new session");
+            unsafe { libc::ioctl(slave, libc::TIOCSCTTY as _, 0) };
+            dup2(slave, libc::STDIN_FILENO).expect("Failed to duplicate to stdin");
+            dup2(slave, libc::STDOUT_FILENO).expect("Failed to duplicate to stdout");
+            dup2(slave, libc::STDERR_FILENO).expect("Failed to duplicate to stderr");
+
+            let e = cmd.exec();
+            // If we get this far, the exec failed
+            bail!("Exec failed: {:?}", e);
+        }
+        Err(e) => bail!("Fork failed: {:?}", e),
+    }
+}
@@ -0,0 +1,362 @@
+use crate::args::Cli;
+use crate::config::state_dir;
+use crate::detect::Scope;
+use crate::taskwarrior::shell_quote;
+use clap::CommandFactory;
+use color_eyre::eyre::bail;
+use color_eyre::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
+use std::fmt::Write as _;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const HISTORY_FILE: &str = "history.jsonl";
+/// Entries kept when the history file is trimmed
+const HISTORY_LIMIT: usize = 1000;
+
+/// Subcommands likely to be run next, after each subcommand
+const FOLLOW_UPS: &[(&str, &[&str])] = &[
+    ("add", &["start"]),
+    ("start", &["stop", "done", "annotate"]),
+    ("stop", &["done", "start"]),
+];
+
+const HOUR: u64 = 60 * 60;
+const DAY: u64 = 24 * HOUR;
+const WEEK: u64 = 7 * DAY;
+
+/// A taskhelper invocation, as typed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    /// Seconds since the Unix epoch
+    pub time: u64,
+    /// Directory the command ran in, which the project is detected from
+    pub cwd: PathBuf,
+    /// Project scope detected for the command, if any
+    pub scope: Option<String>,
+    /// Arguments after the program name
+    pub args: Vec<String>,
+}
+
+impl Entry {
+    fn command_line(&self) -> String {
+        self.args
+            .iter()
+            .map(|arg| shell_quote(arg))
+            .collect::<Vec<String>>()
+            .join(" ")
+    }
+}
+
+fn history_path() -> Option<PathBuf> {
+    state_dir().map(|dir| dir.join(HISTORY_FILE))
+}
+
+fn read(path: &Path) -> Result<Vec<Entry>> {
+    if !path.is_file() {
+        return Ok(Vec::new());
+    }
+    Ok(fs::read_to_string(path)?
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+fn append(path: &Path, entry: &Entry) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(entry)?)?;
+
+    // Trim once the file is well past the limit, rather than rewriting it on every command
+    if file.metadata()?.len() > (HISTORY_LIMIT * 512) as u64 {
+        let entries = read(path)?;
+        if entries.len() > 2 * HISTORY_LIMIT {
+            let mut kept = String::new();
+            for entry in &entries[entries.len() - HISTORY_LIMIT..] {
+                let _ = writeln!(kept, "{}", serde_json::to_string(entry)?);
+            }
+            fs::write(path, kept)?;
+        }
+    }
+    Ok(())
+}
+
+/// Add a command run in the current directory to the history
+pub fn record(args: &[String], scope: Option<&Scope>) -> Result<()> {
+    let Some(path) = history_path() else {
+        bail!("Unable to find a state directory");
+    };
+    let entry = Entry {
+        time: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default(),
+        cwd: env::current_dir()?,
+        scope: scope.map(|scope| scope.to_string()),
+        args: args.to_vec(),
+    };
+    append(&path, &entry)
+}
+
+/// Weight of a use `age` seconds ago, favoring recent ones like zoxide does
+fn recency_weight(age: u64) -> f64 {
+    match age {
+        age if age < HOUR => 4.0,
+        age if age < DAY => 2.0,
+        age if age < WEEK => 0.5,
+        _ => 0.25,
+    }
+}
+
+/// What the command history suggests doing next
+#[derive(Debug, Default, PartialEq)]
+pub struct Suggestions {
+    /// IDs the last command in the project worked on, if it has follow-ups
+    pub ids: Vec<String>,
+    /// Subcommands that usually follow the last command in the project
+    pub subcommands: Vec<&'static str>,
+    /// Frecency of each word used in previous commands
+    scores: HashMap<String, f64>,
+}
+
+impl Suggestions {
+    /// Suggestions scoring each word as given, for tests elsewhere
+    #[cfg(test)]
+    pub(crate) fn with_scores(scores: &[(&str, f64)]) -> Self {
+        Suggestions {
+            scores: scores.iter().map(|(w, s)| (w.to_string(), *s)).collect(),
+            ..Default::default()
+        }
+    }
+
+    /// How frequently and recently `word` was used
+    pub fn score(&self, word: &str) -> f64 {
+        self.scores.get(word).copied().unwrap_or_default()
+    }
+
+    /// Rank suggested subcommands first, then by frecency. The order is otherwise kept
+    pub fn rank(&self, words: &mut [String]) {
+        let rank = |word: &String| match self.subcommands.iter().position(|s| s == word) {
+            Some(i) => (0, i, 0.0),
+            None => (1, 0, -self.score(word)),
+        };
+        words.sort_by(|a, b| {
+            rank(a)
+                .partial_cmp(&rank(b))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+    }
+}
+
+fn is_id(word: &str) -> bool {
+    !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_ascii_digit() || c == ',' || c == '-')
+}
+
+fn suggest(
+    entries: &[Entry],
+    scope: Option<&str>,
+    subcommands: &[String],
+    now: u64,
+) -> Suggestions {
+    let mut suggestions = Suggestions::default();
+    for entry in entries {
+        let weight = recency_weight(now.saturating_sub(entry.time));
+        for arg in entry.args.iter().filter(|arg| !arg.starts_with("--")) {
+            *suggestions.scores.entry(arg.clone()).or_default() += weight;
+        }
+    }
+
+    let Some(last) = entries
+        .iter()
+        .rev()
+        .find(|entry| entry.scope.as_deref() == scope)
+    else {
+        return suggestions;
+    };
+    let Some(position) = last.args.iter().position(|arg| subcommands.contains(arg)) else {
+        return suggestions;
+    };
+    if let Some((_, follow_ups)) = FOLLOW_UPS
+        .iter()
+        .find(|(subcommand, _)| *subcommand == last.args[position])
+    {
+        suggestions.subcommands = follow_ups.to_vec();
+        suggestions.ids = last.args[..position]
+            .iter()
+            .filter(|arg| is_id(arg))
+            .cloned()
+            .collect();
+    }
+    suggestions
+}
+
+/// Suggestions from the command history for commands run in `scope`. An unreadable history
+/// gives none
+pub fn suggestions(scope: Option<&Scope>) -> Suggestions {
+    let entries = match history_path().map(|path| read(&path)) {
+        Some(Ok(entries)) => entries,
+        _ => return Suggestions::default(),
+    };
+    let subcommands: Vec<String> = Cli::command()
+        .get_subcommands()
+        .map(|sub| sub.get_name().to_string())
+        .collect();
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let scope = scope.map(|scope| scope.to_string());
+    suggest(&entries, scope.as_deref(), &subcommands, now)
+}
+
+/// Entries, numbered from 1, whose command line contains `search`
+fn matching<'a>(entries: &'a [Entry], search: Option<&str>) -> Vec<(usize, &'a Entry)> {
+    entries
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| (i + 1, entry))
+        .filter(|(_, entry)| search.is_none_or(|search| entry.command_line().contains(search)))
+        .collect()
+}
+
+/// The entry to re-run: the last one, the one numbered `selector` or the latest containing it
+fn select<'a>(entries: &'a [Entry], selector: Option<&str>) -> Option<&'a Entry> {
+    if let Some(n) = selector.and_then(|s| s.parse::<usize>().ok()) {
+        return n.checked_sub(1).and_then(|i| entries.get(i));
+    }
+    matching(entries, selector).last().map(|(_, entry)| *entry)
+}
+
+fn render(entries: &[(usize, &Entry)]) -> String {
+    let mut out = String::new();
+    for (n, entry) in entries {
+        let time = humantime::format_rfc3339_seconds(UNIX_EPOCH + Duration::from_secs(entry.time));
+        let _ = writeln!(
+            out,
+            "{:>5}  {}  {}  {}",
+            n,
+            time,
+            entry.scope.as_deref().unwrap_or("-"),
+            entry.command_line()
+        );
+    }
+    out
+}
+
+/// Previous commands containing `search`, oldest first
+pub fn list(search: Option<&str>) -> Result<String> {
+    let Some(path) = history_path() else {
+        return Ok(String::new());
+    };
+    let entries = read(&path)?;
+    Ok(render(&matching(&entries, search)))
+}
+
+/// Run a previous command again from the directory it ran in, returning its exit code
+pub fn rerun(selector: Option<&str>) -> Result<i32> {
+    let entries = match history_path() {
+        Some(path) => read(&path)?,
+        None => Vec::new(),
+    };
+    let Some(entry) = select(&entries, selector) else {
+        bail!("No matching command in the history");
+    };
+    if !entry.cwd.is_dir() {
+        bail!("'{}' no longer exists", entry.cwd.display());
+    }
+    eprintln!("{}", entry.command_line());
+    let status = Command::new(env::current_exe()?)
+        .args(&entry.args)
+        .current_dir(&entry.cwd)
+        .status()?;
+    Ok(status.code().unwrap_or(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use s_string::s;
+
+    fn entry(args: &[&str]) -> Entry {
+        Entry {
+            time: 0,
+            cwd: PathBuf::from("/src/foo"),
+            scope: Some(String::from("project:foo")),
+            args: args.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_select() {
+        let entries = vec![
+            entry(&["add", "write docs"]),
+            entry(&["list"]),
+            entry(&["add", "fix bug"]),
+            entry(&["next"]),
+        ];
+        assert_eq!(Some(&entries[3]), select(&entries, None));
+        assert_eq!(Some(&entries[1]), select(&entries, Some("2")));
+        assert_eq!(Some(&entries[2]), select(&entries, Some("add")));
+        assert_eq!(None, select(&entries, Some("0")));
+        assert_eq!(None, select(&entries, Some("done")));
+    }
+
+    #[test]
+    fn test_list() {
+        let entries = vec![entry(&["add", "write docs"]), entry(&["list"])];
+        assert_eq!(
+            "    1  1970-01-01T00:00:00Z  project:foo  add 'write docs'\n",
+            render(&matching(&entries, Some("docs")))
+        );
+    }
+
+    #[test]
+    fn test_suggest() {
+        let subcommands = vec![s!("add"), s!("done"), s!("list"), s!("start")];
+        let mut old = entry(&["list"]);
+        old.time = 0;
+        let mut recent = entry(&["3", "start"]);
+        recent.time = 2 * WEEK;
+        let mut elsewhere = entry(&["add", "thing"]);
+        elsewhere.time = 2 * WEEK;
+        elsewhere.scope = None;
+        let entries = vec![old, recent, elsewhere];
+
+        let suggestions = suggest(&entries, Some("project:foo"), &subcommands, 2 * WEEK);
+        assert_eq!(vec![s!("3")], suggestions.ids);
+        assert_eq!(vec!["stop", "done", "annotate"], suggestions.subcommands);
+        assert_eq!(4.0, suggestions.score("start"));
+        assert_eq!(0.25, suggestions.score("list"));
+
+        let mut words = vec![s!("add"), s!("done"), s!("list"), s!("start")];
+        suggestions.rank(&mut words);
+        assert_eq!(vec![s!("done"), s!("add"), s!("start"), s!("list")], words);
+
+        let suggestions = suggest(&entries, None, &subcommands, 2 * WEEK);
+        assert_eq!(vec!["start"], suggestions.subcommands);
+        assert!(suggestions.ids.is_empty());
+    }
+
+    #[test]
+    fn test_append() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join(HISTORY_FILE);
+        append(&path, &entry(&["list"])).unwrap();
+        append(&path, &entry(&["next"])).unwrap();
+        assert_eq!(
+            vec![entry(&["list"]), entry(&["next"])],
+            read(&path).unwrap()
+        );
+    }
+}
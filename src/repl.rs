@@ -0,0 +1,182 @@
+use crate::args::{join_leading_filter, Cli, Commands};
+use crate::cli::PROJECT_VAR;
+use crate::complete::line_candidates;
+use crate::config::state_dir;
+use crate::detect::Scope;
+use crate::recall::{suggestions, Suggestions};
+use crate::taskwarrior::Taskwarrior;
+use crate::NAME;
+use clap::Parser;
+use color_eyre::Result;
+use log::{debug, warn};
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::FileHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+use std::env;
+use std::fs;
+use std::process::Command;
+
+const HISTORY_FILE: &str = "shell-history";
+
+/// Completes subcommands, projects and tags like the shell completion scripts do
+struct ShellHelper {
+    task: Taskwarrior,
+    suggestions: Suggestions,
+}
+
+impl Completer for ShellHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let line = &line[..pos];
+        let start = line.rfind(char::is_whitespace).map_or(0, |i| i + 1);
+        Ok((start, line_candidates(&self.task, line, &self.suggestions)))
+    }
+}
+
+impl Hinter for ShellHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ShellHelper {}
+
+impl Validator for ShellHelper {}
+
+impl Helper for ShellHelper {}
+
+/// What to do with a line read from the prompt
+#[derive(Debug, PartialEq, Eq)]
+enum Line {
+    Empty,
+    Exit,
+    /// Run taskhelper with these args
+    Run(Vec<String>),
+    /// Report this and read the next line
+    Invalid(String),
+}
+
+/// Check the words of `line` as a taskhelper command line, following the `global` args
+fn parse_line(line: &str, global: &[String]) -> Line {
+    let words: Vec<String> = line.split_whitespace().map(String::from).collect();
+    match words.first().map(String::as_str) {
+        None => return Line::Empty,
+        Some("exit" | "quit") if words.len() == 1 => return Line::Exit,
+        _ => {}
+    }
+    let mut args = global.to_vec();
+    args.extend(words);
+    let mut argv = vec![NAME.to_string()];
+    argv.extend(args.iter().cloned());
+    match Cli::try_parse_from(join_leading_filter(argv)) {
+        Ok(cli) if matches!(cli.command, Some(Commands::Shell)) => {
+            Line::Invalid(String::from("Already in the shell"))
+        }
+        Ok(_) => Line::Run(args),
+        Err(e) => Line::Invalid(e.render().to_string().trim_end().to_string()),
+    }
+}
+
+fn run_line(args: &[String], project: Option<&str>) -> Result<()> {
+    let mut cmd = Command::new(env::current_exe()?);
+    // Scope every command to the project found when the shell started, or to none
+    cmd.args(args).env(PROJECT_VAR, project.unwrap_or_default());
+    debug!("Running command {:?}", cmd);
+    // The command stays in our process group so it can read from our terminal. The terminal
+    // signals it directly, signals sent only to us are passed on
+    #[cfg(unix)]
+    let signals = crate::signals::Forwarding::install()?;
+    let mut child = cmd.spawn()?;
+    #[cfg(unix)]
+    signals.forward_to(nix::unistd::Pid::from_raw(child.id() as i32), false);
+    child.wait()?;
+    Ok(())
+}
+
+/// Read commands from a prompt and run each like taskhelper was given them, scoped to the
+/// project of `scope` and following the `global` args. Lines are kept in a history across sessions
+pub fn run(task: &Taskwarrior, scope: Option<&Scope>, global: &[String]) -> Result<()> {
+    let mut editor: Editor<ShellHelper, FileHistory> = Editor::new()?;
+    editor.set_helper(Some(ShellHelper {
+        task: task.clone(),
+        suggestions: suggestions(scope),
+    }));
+    let project = scope.map(|scope| scope.project().name().as_str());
+    let history = state_dir().map(|dir| dir.join(HISTORY_FILE));
+    if let Some(history) = history.as_ref().filter(|path| path.is_file()) {
+        if let Err(e) = editor.load_history(history) {
+            warn!("Unable to read the shell history: {}", e);
+        }
+    }
+    let prompt = match project {
+        Some(project) => format!("{} ({})> ", NAME, project),
+        None => format!("{}> ", NAME),
+    };
+
+    loop {
+        let line = match editor.readline(&prompt) {
+            Ok(line) => line,
+            // Ctrl-C drops the line, Ctrl-D leaves
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(e.into()),
+        };
+        match parse_line(&line, global) {
+            Line::Empty => continue,
+            Line::Exit => break,
+            Line::Invalid(message) => eprintln!("{}", message),
+            Line::Run(args) => run_line(&args, project)?,
+        }
+        editor.add_history_entry(line)?;
+    }
+
+    if let Some(history) = history {
+        if let Some(parent) = history.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        editor.save_history(&history)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use s_string::s;
+
+    #[test]
+    fn test_parse_line() {
+        assert_eq!(Line::Empty, parse_line("  ", &[]));
+        assert_eq!(Line::Exit, parse_line("exit", &[]));
+        assert_eq!(
+            Line::Run(vec![
+                s!("--taskrc"),
+                s!("/tmp/rc"),
+                s!("add"),
+                s!("Fix"),
+                s!("the"),
+                s!("build"),
+                s!("+ci")
+            ]),
+            parse_line("add Fix the build +ci", &[s!("--taskrc"), s!("/tmp/rc")])
+        );
+        assert_eq!(
+            Line::Run(vec![s!("+bug"), s!("project:foo"), s!("list")]),
+            parse_line("+bug project:foo list", &[])
+        );
+        assert!(matches!(parse_line("shell", &[]), Line::Invalid(_)));
+        assert!(matches!(
+            parse_line("--output nope next", &[]),
+            Line::Invalid(_)
+        ));
+    }
+}
@@ -0,0 +1,303 @@
+use crate::args::Cli;
+use crate::config::{state_dir, Config};
+use crate::download::hex;
+use crate::hooks;
+use crate::lock::default_data_dir;
+use crate::logging::log_file_path;
+use crate::task_args::modifier::{abbreviates, is_builtin_attribute};
+use crate::taskwarrior::Taskwarrior;
+use crate::{task_version, taskwarrior_for, version_supported, NAME, VERSION};
+use clap::CommandFactory;
+use color_eyre::config::PanicHook;
+use color_eyre::eyre::bail;
+use color_eyre::Result;
+use sha2::{Digest, Sha256};
+use std::backtrace::Backtrace;
+use std::fmt::Write as _;
+use std::fs;
+use std::panic::{self, PanicHookInfo};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const CRASH_DIR: &str = "crashes";
+
+/// `word` as a hash, the same in every report so they can be compared
+fn hashed(word: &str) -> String {
+    format!(
+        "<redacted:{}>",
+        &hex(&Sha256::digest(word.as_bytes()))[..16]
+    )
+}
+
+/// Replace words of `args` that may hold private text, like task descriptions, with a hash.
+/// Flags, subcommands, IDs, tags and attributes are kept as they are, except for the value of
+/// `description:`. Words with whitespace, like quoted descriptions, are always hashed
+fn redact(args: &[String]) -> Vec<String> {
+    let cmd = Cli::command();
+    let is_subcommand = |word: &str| cmd.get_subcommands().any(|sub| sub.get_name() == word);
+    args.iter()
+        .enumerate()
+        .map(|(i, arg)| {
+            if i == 0 {
+                return arg.clone();
+            }
+            if arg.contains(char::is_whitespace) {
+                return hashed(arg);
+            }
+            if let Some((name, value)) = arg.split_once(':') {
+                // Attribute modifiers like `due.before:` are named by the part before the dot
+                let attribute = name.split('.').next().unwrap_or(name);
+                return match is_builtin_attribute(attribute) {
+                    true if abbreviates(attribute, "description") => {
+                        format!("{}:{}", name, hashed(value))
+                    }
+                    true => arg.clone(),
+                    false => hashed(arg),
+                };
+            }
+            let keep = arg.starts_with('-')
+                || arg.starts_with('+')
+                || arg.chars().all(|c| c.is_ascii_digit() || c == ',')
+                || is_subcommand(arg);
+            match keep {
+                true => arg.clone(),
+                false => hashed(arg),
+            }
+        })
+        .collect()
+}
+
+fn crash_dir() -> Option<PathBuf> {
+    state_dir().map(|dir| dir.join(CRASH_DIR))
+}
+
+fn crash_report(info: &PanicHookInfo, args: &[String]) -> String {
+    let mut report = String::new();
+    let _ = writeln!(report, "{} {} crash report", NAME, VERSION);
+    let _ = writeln!(
+        report,
+        "platform: {} {}",
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    );
+    let _ = writeln!(report, "argv: {}", redact(args).join(" "));
+    let _ = writeln!(report, "panic: {}", info);
+    let _ = writeln!(report, "\nbacktrace:\n{}", Backtrace::force_capture());
+    report
+}
+
+fn write_crash_report(info: &PanicHookInfo, args: &[String]) -> Result<PathBuf> {
+    let Some(dir) = crash_dir() else {
+        bail!("Unable to find a state directory");
+    };
+    fs::create_dir_all(&dir)?;
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    let path = dir.join(format!("crash-{}.txt", secs));
+    fs::write(&path, crash_report(info, args))?;
+    Ok(path)
+}
+
+/// Print panics with `hook` and write a redacted crash report for them to the state directory
+pub fn install_panic_hook(hook: PanicHook, args: &[String]) {
+    let args = args.to_vec();
+    panic::set_hook(Box::new(move |info| {
+        eprintln!("{}", hook.panic_report(info));
+        match write_crash_report(info, &args) {
+            Ok(path) => eprintln!(
+                "A crash report was written to '{}'. Please run '{} report-bug' and include its output when reporting this issue",
+                path.display(),
+                NAME
+            ),
+            Err(e) => eprintln!("Unable to write a crash report: {}", e),
+        }
+    }));
+}
+
+/// Note the hooks directory and any hook scripts taskwarrior won't run
+fn hook_problems(out: &mut String, task: &Taskwarrior) {
+    let dir = match hooks::hooks_dir(task) {
+        Ok(dir) => dir,
+        Err(e) => {
+            let _ = writeln!(out, "hooks: problem: {}", e);
+            return;
+        }
+    };
+    let _ = writeln!(out, "hooks: {}", dir.display());
+    match hooks::check(&dir) {
+        Ok(hooks) => {
+            for (name, problems) in hooks {
+                for problem in problems {
+                    let _ = writeln!(out, "hook: {} (problem: {})", name, problem);
+                }
+            }
+        }
+        Err(e) => {
+            let _ = writeln!(out, "hooks: problem: {}", e);
+        }
+    }
+}
+
+/// Describe taskhelper's setup, noting anything that stops it from working
+pub fn doctor(this_program: &Path) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "{}: {}", NAME, VERSION);
+    let _ = writeln!(
+        out,
+        "platform: {} {}",
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    );
+
+    let config = match Config::path() {
+        Some(path) if path.is_file() => {
+            let _ = writeln!(out, "config: {}", path.display());
+            Config::from_file(&path)
+        }
+        Some(path) => {
+            let _ = writeln!(
+                out,
+                "config: {} (not found, using defaults)",
+                path.display()
+            );
+            Ok(Config::default())
+        }
+        None => {
+            let _ = writeln!(out, "config: no home directory found, using defaults");
+            Ok(Config::default())
+        }
+    };
+    match config {
+        Ok(config) => match taskwarrior_for(&config, this_program) {
+            Ok(task) => {
+                let _ = writeln!(out, "backend: {}", task.describe());
+                match task_version(&task) {
+                    Ok(version) => match version_supported(&version, config.supported_versions()) {
+                        Ok(true) => {
+                            let _ = writeln!(out, "taskwarrior: {}", version);
+                        }
+                        Ok(false) => {
+                            let _ = writeln!(
+                                out,
+                                "taskwarrior: {} (problem: {} is supported)",
+                                version,
+                                config.supported_versions()
+                            );
+                        }
+                        Err(e) => {
+                            let _ = writeln!(out, "taskwarrior: {} (problem: {})", version, e);
+                        }
+                    },
+                    Err(e) => {
+                        let _ = writeln!(out, "taskwarrior: problem: {}", e);
+                    }
+                }
+                if task.is_local() {
+                    hook_problems(&mut out, &task);
+                }
+            }
+            Err(e) => {
+                let _ = writeln!(out, "backend: problem: {}", e);
+            }
+        },
+        Err(e) => {
+            let _ = writeln!(out, "config: problem: {}", e);
+        }
+    }
+
+    match default_data_dir() {
+        Some(dir) if dir.is_dir() => {
+            let _ = writeln!(out, "data: {}", dir.display());
+        }
+        Some(dir) => {
+            let _ = writeln!(out, "data: {} (not found)", dir.display());
+        }
+        None => {
+            let _ = writeln!(out, "data: unknown");
+        }
+    }
+    if let Some(path) = log_file_path() {
+        let _ = writeln!(out, "log: {}", path.display());
+    }
+    out
+}
+
+/// The most recently written crash report
+fn latest_crash_report() -> Result<Option<PathBuf>> {
+    let Some(dir) = crash_dir().filter(|dir| dir.is_dir()) else {
+        return Ok(None);
+    };
+    let mut reports = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        reports.push((entry.metadata()?.modified()?, entry.path()));
+    }
+    Ok(reports.into_iter().max().map(|(_, path)| path))
+}
+
+/// Doctor output and the latest crash report, ready to paste into an issue. The log isn't
+/// included as it isn't redacted
+pub fn report_bug(this_program: &Path) -> Result<String> {
+    let mut out = String::new();
+    let _ = writeln!(out, "## Doctor\n\n```\n{}```\n", doctor(this_program));
+    match latest_crash_report()? {
+        Some(path) => {
+            let report = fs::read_to_string(&path)?;
+            let _ = writeln!(out, "## Crash report\n\n```\n{}```\n", report);
+        }
+        None => {
+            let _ = writeln!(out, "## Crash report\n\nNone found\n");
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use s_string::s;
+
+    #[test]
+    fn test_redact() {
+        let args: Vec<String> = ["taskhelper", "add", "call", "+home", "due:tomorrow", "-v"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let redacted = redact(&args);
+        assert_eq!(
+            vec!["taskhelper", "add", "+home", "due:tomorrow", "-v"],
+            [&redacted[..2], &redacted[3..]].concat()
+        );
+        assert_eq!(hashed("call"), redacted[2]);
+        assert_eq!("<redacted:", &redacted[2][..10]);
+    }
+
+    #[test]
+    fn test_redact_colons() {
+        let args: Vec<String> = [
+            "taskhelper",
+            "add",
+            "call Bob: salary talk",
+            "description:secret",
+            "due.before:eow",
+            "https://example.com/private",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        assert_eq!(
+            vec![
+                s!("taskhelper"),
+                s!("add"),
+                hashed("call Bob: salary talk"),
+                format!("description:{}", hashed("secret")),
+                s!("due.before:eow"),
+                hashed("https://example.com/private"),
+            ],
+            redact(&args)
+        );
+    }
+}
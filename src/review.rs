@@ -0,0 +1,191 @@
+use crate::config::LinksConfig;
+use crate::hyperlink;
+use crate::prompt::Prompt;
+use crate::task::{export_tasks, Task};
+use crate::taskwarrior::Taskwarrior;
+use crate::{run, run_quiet};
+use color_eyre::Result;
+use std::io::{BufRead, Write};
+
+/// Defines the `reviewed` UDA for every command, so the user's taskrc doesn't need it
+const REVIEW_OVERRIDES: &[&str] = &[
+    "rc.uda.reviewed.type=date",
+    "rc.uda.reviewed.label=Reviewed",
+];
+
+/// Pending tasks never reviewed or last reviewed over a week ago
+const REVIEW_FILTER: &[&str] = &[
+    "status:pending",
+    "(",
+    "reviewed.none:",
+    "or",
+    "reviewed.before:now-1wk",
+    ")",
+];
+
+const CHOICES: &str = "Enter to mark reviewed, (m)odify, (c)omplete, (d)elete, (s)kip or (q)uit";
+
+/// What to do with the task under review
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Choice {
+    Reviewed,
+    Modify,
+    Complete,
+    Delete,
+    Skip,
+    Quit,
+}
+
+fn choice(answer: &str) -> Option<Choice> {
+    match answer.to_lowercase().as_str() {
+        "" => Some(Choice::Reviewed),
+        "m" | "modify" => Some(Choice::Modify),
+        "c" | "complete" => Some(Choice::Complete),
+        "d" | "delete" => Some(Choice::Delete),
+        "s" | "skip" => Some(Choice::Skip),
+        "q" | "quit" => Some(Choice::Quit),
+        _ => None,
+    }
+}
+
+/// Taskwarrior args carrying out `choice` on the task `uuid`, with `mods` when modifying. None
+/// for choices that leave the task alone
+fn choice_args(uuid: &str, choice: Choice, mods: &str) -> Option<Vec<String>> {
+    let mut args = match choice {
+        Choice::Reviewed | Choice::Modify => vec![uuid.to_string(), String::from("modify")],
+        Choice::Complete => vec![uuid.to_string(), String::from("done")],
+        // Confirmed by the user already
+        Choice::Delete => vec![
+            String::from("rc.confirmation=off"),
+            uuid.to_string(),
+            String::from("delete"),
+        ],
+        Choice::Skip | Choice::Quit => return None,
+    };
+    if choice == Choice::Modify {
+        args.extend(mods.split_whitespace().map(String::from));
+    }
+    if matches!(choice, Choice::Reviewed | Choice::Modify) {
+        args.push(String::from("reviewed:now"));
+    }
+    Some(args)
+}
+
+/// Ask what to do with `task` until the answer is understood. Quits at the end of the input
+fn ask<R: BufRead, W: Write>(prompt: &mut Prompt<R, W>, task: &Task) -> Result<(Choice, String)> {
+    loop {
+        let Some(answer) = prompt.answer(CHOICES, "m/c/d/s/q")? else {
+            return Ok((Choice::Quit, String::new()));
+        };
+        match choice(&answer) {
+            Some(Choice::Modify) => {
+                let mods = prompt.read_answer("Modifications", "e.g. due:tomorrow +next")?;
+                return Ok((Choice::Modify, mods));
+            }
+            Some(Choice::Delete) => {
+                let question = format!("Delete '{}'?", task.description);
+                if prompt.confirm(&question, false)? {
+                    return Ok((Choice::Delete, String::new()));
+                }
+            }
+            Some(choice) => return Ok((choice, String::new())),
+            None => prompt.say(&format!("Please answer with one of: {}", CHOICES))?,
+        }
+    }
+}
+
+/// Walk through the pending tasks matching `filter` that are due a review, showing each and
+/// asking what to do with it. `rc_overrides` are passed to every taskwarrior command. With
+/// `links`, tasks and the URLs in their descriptions are hyperlinked
+pub fn review<R: BufRead, W: Write>(
+    prompt: &mut Prompt<R, W>,
+    task: &Taskwarrior,
+    rc_overrides: &[String],
+    filter: &[String],
+    links: Option<&LinksConfig>,
+) -> Result<()> {
+    let mut rc: Vec<String> = REVIEW_OVERRIDES.iter().map(|o| o.to_string()).collect();
+    rc.extend_from_slice(rc_overrides);
+    let mut export_args = rc.clone();
+    export_args.extend_from_slice(filter);
+    export_args.extend(REVIEW_FILTER.iter().map(|arg| arg.to_string()));
+    let tasks = export_tasks(task, &export_args)?;
+    if tasks.is_empty() {
+        return prompt.say("Nothing to review");
+    }
+
+    let mut reviewed = 0;
+    for (i, current) in tasks.iter().enumerate() {
+        let description = match links {
+            Some(links) => hyperlink::task_description(links, current),
+            None => current.description.clone(),
+        };
+        prompt.say(&format!("\n[{}/{}] {}", i + 1, tasks.len(), description))?;
+        let mut info_args = rc.clone();
+        info_args.extend([current.uuid.clone(), String::from("information")]);
+        run(task, &info_args)?;
+
+        let (choice, mods) = ask(prompt, current)?;
+        if choice == Choice::Quit {
+            break;
+        }
+        let Some(action) = choice_args(&current.uuid, choice, &mods) else {
+            continue;
+        };
+        let mut action_args = rc.clone();
+        action_args.extend(action);
+        let res = run_quiet(task, &action_args)?;
+        if res.exit.success() {
+            reviewed += 1;
+        } else {
+            prompt.say(res.stderr.trim())?;
+        }
+    }
+    prompt.say(&format!("\nReviewed {} of {} tasks", reviewed, tasks.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use s_string::s;
+
+    #[test]
+    fn test_choice_args() {
+        assert_eq!(
+            Some(vec![s!("a1"), s!("modify"), s!("reviewed:now")]),
+            choice_args("a1", Choice::Reviewed, "")
+        );
+        assert_eq!(
+            Some(vec![
+                s!("a1"),
+                s!("modify"),
+                s!("due:tomorrow"),
+                s!("+next"),
+                s!("reviewed:now")
+            ]),
+            choice_args("a1", Choice::Modify, "due:tomorrow  +next")
+        );
+        assert_eq!(
+            Some(vec![s!("rc.confirmation=off"), s!("a1"), s!("delete")]),
+            choice_args("a1", Choice::Delete, "")
+        );
+        assert_eq!(None, choice_args("a1", Choice::Skip, ""));
+    }
+
+    #[test]
+    fn test_ask() {
+        let task: Task =
+            serde_json::from_str(r#"{"uuid": "a1", "description": "Old", "status": "pending"}"#)
+                .unwrap();
+        let mut prompt = Prompt {
+            input: "x\nd\nn\nm\npriority:L\n".as_bytes(),
+            output: Vec::new(),
+        };
+        assert_eq!(
+            (Choice::Modify, s!("priority:L")),
+            ask(&mut prompt, &task).unwrap()
+        );
+        assert_eq!((Choice::Quit, s!("")), ask(&mut prompt, &task).unwrap());
+    }
+}
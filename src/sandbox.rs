@@ -0,0 +1,36 @@
+use crate::run;
+use crate::taskwarrior::Taskwarrior;
+use color_eyre::eyre::bail;
+use color_eyre::Result;
+use log::debug;
+use std::path::Path;
+use tempfile::TempDir;
+
+/// Run taskwarrior with `args` against a throwaway task database, optionally seeded by importing
+/// `seed` first. Returns the exit code of the command
+pub fn run_sandbox(task: &Taskwarrior, seed: Option<&Path>, args: &[String]) -> Result<i32> {
+    // The throwaway database is made here, elsewhere taskwarrior would miss it or use real data
+    if !task.is_local() {
+        bail!("The sandbox needs taskwarrior running on this machine");
+    }
+    let dir = TempDir::with_prefix("taskhelper-sandbox-")?;
+    debug!("Using sandbox task database '{}'", dir.path().display());
+    let data_location = format!("rc.data.location={}", dir.path().display());
+
+    if let Some(seed) = seed {
+        let import = vec![
+            data_location.clone(),
+            String::from("import"),
+            seed.display().to_string(),
+        ];
+        let res = run(task, &import)?;
+        if !res.exit.success() {
+            bail!("Unable to seed sandbox from '{}'", seed.display());
+        }
+    }
+
+    let mut task_args = vec![data_location];
+    task_args.extend_from_slice(args);
+    let res = run(task, &task_args)?;
+    Ok(res.exit.code())
+}
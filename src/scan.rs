@@ -0,0 +1,234 @@
+use crate::detect::Scope;
+use crate::download::hex;
+use crate::git;
+use crate::task::{export_tasks, import_tasks};
+use crate::taskwarrior::Taskwarrior;
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
+use log::debug;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+/// Defines the UDAs tasks from comments are recorded with, so the user's taskrc doesn't need them
+const SCAN_OVERRIDES: &[&str] = &[
+    "rc.uda.codeline.type=string",
+    "rc.uda.codeline.label=Code",
+    "rc.uda.codehash.type=string",
+    "rc.uda.codehash.label=Code hash",
+];
+
+const KEYWORDS: &[&str] = &["TODO", "FIXME"];
+
+/// What has to come before a keyword on its line for it to be in a comment
+const COMMENT_MARKERS: &[&str] = &["//", "#", "/*", "--", "<!--"];
+
+/// Markers that only start a comment at the beginning of a line, like inside a block comment
+const LINE_COMMENT_MARKERS: &[&str] = &["*", ";"];
+
+const TAG: &str = "code";
+
+/// A TODO or FIXME comment found in the repository
+#[derive(Debug, PartialEq, Eq)]
+struct Comment {
+    /// Path relative to the root of the repository
+    path: String,
+    line: usize,
+    keyword: &'static str,
+    text: String,
+}
+
+impl Comment {
+    fn location(&self) -> String {
+        format!("{}:{}", self.path, self.line)
+    }
+
+    /// Identifies the comment across scans. The line isn't part of it, so a comment moved by
+    /// edits around it isn't imported again
+    fn hash(&self) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.path.as_bytes());
+        hasher.update([0]);
+        hasher.update(self.keyword.as_bytes());
+        hasher.update([0]);
+        hasher.update(self.text.as_bytes());
+        hex(&hasher.finalize())[..16].to_string()
+    }
+
+    fn description(&self) -> String {
+        match self.text.as_str() {
+            "" => format!("{} in {}", self.keyword, self.path),
+            text => text.to_string(),
+        }
+    }
+}
+
+/// Text of the comment after `keyword`, without an author in parentheses, a colon or the end of
+/// a block comment
+fn comment_text(rest: &str) -> String {
+    let mut rest = rest;
+    if rest.starts_with('(') {
+        if let Some(end) = rest.find(')') {
+            rest = &rest[end + 1..];
+        }
+    }
+    let rest = rest.trim_start_matches([':', '-']).trim();
+    let rest = rest.trim_end_matches("*/").trim_end_matches("-->");
+    rest.trim().to_string()
+}
+
+/// The keyword and its text when `line` has a TODO or FIXME comment
+fn find_comment(line: &str) -> Option<(&'static str, String)> {
+    for keyword in KEYWORDS {
+        let Some(start) = line.find(keyword) else {
+            continue;
+        };
+        let before = &line[..start];
+        let rest = &line[start + keyword.len()..];
+        let in_comment = COMMENT_MARKERS.iter().any(|marker| before.contains(marker))
+            || LINE_COMMENT_MARKERS
+                .iter()
+                .any(|marker| before.trim_start().starts_with(marker));
+        let whole_word = !before.ends_with(|c: char| c.is_alphanumeric() || c == '_')
+            && !rest.starts_with(|c: char| c.is_alphanumeric() || c == '_');
+        if in_comment && whole_word {
+            return Some((keyword, comment_text(rest)));
+        }
+    }
+    None
+}
+
+/// TODO and FIXME comments in the file at `path` holding `contents`
+fn comments(path: &str, contents: &str) -> Vec<Comment> {
+    contents
+        .lines()
+        .enumerate()
+        .filter_map(|(i, line)| {
+            let (keyword, text) = find_comment(line)?;
+            Some(Comment {
+                path: path.to_string(),
+                line: i + 1,
+                keyword,
+                text,
+            })
+        })
+        .collect()
+}
+
+/// Comments in the files git tracks in the repository at `root`, skipping ones that aren't text
+fn scan_repository(root: &Path) -> Result<Vec<Comment>> {
+    let files = git::git(Some(root), &["ls-files"])
+        .ok_or_else(|| eyre!("Unable to list the files of the git repository"))?;
+    let mut found = Vec::new();
+    for file in files.lines() {
+        let Ok(contents) = fs::read_to_string(root.join(file)) else {
+            debug!("Skipping '{}', it isn't text", file);
+            continue;
+        };
+        found.extend(comments(file, &contents));
+    }
+    Ok(found)
+}
+
+/// Add a task to the project of `scope` for each TODO and FIXME comment in the git repository in
+/// the current directory, tagged `+code` and with its location in the `codeline` UDA. Comments
+/// imported before are skipped. `rc` is prepended to each taskwarrior command
+pub fn scan(task: &Taskwarrior, rc: &[String], scope: &Scope, dry_run: bool) -> Result<()> {
+    let root = git::git(None, &["rev-parse", "--show-toplevel"])
+        .ok_or_else(|| eyre!("Scanning needs a git repository"))?;
+    let found = scan_repository(Path::new(&root))?;
+
+    let mut args: Vec<String> = SCAN_OVERRIDES.iter().map(|o| o.to_string()).collect();
+    args.extend_from_slice(rc);
+    let mut export_args = args.clone();
+    export_args.extend([scope.project().to_string(), String::from("codehash.any:")]);
+    let mut imported: HashSet<String> = export_tasks(task, &export_args)?
+        .into_iter()
+        .filter_map(|task| task.udas.get("codehash")?.as_str().map(String::from))
+        .collect();
+
+    let project = scope.project().name();
+    let mut new = Vec::new();
+    let mut skipped = 0;
+    for comment in &found {
+        let hash = comment.hash();
+        // Repeated comments in a file are only added once
+        if !imported.insert(hash.clone()) {
+            skipped += 1;
+            continue;
+        }
+        if dry_run {
+            println!(
+                "Would add '{}' ({})",
+                comment.description(),
+                comment.location()
+            );
+        }
+        new.push(json!({
+            "description": comment.description(),
+            "status": "pending",
+            "project": project,
+            "tags": [TAG],
+            "codeline": comment.location(),
+            "codehash": hash,
+        }));
+    }
+    if dry_run || new.is_empty() {
+        println!("{} new comments, {} already imported", new.len(), skipped);
+        return Ok(());
+    }
+
+    import_tasks(task, &args, &new)?;
+    println!(
+        "Added {} tasks from comments, {} already imported",
+        new.len(),
+        skipped
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use s_string::s;
+
+    #[test]
+    fn test_comments() {
+        let contents = "fn main() {\n    // TODO(adam): handle errors\n    let todo = \"TODO\";\n    /* FIXME */\n}\n# TODOS aren't comments\n";
+        assert_eq!(
+            vec![
+                Comment {
+                    path: s!("src/main.rs"),
+                    line: 2,
+                    keyword: "TODO",
+                    text: s!("handle errors"),
+                },
+                Comment {
+                    path: s!("src/main.rs"),
+                    line: 4,
+                    keyword: "FIXME",
+                    text: s!(""),
+                },
+            ],
+            comments("src/main.rs", contents)
+        );
+    }
+
+    #[test]
+    fn test_hash_ignores_line() {
+        let mut comment = Comment {
+            path: s!("src/lib.rs"),
+            line: 3,
+            keyword: "TODO",
+            text: s!("handle errors"),
+        };
+        let hash = comment.hash();
+        comment.line = 30;
+        assert_eq!(hash, comment.hash());
+        comment.text = s!("handle more errors");
+        assert_ne!(hash, comment.hash());
+    }
+}
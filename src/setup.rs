@@ -0,0 +1,254 @@
+use crate::args::{BundledHook, Shell};
+use crate::config::Config;
+use crate::prompt::Prompt;
+use crate::report::doctor;
+use crate::{
+    hooks, task_version, taskwarrior_for, version_supported, NAME, RECOMMENDED_TASKWARRIOR_VERSION,
+    TASK_BIN,
+};
+use color_eyre::eyre::bail;
+use color_eyre::Result;
+use directories::BaseDirs;
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+/// Project detection options chosen during setup
+#[derive(Debug, Default, PartialEq, Eq)]
+struct ProjectAnswers {
+    subdir_tag: bool,
+    isolate: bool,
+    audit: bool,
+}
+
+fn config_toml(answers: &ProjectAnswers) -> String {
+    format!(
+        "# Written by '{} setup'\n\n[project]\nsubdir_tag = {}\nisolate = {}\naudit = {}\n",
+        NAME, answers.subdir_tag, answers.isolate, answers.audit
+    )
+}
+
+/// Shell named by `$SHELL`
+fn current_shell() -> Option<Shell> {
+    let shell = env::var("SHELL").ok()?;
+    match Path::new(&shell).file_name()?.to_str()? {
+        "bash" => Some(Shell::Bash),
+        "zsh" => Some(Shell::Zsh),
+        "fish" => Some(Shell::Fish),
+        _ => None,
+    }
+}
+
+/// The rc file for `shell` and the line loading `shell-init` from it
+fn rc_line(shell: Shell, home: &Path) -> (PathBuf, String) {
+    match shell {
+        Shell::Bash => (
+            home.join(".bashrc"),
+            format!("eval \"$({} shell-init bash)\"", NAME),
+        ),
+        Shell::Zsh => (
+            home.join(".zshrc"),
+            format!("eval \"$({} shell-init zsh)\"", NAME),
+        ),
+        Shell::Fish => (
+            home.join(".config").join("fish").join("config.fish"),
+            format!("{} shell-init fish | source", NAME),
+        ),
+    }
+}
+
+fn add_rc_line(path: &Path, line: &str) -> Result<bool> {
+    let contents = fs::read_to_string(path).unwrap_or_default();
+    if contents.lines().any(|existing| existing.trim() == line) {
+        return Ok(false);
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "\n{}", line)?;
+    Ok(true)
+}
+
+#[cfg(unix)]
+fn link_task(dir: &Path, this_program: &Path) -> Result<PathBuf> {
+    let link = dir.join(TASK_BIN);
+    if link.symlink_metadata().is_ok() {
+        bail!("'{}' already exists", link.display());
+    }
+    fs::create_dir_all(dir)?;
+    std::os::unix::fs::symlink(this_program, &link)?;
+    Ok(link)
+}
+
+#[cfg(not(unix))]
+fn link_task(_dir: &Path, _this_program: &Path) -> Result<PathBuf> {
+    bail!("Symlinks are only created on unix");
+}
+
+fn wizard<R: BufRead, W: Write>(prompt: &mut Prompt<R, W>, this_program: &Path) -> Result<()> {
+    let Some(home) = BaseDirs::new().map(|dirs| dirs.home_dir().to_path_buf()) else {
+        bail!("Unable to find a home directory");
+    };
+    let config = Config::load().unwrap_or_default();
+
+    prompt.say(&format!("Welcome to {}!\n", NAME))?;
+    let task = match taskwarrior_for(&config, this_program) {
+        Ok(task) => {
+            match task_version(&task) {
+                Ok(version)
+                    if version_supported(&version, config.supported_versions()).unwrap_or(false) =>
+                {
+                    prompt.say(&format!("Found taskwarrior {} ({})", version, task.describe()))?
+                }
+                Ok(version) => prompt.say(&format!(
+                    "Found taskwarrior {}, but {} is supported. Run '{} toolchain install {}' to get it",
+                    version,
+                    config.supported_versions(),
+                    NAME,
+                    RECOMMENDED_TASKWARRIOR_VERSION
+                ))?,
+                Err(e) => prompt.say(&format!("Unable to run taskwarrior: {}", e))?,
+            }
+            Some(task)
+        }
+        Err(e) => {
+            prompt.say(&format!(
+                "{}. Install it, or run '{} toolchain install {}'",
+                e, NAME, RECOMMENDED_TASKWARRIOR_VERSION
+            ))?;
+            None
+        }
+    };
+
+    prompt.say(
+        "\nProjects are detected from the repository you run commands in. Add files such as \
+         Cargo.toml to 'markers' under [project] in the config file to detect more kinds of \
+         project.",
+    )?;
+    let answers = ProjectAnswers {
+        subdir_tag: prompt.confirm(
+            "Tag tasks with the top level directory of the repository you're in?",
+            false,
+        )?,
+        isolate: prompt.confirm("Give each project its own task database?", false)?,
+        audit: prompt.confirm(
+            "Print a note whenever the project is added to a command?",
+            false,
+        )?,
+    };
+    match Config::path() {
+        Some(path) => {
+            let write = !path.exists()
+                || prompt.confirm(&format!("'{}' exists. Replace it?", path.display()), false)?;
+            if write {
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(&path, config_toml(&answers))?;
+                prompt.say(&format!("Wrote '{}'", path.display()))?;
+            }
+        }
+        None => prompt.say("Unable to find where the config file goes, skipping it")?,
+    }
+
+    prompt.say("")?;
+    if prompt.confirm(
+        &format!(
+            "Link '{}' to {} so 'task' is project aware too?",
+            TASK_BIN, NAME
+        ),
+        false,
+    )? {
+        let dir = prompt.ask(
+            "Directory to put the link in",
+            &home.join(".local").join("bin").display().to_string(),
+        )?;
+        match link_task(Path::new(&dir), this_program) {
+            Ok(link) => prompt.say(&format!("Linked '{}'", link.display()))?,
+            Err(e) => prompt.say(&format!("Unable to link: {}", e))?,
+        }
+    }
+
+    match current_shell() {
+        Some(shell) => {
+            let (rc, line) = rc_line(shell, &home);
+            if prompt.confirm(
+                &format!(
+                    "Set up completions, the 't' shorthand and a prompt segment in '{}'?",
+                    rc.display()
+                ),
+                true,
+            )? {
+                if add_rc_line(&rc, &line)? {
+                    prompt.say(&format!("Added '{}' to '{}'", line, rc.display()))?;
+                } else {
+                    prompt.say("Already set up")?;
+                }
+            }
+        }
+        None => prompt.say(&format!(
+            "Unknown shell, see '{} shell-init --help' to set up completions",
+            NAME
+        ))?,
+    }
+
+    if let Some(task) = task.as_ref().filter(|task| task.is_local()) {
+        if prompt.confirm(
+            "Install a taskwarrior hook annotating tasks started in a git repository with the branch and commit?",
+            false,
+        )? {
+            let dir = hooks::hooks_dir(task)?;
+            for path in hooks::install(&dir, BundledHook::GitAnnotate, this_program)? {
+                prompt.say(&format!("Installed '{}'", path.display()))?;
+            }
+        }
+    }
+
+    prompt.say("\nChecking the setup:\n")?;
+    prompt.say(&doctor(this_program))?;
+    Ok(())
+}
+
+/// Walk through setting taskhelper up, asking on the terminal
+pub fn setup(this_program: &Path) -> Result<()> {
+    if !io::stdin().is_terminal() {
+        bail!("Setup asks questions, so it must be run in a terminal");
+    }
+    let this_program = env::current_exe().unwrap_or_else(|_| this_program.to_path_buf());
+    let mut prompt = Prompt {
+        input: io::stdin().lock(),
+        output: io::stdout(),
+    };
+    wizard(&mut prompt, &this_program)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_config_toml() {
+        let answers = ProjectAnswers {
+            isolate: true,
+            ..Default::default()
+        };
+        let config: Config = toml::from_str(&config_toml(&answers)).unwrap();
+        assert!(config.project.isolate);
+        assert!(!config.project.subdir_tag);
+    }
+
+    #[test]
+    fn test_add_rc_line() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let (rc, line) = rc_line(Shell::Bash, dir.path());
+        assert!(add_rc_line(&rc, &line).unwrap());
+        assert!(!add_rc_line(&rc, &line).unwrap());
+        assert_eq!(
+            "\neval \"$(taskhelper shell-init bash)\"\n",
+            fs::read_to_string(rc).unwrap()
+        );
+    }
+}
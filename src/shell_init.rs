@@ -0,0 +1,137 @@
+use crate::args::Shell;
+use crate::complete::script as completion_script;
+use crate::detect::Scope;
+use crate::taskwarrior::Taskwarrior;
+use crate::NAME;
+use color_eyre::eyre::bail;
+use color_eyre::Result;
+
+fn valid_function_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('-')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// Function running taskhelper as `alias`, completed like taskhelper
+fn shorthand(shell: Shell, alias: &str) -> String {
+    match shell {
+        Shell::Bash => format!(
+            "{alias}() {{\n    {name} \"$@\"\n}}\ncomplete -o nosort -F _{name} {alias}\n",
+            alias = alias,
+            name = NAME
+        ),
+        Shell::Zsh => format!(
+            "{alias}() {{\n    {name} \"$@\"\n}}\ncompdef _{name} {alias}\n",
+            alias = alias,
+            name = NAME
+        ),
+        Shell::Fish => format!(
+            "function {alias} --wraps {name}\n    {name} $argv\nend\n",
+            alias = alias,
+            name = NAME
+        ),
+    }
+}
+
+/// Prompt segment showing the detected project and its pending task count, added in front of
+/// the existing prompt. Sourcing it twice doesn't add it twice
+fn prompt(shell: Shell) -> String {
+    match shell {
+        Shell::Bash => format!(
+            r#"__{name}_prompt() {{
+    local segment
+    segment="$({name} prompt 2>/dev/null)" && [ -n "$segment" ] && printf '[%s] ' "$segment"
+}}
+if [[ $PS1 != *__{name}_prompt* ]]; then
+    PS1='$(__{name}_prompt)'"$PS1"
+fi
+"#,
+            name = NAME
+        ),
+        Shell::Zsh => format!(
+            r#"__{name}_prompt() {{
+    local segment
+    segment="$({name} prompt 2>/dev/null)" && [ -n "$segment" ] && printf '[%s] ' "$segment"
+}}
+setopt prompt_subst
+if [[ $PROMPT != *__{name}_prompt* ]]; then
+    PROMPT='$(__{name}_prompt)'"$PROMPT"
+fi
+"#,
+            name = NAME
+        ),
+        Shell::Fish => format!(
+            r#"if not functions -q __{name}_original_prompt
+    functions -c fish_prompt __{name}_original_prompt
+    function fish_prompt
+        set -l segment ({name} prompt 2>/dev/null)
+        and test -n "$segment"
+        and printf '[%s] ' "$segment"
+        __{name}_original_prompt
+    end
+end
+"#,
+            name = NAME
+        ),
+    }
+}
+
+/// Script for `shell`'s rc file setting up completions, the `alias` shorthand and, unless
+/// `no_prompt`, the prompt segment
+pub fn init(shell: Shell, alias: &str, no_prompt: bool) -> Result<String> {
+    if !valid_function_name(alias) {
+        bail!("'{}' can't be used as a shell function name", alias);
+    }
+    let mut script = completion_script(shell);
+    script.push_str(&shorthand(shell, alias));
+    if !no_prompt {
+        script.push_str(&prompt(shell));
+    }
+    Ok(script)
+}
+
+/// Prompt segment for `scope`: the project name and its number of pending tasks. `rc` is
+/// prepended to the taskwarrior command
+pub fn segment(task: &Taskwarrior, scope: &Scope, rc: &[String]) -> Result<String> {
+    let mut args = rc.to_vec();
+    args.push(String::from("rc.verbose=nothing"));
+    args.extend(scope.tokens());
+    args.extend([String::from("status:pending"), String::from("count")]);
+    let output = task.command(&args).output()?;
+    if !output.status.success() {
+        bail!(
+            "Unable to count pending tasks: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let count = String::from_utf8_lossy(&output.stdout);
+    Ok(format!("{} {}", scope.project().name(), count.trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_shorthand() {
+        assert_eq!(
+            "t() {\n    taskhelper \"$@\"\n}\ncomplete -o nosort -F _taskhelper t\n",
+            shorthand(Shell::Bash, "t")
+        );
+        assert_eq!(
+            "function t --wraps taskhelper\n    taskhelper $argv\nend\n",
+            shorthand(Shell::Fish, "t")
+        );
+    }
+
+    #[test]
+    fn test_init() {
+        let script = init(Shell::Zsh, "th", true).unwrap();
+        assert!(script.contains("compdef _taskhelper th\n"));
+        assert!(!script.contains("PROMPT"));
+        assert!(init(Shell::Bash, "t; rm -rf ~", false).is_err());
+    }
+}
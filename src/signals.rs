@@ -0,0 +1,89 @@
+use color_eyre::Result;
+use nix::sys::signal::{kill, sigaction, SaFlags, SigAction, SigHandler, SigSet, Signal};
+use nix::unistd::Pid;
+use std::ffi::c_void;
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+
+/// Signals meant for taskwarrior as much as for us
+const FORWARDED: [Signal; 4] = [
+    Signal::SIGINT,
+    Signal::SIGTERM,
+    Signal::SIGHUP,
+    Signal::SIGQUIT,
+];
+
+/// Process, or negated process group, signals are forwarded to. 0 until it's known
+static TARGET: AtomicI32 = AtomicI32::new(0);
+/// Signal that arrived before the target was known, 0 if none did
+static PENDING: AtomicI32 = AtomicI32::new(0);
+/// Whether the target is in our process group, so signals the terminal sends reach it directly
+static SHARES_GROUP: AtomicBool = AtomicBool::new(false);
+
+/// Pass `signal` on to the target. Only async-signal-safe calls are made
+extern "C" fn on_signal(signal: libc::c_int, info: *mut libc::siginfo_t, _context: *mut c_void) {
+    // Signals from the terminal, like Ctrl-C, come from no process
+    let from_terminal = !info.is_null() && unsafe { (*info).si_pid() } == 0;
+    match TARGET.load(Ordering::Relaxed) {
+        0 => PENDING.store(signal, Ordering::Relaxed),
+        _ if from_terminal && SHARES_GROUP.load(Ordering::Relaxed) => {}
+        target => unsafe {
+            // A child yet to start its process group gets the signal on its own
+            if libc::kill(target, signal) == -1 && target < 0 {
+                libc::kill(-target, signal);
+            }
+        },
+    }
+}
+
+/// Forwards terminal and termination signals to a child instead of letting them end us, until
+/// dropped. We then exit once the child has, with the lock on the task data released properly
+pub struct Forwarding {
+    previous: Vec<(Signal, SigAction)>,
+}
+
+impl Forwarding {
+    /// Start catching signals. Install before starting the child so none are missed, signals
+    /// arriving before `forward_to` is called are passed on then
+    pub fn install() -> Result<Self> {
+        TARGET.store(0, Ordering::Relaxed);
+        PENDING.store(0, Ordering::Relaxed);
+        SHARES_GROUP.store(false, Ordering::Relaxed);
+        let action = SigAction::new(
+            SigHandler::SigAction(on_signal),
+            SaFlags::SA_RESTART | SaFlags::SA_SIGINFO,
+            SigSet::empty(),
+        );
+        let mut previous = Vec::new();
+        for signal in FORWARDED {
+            previous.push((signal, unsafe { sigaction(signal, &action)? }));
+        }
+        Ok(Forwarding { previous })
+    }
+
+    /// Forward signals to `child`, or to its whole process group if `group`. A child signalled
+    /// on its own shares our process group, so signals from the terminal, which it gets as well,
+    /// aren't forwarded to it a second time
+    pub fn forward_to(&self, child: Pid, group: bool) {
+        let target = if group {
+            -child.as_raw()
+        } else {
+            child.as_raw()
+        };
+        SHARES_GROUP.store(!group, Ordering::Relaxed);
+        TARGET.store(target, Ordering::Relaxed);
+        if let Ok(signal) = Signal::try_from(PENDING.swap(0, Ordering::Relaxed)) {
+            if kill(Pid::from_raw(target), signal).is_err() {
+                let _ = kill(child, signal);
+            }
+        }
+    }
+}
+
+impl Drop for Forwarding {
+    fn drop(&mut self) {
+        for (signal, action) in &self.previous {
+            let _ = unsafe { sigaction(*signal, action) };
+        }
+        TARGET.store(0, Ordering::Relaxed);
+    }
+}
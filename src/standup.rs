@@ -0,0 +1,98 @@
+use crate::config::LinksConfig;
+use crate::hyperlink;
+use crate::task::{export_tasks, Task};
+use crate::taskwarrior::Taskwarrior;
+use color_eyre::Result;
+
+/// Most pending tasks listed under "Next"
+const NEXT_COUNT: usize = 5;
+
+/// Tasks the standup report is made of
+#[derive(Debug, Default)]
+pub struct Standup {
+    pub done: Vec<Task>,
+    pub active: Vec<Task>,
+    pub next: Vec<Task>,
+}
+
+fn export(task: &Taskwarrior, args: &[String], filter: &[&str]) -> Result<Vec<Task>> {
+    let mut export_args = args.to_vec();
+    export_args.extend(filter.iter().map(|arg| arg.to_string()));
+    export_tasks(task, &export_args)
+}
+
+impl Standup {
+    /// Query the tasks matching `args`, which may also hold rc overrides, completed since the
+    /// start of yesterday, started, and most urgent among the rest
+    pub fn query(task: &Taskwarrior, args: &[String]) -> Result<Self> {
+        let mut done = export(task, args, &["status:completed", "end.after:yesterday"])?;
+        done.sort_by_key(|task| task.end);
+        let active = export(task, args, &["status:pending", "+ACTIVE"])?;
+        let mut next = export(task, args, &["status:pending", "-ACTIVE", "-WAITING"])?;
+        next.sort_by(|a, b| b.urgency.total_cmp(&a.urgency));
+        next.truncate(NEXT_COUNT);
+        Ok(Standup { done, active, next })
+    }
+
+    /// The report as short plain text, titled with `project` if given. With `links`, tasks and
+    /// the URLs in their descriptions are hyperlinked
+    pub fn render(&self, project: Option<&str>, links: Option<&LinksConfig>) -> String {
+        let mut out = match project {
+            Some(project) => format!("Standup: {}\n", project),
+            None => String::from("Standup\n"),
+        };
+        for (title, tasks) in [
+            ("Done", &self.done),
+            ("In progress", &self.active),
+            ("Next", &self.next),
+        ] {
+            out.push_str(&format!("\n{}:\n", title));
+            if tasks.is_empty() {
+                out.push_str("- nothing\n");
+            }
+            for task in tasks {
+                let description = match links {
+                    Some(links) => hyperlink::task_description(links, task),
+                    None => task.description.clone(),
+                };
+                out.push_str(&format!("- {}\n", description));
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_render() {
+        let tasks: Vec<Task> = serde_json::from_str(
+            r#"[
+                {"uuid": "a", "description": "Fix the build", "status": "completed"},
+                {"id": 2, "uuid": "b", "description": "Write docs", "status": "pending"}
+            ]"#,
+        )
+        .unwrap();
+        let standup = Standup {
+            done: tasks[..1].to_vec(),
+            active: Vec::new(),
+            next: tasks[1..].to_vec(),
+        };
+        let expected = "\
+Standup: repo
+
+Done:
+- Fix the build
+
+In progress:
+- nothing
+
+Next:
+- Write docs
+";
+        assert_eq!(expected, standup.render(Some("repo"), None));
+    }
+}
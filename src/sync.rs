@@ -0,0 +1,109 @@
+use crate::config::SyncConfig;
+use crate::i18n::tr;
+use crate::taskwarrior::Taskwarrior;
+use crate::{run, run_quiet, CommandResult};
+use color_eyre::eyre::bail;
+use color_eyre::Result;
+use log::warn;
+use std::thread;
+use std::time::Duration;
+
+/// Output fragments of a failed sync that suggest trying again may succeed
+const TRANSIENT_ERRORS: &[&str] = &[
+    "timed out",
+    "timeout",
+    "connection refused",
+    "connection reset",
+    "temporarily unavailable",
+    "500 internal server error",
+    "502 bad gateway",
+    "503 service unavailable",
+    "504 gateway timeout",
+];
+
+fn is_transient(output: &str) -> bool {
+    let output = output.to_lowercase();
+    TRANSIENT_ERRORS.iter().any(|e| output.contains(e))
+}
+
+/// Delay before retry number `retry` (counting from zero)
+fn backoff(base: Duration, retry: u32) -> Duration {
+    base.saturating_mul(2u32.saturating_pow(retry))
+}
+
+/// Run `task synchronize`, retrying with exponential backoff while it fails with errors that look
+/// transient
+pub fn synchronize(
+    task: &Taskwarrior,
+    args: &[String],
+    config: &SyncConfig,
+) -> Result<CommandResult> {
+    synchronize_with(run, task, args, config)
+}
+
+/// Synchronize after a command changed task data. The output is only shown if it fails
+pub fn auto_synchronize(task: &Taskwarrior, overrides: &[String], config: &SyncConfig) {
+    let mut args = overrides.to_vec();
+    args.push(String::from("synchronize"));
+    match synchronize_with(run_quiet, task, &args, config) {
+        Ok(res) if res.exit.success() => {}
+        Ok(res) => {
+            eprint!("{}{}", res.stdout, res.stderr);
+            eprintln!("Warning: {}", tr("sync-auto-failed", &[]));
+        }
+        Err(e) => eprintln!("Warning: {}", e),
+    }
+}
+
+fn synchronize_with(
+    run: fn(&Taskwarrior, &[String]) -> Result<CommandResult>,
+    task: &Taskwarrior,
+    args: &[String],
+    config: &SyncConfig,
+) -> Result<CommandResult> {
+    let base = Duration::from_millis(config.backoff_ms);
+    let mut retry = 0;
+    loop {
+        let res = run(task, args)?;
+        if res.exit.success() || !(is_transient(&res.stderr) || is_transient(&res.stdout)) {
+            return Ok(res);
+        }
+        if retry >= config.retries {
+            bail!(tr("sync-failed", &[("attempts", &(config.retries + 1))]));
+        }
+        let delay = backoff(base, retry);
+        warn!(
+            "{}",
+            tr(
+                "sync-retry",
+                &[
+                    ("attempt", &(retry + 1)),
+                    ("attempts", &(config.retries + 1)),
+                    ("delay", &format!("{:.1}", delay.as_secs_f64())),
+                ]
+            )
+        );
+        thread::sleep(delay);
+        retry += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_is_transient() {
+        assert!(is_transient("Sync failed: Connection timed out"));
+        assert!(is_transient("HTTP status: 503 Service Unavailable"));
+        assert!(!is_transient("Sync failed: invalid client key"));
+    }
+
+    #[test]
+    fn test_backoff() {
+        let base = Duration::from_secs(1);
+        assert_eq!(Duration::from_secs(1), backoff(base, 0));
+        assert_eq!(Duration::from_secs(4), backoff(base, 2));
+    }
+}
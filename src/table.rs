@@ -0,0 +1,269 @@
+use crate::config::LinksConfig;
+use crate::hyperlink;
+use crate::task::Task;
+use color_eyre::owo_colors::OwoColorize;
+use serde_json::Value;
+use std::time::SystemTime;
+
+/// Columns shown unless the config lists others
+pub const DEFAULT_COLUMNS: &[&str] = &["id", "project", "tags", "due", "description", "urgency"];
+
+/// Narrowest a column is truncated to when the table is too wide for the terminal
+const MIN_WIDTH: usize = 10;
+
+/// Urgency from which a row is highlighted, and from which it's highlighted as urgent
+const URGENCY_NOTABLE: f64 = 5.0;
+const URGENCY_URGENT: f64 = 10.0;
+
+/// The day of `time`, e.g. `2024-01-01`
+pub(crate) fn date(time: Option<SystemTime>) -> String {
+    time.map(|time| humantime::format_rfc3339_seconds(time).to_string()[..10].to_string())
+        .unwrap_or_default()
+}
+
+/// Text of `column` for `task`. Attributes the model doesn't have are looked up among its UDAs
+pub(crate) fn cell(task: &Task, column: &str) -> String {
+    match column {
+        "id" if task.id == 0 => String::from("-"),
+        "id" => task.id.to_string(),
+        "uuid" => task.uuid.clone(),
+        "description" => task.description.clone(),
+        "status" => task.status.clone(),
+        "project" => task.project.clone().unwrap_or_default(),
+        "tags" => task.tags.join(" "),
+        "priority" => task.priority.clone().unwrap_or_default(),
+        "depends" => task.depends.len().to_string(),
+        "entry" => date(task.entry),
+        "modified" => date(task.modified),
+        "start" => date(task.start),
+        "end" => date(task.end),
+        "due" => date(task.due),
+        "scheduled" => date(task.scheduled),
+        "wait" => date(task.wait),
+        "urgency" => format!("{:.1}", task.urgency),
+        "annotations" => task.annotations.len().to_string(),
+        uda => match task.udas.get(uda) {
+            Some(Value::String(value)) => value.clone(),
+            Some(Value::Null) | None => String::new(),
+            Some(value) => value.to_string(),
+        },
+    }
+}
+
+/// `text` cut down to `width` characters, ending with an ellipsis if anything was cut
+fn truncate(text: &str, width: usize) -> String {
+    if text.chars().count() <= width {
+        return text.to_string();
+    }
+    let mut cut: String = text.chars().take(width.saturating_sub(1)).collect();
+    cut.push('…');
+    cut
+}
+
+/// Shrink the description column, or the last column without one, so a row of `widths` fits in
+/// `max` characters
+fn fit(widths: &mut [usize], columns: &[String], max: usize) {
+    let total = widths.iter().sum::<usize>() + widths.len().saturating_sub(1);
+    if total <= max {
+        return;
+    }
+    let flexible = columns
+        .iter()
+        .position(|column| column == "description")
+        .unwrap_or(columns.len() - 1);
+    let excess = total - max;
+    widths[flexible] = widths[flexible]
+        .saturating_sub(excess)
+        .max(MIN_WIDTH.min(widths[flexible]));
+}
+
+/// Render records of `(label, value)` fields as one `label: value` line per field with a value,
+/// with a blank line between records. Nothing is aligned or cut, for screen readers and dumb
+/// terminals
+pub(crate) fn render_plain(records: &[Vec<(String, String)>]) -> String {
+    let records: Vec<String> = records
+        .iter()
+        .map(|fields| {
+            fields
+                .iter()
+                .filter(|(_, value)| !value.is_empty())
+                .map(|(label, value)| format!("{}: {}\n", label, value))
+                .collect()
+        })
+        .collect();
+    records.join("\n")
+}
+
+/// Render `tasks` as `column: value` lines, the plain counterpart of [`render`]
+pub fn render_records(tasks: &[Task], columns: &[String]) -> String {
+    let records: Vec<Vec<(String, String)>> = tasks
+        .iter()
+        .map(|task| {
+            columns
+                .iter()
+                .map(|column| (column.clone(), cell(task, column)))
+                .collect()
+        })
+        .collect();
+    render_plain(&records)
+}
+
+/// `text`, the cell of `column` for `task`, with the task and the URLs in it hyperlinked.
+/// Descriptions that were cut are left alone, their links would be cut too
+fn linked(links: &LinksConfig, task: &Task, column: &str, text: &str, cut: bool) -> String {
+    match column {
+        "id" | "uuid" => hyperlink::task_link(links, &task.uuid, text),
+        "description" if !cut => hyperlink::linkify(text),
+        _ => text.to_string(),
+    }
+}
+
+/// Render `tasks` as a table of `columns`, fit to `width` characters if given. With `color`,
+/// urgent tasks are highlighted, with `links` tasks and URLs are hyperlinked
+pub fn render(
+    tasks: &[Task],
+    columns: &[String],
+    width: Option<usize>,
+    color: bool,
+    links: Option<&LinksConfig>,
+) -> String {
+    if columns.is_empty() {
+        return String::new();
+    }
+    let header: Vec<String> = columns.iter().map(|c| c.to_uppercase()).collect();
+    let rows: Vec<Vec<String>> = tasks
+        .iter()
+        .map(|task| columns.iter().map(|column| cell(task, column)).collect())
+        .collect();
+
+    let mut widths: Vec<usize> = header.iter().map(|cell| cell.chars().count()).collect();
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    if let Some(max) = width {
+        fit(&mut widths, columns, max);
+    }
+
+    // Padded by their plain text, escape sequences in hyperlinks take up no space
+    let line = |row: &[String], task: Option<&Task>| {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .zip(columns)
+            .map(|((cell, width), column)| {
+                let shown = truncate(cell, *width);
+                let padding = " ".repeat(width - shown.chars().count());
+                match (links, task) {
+                    (Some(links), Some(task)) => {
+                        let cut = shown != *cell;
+                        format!("{}{}", linked(links, task, column, &shown, cut), padding)
+                    }
+                    _ => format!("{}{}", shown, padding),
+                }
+            })
+            .collect();
+        cells.join(" ").trim_end().to_string()
+    };
+    let mut out = String::new();
+    let header = line(&header, None);
+    if color {
+        out.push_str(&header.underline().to_string());
+    } else {
+        out.push_str(&header);
+    }
+    out.push('\n');
+    for (task, row) in tasks.iter().zip(&rows) {
+        let row = line(row, Some(task));
+        let row = match task.urgency {
+            _ if !color => row,
+            urgency if urgency >= URGENCY_URGENT => row.red().bold().to_string(),
+            urgency if urgency >= URGENCY_NOTABLE => row.yellow().to_string(),
+            _ => row,
+        };
+        out.push_str(&row);
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn tasks() -> Vec<Task> {
+        serde_json::from_str(
+            r#"[
+                {"id": 1, "uuid": "a", "description": "Write the docs for the release",
+                 "project": "repo", "status": "pending", "due": "20240101T120000Z",
+                 "urgency": 11.2, "estimate": "PT2H"},
+                {"id": 0, "uuid": "b", "description": "Ship", "status": "completed",
+                 "tags": ["a", "b"], "urgency": 0}
+            ]"#,
+        )
+        .unwrap()
+    }
+
+    fn columns(columns: &[&str]) -> Vec<String> {
+        columns.iter().map(|c| c.to_string()).collect()
+    }
+
+    #[test]
+    fn test_render() {
+        let columns = columns(&["id", "project", "tags", "due", "description", "estimate"]);
+        let expected = "\
+ID PROJECT TAGS DUE        DESCRIPTION                    ESTIMATE
+1  repo         2024-01-01 Write the docs for the release PT2H
+-          a b             Ship
+";
+        assert_eq!(expected, render(&tasks(), &columns, None, false, None));
+    }
+
+    #[test]
+    fn test_render_truncated() {
+        let columns = columns(&["id", "description", "urgency"]);
+        let expected = "\
+ID DESCRIPTION  URGENCY
+1  Write the d… 11.2
+-  Ship         0.0
+";
+        assert_eq!(expected, render(&tasks(), &columns, Some(23), false, None));
+    }
+
+    #[test]
+    fn test_render_links() {
+        let columns = columns(&["id", "uuid", "description"]);
+        let tasks: Vec<Task> = serde_json::from_str(
+            r#"[{"id": 1, "uuid": "a", "description": "see https://x.org", "status": "pending"}]"#,
+        )
+        .unwrap();
+        let links = LinksConfig {
+            task_url: Some(String::from("https://tasks/{uuid}")),
+            ..Default::default()
+        };
+        let expected = "\
+ID UUID DESCRIPTION
+\x1b]8;;https://tasks/a\x1b\\1\x1b]8;;\x1b\\  \x1b]8;;https://tasks/a\x1b\\a\x1b]8;;\x1b\\    see \x1b]8;;https://x.org\x1b\\https://x.org\x1b]8;;\x1b\\
+";
+        assert_eq!(
+            expected,
+            render(&tasks, &columns, None, false, Some(&links))
+        );
+    }
+
+    #[test]
+    fn test_render_records() {
+        let columns = columns(&["id", "tags", "description"]);
+        let expected = "\
+id: 1
+description: Write the docs for the release
+
+id: -
+tags: a b
+description: Ship
+";
+        assert_eq!(expected, render_records(&tasks(), &columns));
+    }
+}
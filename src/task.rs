@@ -0,0 +1,343 @@
+use crate::taskwarrior::Taskwarrior;
+use color_eyre::eyre::{bail, eyre};
+use color_eyre::Result;
+use log::debug;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::time::SystemTime;
+
+/// `time` in the format taskwarrior uses for dates in JSON, e.g. `20240101T120000Z`
+pub fn format_date(time: SystemTime) -> String {
+    humantime::format_rfc3339_seconds(time)
+        .to_string()
+        .replace(['-', ':'], "")
+}
+
+/// Parse a date in the format taskwarrior uses in JSON, e.g. `20240101T120000Z`
+pub fn parse_date(date: &str) -> Result<SystemTime> {
+    let invalid = || eyre!("'{}' is not a taskwarrior date", date);
+    let (day, time) = date.split_once('T').ok_or_else(invalid)?;
+    let time = time.strip_suffix('Z').ok_or_else(invalid)?;
+    if day.len() != 8 || time.len() != 6 {
+        return Err(invalid());
+    }
+    let rfc3339 = format!(
+        "{}-{}-{}T{}:{}:{}Z",
+        &day[..4],
+        &day[4..6],
+        &day[6..],
+        &time[..2],
+        &time[2..4],
+        &time[4..]
+    );
+    humantime::parse_rfc3339(&rfc3339).map_err(|_| invalid())
+}
+
+/// Parse a `YYYY-MM-DD` day, as issue trackers and todo.txt give dates, to its start in UTC
+pub fn parse_day(day: &str) -> Option<SystemTime> {
+    if day.len() != 10 {
+        return None;
+    }
+    humantime::parse_rfc3339(&format!("{}T00:00:00Z", day)).ok()
+}
+
+/// Serde adapter for optional taskwarrior dates
+mod optional_date {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        date: &Option<SystemTime>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match date {
+            Some(date) => serializer.serialize_str(&format_date(*date)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<SystemTime>, D::Error> {
+        match Option::<String>::deserialize(deserializer)? {
+            Some(date) => parse_date(&date)
+                .map(Some)
+                .map_err(serde::de::Error::custom),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Serde adapter for required taskwarrior dates
+mod date {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(date: &SystemTime, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format_date(*date))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<SystemTime, D::Error> {
+        parse_date(&String::deserialize(deserializer)?).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Taskwarrior 3 exports dependencies as a list, 2.x as a comma separated string
+fn depends<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Depends {
+        List(Vec<String>),
+        Joined(String),
+    }
+    Ok(match Depends::deserialize(deserializer)? {
+        Depends::List(uuids) => uuids,
+        Depends::Joined(uuids) => uuids
+            .split(',')
+            .filter(|uuid| !uuid.is_empty())
+            .map(String::from)
+            .collect(),
+    })
+}
+
+/// A note attached to a task
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Annotation {
+    #[serde(with = "date")]
+    pub entry: SystemTime,
+    pub description: String,
+}
+
+/// A task as `task export` describes it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Task {
+    /// Working set ID, 0 for tasks that are no longer pending
+    #[serde(default)]
+    pub id: u64,
+    /// Empty for a task being imported without one, taskwarrior makes one up
+    #[serde(default)]
+    pub uuid: String,
+    pub description: String,
+    /// Empty for a task being imported without one, taskwarrior imports it as pending
+    #[serde(default)]
+    pub status: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "depends",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub depends: Vec<String>,
+    #[serde(
+        default,
+        with = "optional_date",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub entry: Option<SystemTime>,
+    #[serde(
+        default,
+        with = "optional_date",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub modified: Option<SystemTime>,
+    #[serde(
+        default,
+        with = "optional_date",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub start: Option<SystemTime>,
+    #[serde(
+        default,
+        with = "optional_date",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub end: Option<SystemTime>,
+    #[serde(
+        default,
+        with = "optional_date",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub due: Option<SystemTime>,
+    #[serde(
+        default,
+        with = "optional_date",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub scheduled: Option<SystemTime>,
+    #[serde(
+        default,
+        with = "optional_date",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub wait: Option<SystemTime>,
+    #[serde(default)]
+    pub urgency: f64,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub annotations: Vec<Annotation>,
+    /// User defined attributes, and any other attribute not listed above, by name
+    #[serde(flatten)]
+    pub udas: BTreeMap<String, Value>,
+}
+
+/// Tasks matching `args`, which may also hold rc overrides, as exported by taskwarrior
+pub fn export_tasks(task: &Taskwarrior, args: &[String]) -> Result<Vec<Task>> {
+    export_report(task, args, None)
+}
+
+/// Tasks matching `args` and the filter of `report`, if given, as exported by taskwarrior
+pub fn export_report(
+    task: &Taskwarrior,
+    args: &[String],
+    report: Option<&str>,
+) -> Result<Vec<Task>> {
+    let mut export_args = vec![String::from("rc.json.array=on")];
+    export_args.extend_from_slice(args);
+    export_args.push(String::from("export"));
+    export_args.extend(report.map(String::from));
+    let mut cmd = task.command(&export_args);
+    debug!("Running command {:?}", cmd);
+
+    let output = cmd.output()?;
+    if !output.status.success() {
+        bail!(
+            "Export failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(serde_json::from_slice(&output.stdout)?)
+}
+
+/// Import `tasks` as JSON, with `args` ahead of the import command, which may hold rc overrides
+pub fn import_tasks(task: &Taskwarrior, args: &[String], tasks: &[Value]) -> Result<()> {
+    let mut file = tempfile::Builder::new().suffix(".json").tempfile()?;
+    file.write_all(serde_json::to_string(tasks)?.as_bytes())?;
+    let mut import_args = args.to_vec();
+    import_args.extend([
+        String::from("rc.verbose=nothing"),
+        String::from("import"),
+        file.path().display().to_string(),
+    ]);
+    let output = task.command(&import_args).output()?;
+    if !output.status.success() {
+        bail!(
+            "Import failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+/// Describe `e`, from parsing JSON that starts on line `first_line`, with where it happened
+fn located(e: &serde_json::Error, first_line: usize) -> String {
+    let message = e.to_string();
+    let message = message
+        .rsplit_once(" at line ")
+        .map_or(message.as_str(), |(message, _)| message);
+    format!(
+        "line {}, column {}: {}",
+        first_line + e.line().saturating_sub(1),
+        e.column(),
+        message
+    )
+}
+
+/// Check `json` holds tasks `task import` accepts: a JSON array of tasks, or one task per line.
+/// Returns how many there are
+pub fn check_import(json: &str) -> Result<usize> {
+    if json.trim_start().starts_with('[') {
+        let tasks: Vec<Task> = serde_json::from_str(json).map_err(|e| eyre!(located(&e, 1)))?;
+        return Ok(tasks.len());
+    }
+    let mut count = 0;
+    for (i, line) in json.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        serde_json::from_str::<Task>(line).map_err(|e| eyre!(located(&e, i + 1)))?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use s_string::s;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn test_dates() {
+        let time = UNIX_EPOCH + Duration::from_secs(1_704_110_400);
+        assert_eq!("20240101T120000Z", format_date(time));
+        assert_eq!(time, parse_date("20240101T120000Z").unwrap());
+        assert!(parse_date("2024-01-01").is_err());
+        assert_eq!(
+            "20240501T000000Z",
+            format_date(parse_day("2024-05-01").unwrap())
+        );
+        assert_eq!(None, parse_day("2024-5-1"));
+        assert_eq!(None, parse_day("tomorrow"));
+    }
+
+    #[test]
+    fn test_task() {
+        let task: Task = serde_json::from_str(
+            r#"{
+                "id": 3,
+                "description": "Write docs",
+                "due": "20240101T120000Z",
+                "entry": "20231201T090000Z",
+                "modified": "20231201T090000Z",
+                "project": "repo",
+                "status": "pending",
+                "tags": ["docs"],
+                "uuid": "a1b2",
+                "depends": "c3d4,e5f6",
+                "annotations": [{"entry": "20231202T090000Z", "description": "see issue"}],
+                "estimate": "PT2H",
+                "urgency": 9.2
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(3, task.id);
+        assert_eq!(Some(s!("repo")), task.project);
+        assert_eq!(vec![s!("docs")], task.tags);
+        assert_eq!(vec![s!("c3d4"), s!("e5f6")], task.depends);
+        assert_eq!(Some(parse_date("20240101T120000Z").unwrap()), task.due);
+        assert_eq!("see issue", task.annotations[0].description);
+        assert_eq!(Some(&Value::from("PT2H")), task.udas.get("estimate"));
+        assert_eq!(None, task.start);
+
+        let json = serde_json::to_value(&task).unwrap();
+        assert_eq!(Value::from("20240101T120000Z"), json["due"]);
+        assert_eq!(Value::from("PT2H"), json["estimate"]);
+        assert_eq!(task, serde_json::from_value(json).unwrap());
+    }
+
+    #[test]
+    fn test_check_import() {
+        let array = r#"[
+            {"description": "Write docs", "due": "20240101T120000Z"},
+            {"description": "Review", "tags": ["code"]}
+        ]"#;
+        assert_eq!(2, check_import(array).unwrap());
+        let lines = "{\"description\": \"Write docs\"}\n\n{\"description\": \"Review\"}\n";
+        assert_eq!(2, check_import(lines).unwrap());
+
+        let bad_date = "{\"description\": \"a\"}\n{\"description\": \"b\", \"due\": \"soon\"}";
+        let e = check_import(bad_date).unwrap_err().to_string();
+        assert!(e.starts_with("line 2, "), "{}", e);
+        assert!(e.contains("'soon' is not a taskwarrior date"), "{}", e);
+
+        let unclosed = "[\n  {\"description\": \"a\"},\n  {\"description\": \"b\"\n";
+        let e = check_import(unclosed).unwrap_err().to_string();
+        assert!(e.starts_with("line 4, "), "{}", e);
+        assert!(check_import(r#"[{"due": "20240101T120000Z"}]"#).is_err());
+    }
+}
@@ -1,55 +1,89 @@
 pub mod burndown;
+pub mod date;
+pub mod expression;
 pub mod filter;
 pub mod modifier;
 
-use filter::Filter;
-use filter::Filters;
-use libc::PR_GET_PDEATHSIG;
 use thiserror::Error;
 
 pub mod history;
 pub mod project;
 
-use color_eyre::eyre::bail;
-use color_eyre::Result;
-use core::fmt::Error;
-use log::debug;
-use log::info;
-use log::trace;
-use serde::Serialize;
-use std::env;
-use std::fmt;
-use std::fs::File;
-use std::io::{Read, Write};
-use std::path::Path;
-use std::process::Command;
 use std::str;
-use std::str::FromStr;
-use winnow::ascii::alphanumeric1;
 use winnow::ascii::multispace1;
 use winnow::combinator::alt;
 use winnow::combinator::eof;
 use winnow::combinator::repeat;
-use winnow::combinator::Repeat;
-use winnow::stream::Accumulate;
 use winnow::stream::AsChar;
-use winnow::token::one_of;
 use winnow::token::take_while;
 use winnow::PResult;
 use winnow::Parser;
 
 #[derive(Debug, Error)]
 pub enum ParseError {
-    #[error("Unable to parse project name")]
-    Project,
     #[error("Unable to parse filter")]
     Filter,
+    #[error("Unknown virtual tag '{name}', did you mean '{suggestion}'?")]
+    VirtualTag { name: String, suggestion: String },
+    #[error(
+        "Invalid date '{0}', expected a date like 2024-01-01, a name like eom or monday, or an offset like +3d"
+    )]
+    Date(String),
+    #[error("Invalid duration '{0}', expected a duration like 3d, 2weeks or P1D")]
+    Duration(String),
+    #[error(
+        "Invalid priority '{value}', expected H, M, L or nothing{}",
+        .suggestion.as_ref().map(|s| format!(". Did you mean '{}'?", s)).unwrap_or_default()
+    )]
+    Priority {
+        value: String,
+        suggestion: Option<String>,
+    },
+    #[error("Invalid dependencies '{0}', expected task ids or uuids separated by commas")]
+    Depends(String),
     #[error("Unable to parse modifier")]
     Modifier,
     #[error("Unable to parse burndown")]
     Burndown,
     #[error("Unable to parse (g)history")]
     History,
+    #[error("Unknown named filter '@{0}'")]
+    UnknownFilter(String),
+    #[error("Unable to parse named filter '@{0}'")]
+    NamedFilter(String),
+    #[error("Unable to parse default filter for '{0}'")]
+    DefaultFilter(String),
+    #[error("Named filter '@{0}' refers to itself")]
+    RecursiveFilter(String),
+}
+
+/// Whether `arg` overrides a taskwarrior setting, like `rc.confirmation=off` or
+/// `rc.confirmation:off`
+pub fn is_rc_override(arg: &str) -> bool {
+    arg.strip_prefix("rc.")
+        .and_then(|setting| setting.find(['=', ':']))
+        .is_some_and(|end| end > 0)
+}
+
+/// Split `args` into the rc overrides among them and everything else. Taskwarrior takes
+/// overrides anywhere on the command line up to a `--`
+pub fn split_rc_overrides(args: Vec<String>) -> (Vec<String>, Vec<String>) {
+    let mut rest = Vec::with_capacity(args.len());
+    let mut overrides = Vec::new();
+    let mut args = args.into_iter();
+    for arg in args.by_ref() {
+        if arg == "--" {
+            rest.push(arg);
+            break;
+        }
+        if is_rc_override(&arg) {
+            overrides.push(arg);
+        } else {
+            rest.push(arg);
+        }
+    }
+    rest.extend(args);
+    (rest, overrides)
 }
 
 // TODO: parse things in quotes?
@@ -61,12 +95,14 @@ fn word<'a>(s: &mut &'a str) -> PResult<&'a str> {
     .parse_next(s)
 }
 
+#[allow(dead_code)]
 fn word_space_or_end<'a>(s: &mut &'a str) -> PResult<&'a str> {
     let w = word.parse_next(s)?;
     let _ = alt((multispace1, eof)).parse_next(s)?;
     Ok(w)
 }
 
+#[allow(dead_code)]
 fn multi_word(s: &mut &str) -> PResult<Vec<String>> {
     repeat(0.., word_space_or_end.map(|s: &str| s.to_string())).parse_next(s)
 }
@@ -77,6 +113,36 @@ mod tests {
     use pretty_assertions::assert_eq;
     use s_string::s;
 
+    #[test]
+    fn test_split_rc_overrides() {
+        let args = vec![
+            s!("taskhelper"),
+            s!("rc.confirmation=off"),
+            s!("+work"),
+            s!("done"),
+            s!("rc.verbose:nothing"),
+            s!("rc."),
+            s!("--"),
+            s!("rc.data.location=x"),
+        ];
+        let (rest, overrides) = split_rc_overrides(args);
+        assert_eq!(
+            vec![
+                s!("taskhelper"),
+                s!("+work"),
+                s!("done"),
+                s!("rc."),
+                s!("--"),
+                s!("rc.data.location=x")
+            ],
+            rest
+        );
+        assert_eq!(
+            vec![s!("rc.confirmation=off"), s!("rc.verbose:nothing")],
+            overrides
+        );
+    }
+
     #[test]
     fn test_words() {
         let mut input = "this is a lot of words";
@@ -1,27 +1,8 @@
-use super::word;
 use super::ParseError;
-use color_eyre::eyre::bail;
 use color_eyre::Result;
-use core::fmt::Error;
-use log::debug;
-use log::info;
-use log::trace;
-use serde::Serialize;
-use std::env;
-use std::fmt;
-use std::fs::File;
-use std::io::{Read, Write};
-use std::path::Path;
-use std::process::Command;
 use std::str;
 use std::str::FromStr;
-use winnow::ascii::alphanumeric1;
 use winnow::combinator::alt;
-use winnow::combinator::repeat;
-use winnow::stream::Accumulate;
-use winnow::stream::AsChar;
-use winnow::token::one_of;
-use winnow::token::take_while;
 use winnow::PResult;
 use winnow::Parser;
 
@@ -0,0 +1,406 @@
+use super::ParseError;
+use std::fmt;
+use std::str;
+use std::str::FromStr;
+use winnow::ascii::{alpha1, digit1};
+use winnow::combinator::{alt, eof, opt, repeat, terminated};
+use winnow::token::{one_of, take_while};
+use winnow::PResult;
+use winnow::Parser;
+
+/// Attributes holding a date
+pub const DATE_ATTRIBUTES: &[&str] =
+    &["due", "wait", "scheduled", "until", "entry", "start", "end"];
+
+/// Names taskwarrior resolves to a date
+const SYNONYMS: &[&str] = &[
+    "now",
+    "today",
+    "sod",
+    "eod",
+    "yesterday",
+    "tomorrow",
+    "later",
+    "someday",
+    "monday",
+    "tuesday",
+    "wednesday",
+    "thursday",
+    "friday",
+    "saturday",
+    "sunday",
+    "mon",
+    "tue",
+    "wed",
+    "thu",
+    "fri",
+    "sat",
+    "sun",
+    "january",
+    "february",
+    "march",
+    "april",
+    "may",
+    "june",
+    "july",
+    "august",
+    "september",
+    "october",
+    "november",
+    "december",
+    "jan",
+    "feb",
+    "mar",
+    "apr",
+    "jun",
+    "jul",
+    "aug",
+    "sep",
+    "oct",
+    "nov",
+    "dec",
+    "sow",
+    "eow",
+    "socw",
+    "eocw",
+    "soww",
+    "eoww",
+    "sopw",
+    "eopw",
+    "sonw",
+    "eonw",
+    "som",
+    "eom",
+    "socm",
+    "eocm",
+    "sopm",
+    "eopm",
+    "sonm",
+    "eonm",
+    "soq",
+    "eoq",
+    "sopq",
+    "eopq",
+    "sonq",
+    "eonq",
+    "soy",
+    "eoy",
+    "socy",
+    "eocy",
+    "sopy",
+    "eopy",
+    "sony",
+    "eony",
+    "sopd",
+    "eopd",
+    "sond",
+    "eond",
+    "easter",
+    "eastermonday",
+    "ascension",
+    "pentecost",
+    "goodfriday",
+    "midsommar",
+    "midsommarafton",
+    "juhannus",
+];
+
+/// Units a duration can be given in, like the `d` in `3d`
+const DURATION_UNITS: &[&str] = &[
+    "annual",
+    "biannual",
+    "bimonthly",
+    "biweekly",
+    "biyearly",
+    "daily",
+    "days",
+    "day",
+    "d",
+    "fortnight",
+    "hours",
+    "hour",
+    "hrs",
+    "hr",
+    "h",
+    "minutes",
+    "minute",
+    "mins",
+    "min",
+    "monthly",
+    "months",
+    "month",
+    "mnths",
+    "mths",
+    "mth",
+    "mos",
+    "mo",
+    "m",
+    "quarterly",
+    "quarters",
+    "quarter",
+    "qrtrs",
+    "qtrs",
+    "qtr",
+    "q",
+    "seconds",
+    "second",
+    "secs",
+    "sec",
+    "s",
+    "semiannual",
+    "sennight",
+    "weekdays",
+    "weekly",
+    "weeks",
+    "week",
+    "wks",
+    "wk",
+    "w",
+    "yearly",
+    "years",
+    "year",
+    "yrs",
+    "yr",
+    "y",
+];
+
+/// Value of a date attribute, checked to be something taskwarrior can resolve to a date:
+/// a calendar date (`2024-01-01`), a name (`eom`, `monday`), either of those offset by
+/// durations (`eom+1d`) or an offset from now (`+3d`). Empty to clear the date
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Date {
+    repr: String,
+}
+
+impl Date {
+    /// Whether this clears the date rather than setting it
+    pub fn is_empty(&self) -> bool {
+        self.repr.is_empty()
+    }
+}
+
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.repr)
+    }
+}
+
+impl FromStr for Date {
+    type Err = ParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        date.parse(s)
+            .map(|repr| Date { repr })
+            .map_err(|_| ParseError::Date(s.to_string()))
+    }
+}
+
+/// Length of time, like `3d`, `2weeks` or `P1D`
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Duration {
+    repr: String,
+}
+
+impl fmt::Display for Duration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.repr)
+    }
+}
+
+impl FromStr for Duration {
+    type Err = ParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        duration
+            .parse(s)
+            .map(|repr| Duration { repr })
+            .map_err(|_| ParseError::Duration(s.to_string()))
+    }
+}
+
+fn digits<'a>(count: usize) -> impl Parser<&'a str, &'a str, winnow::error::ContextError> {
+    take_while(count, |c: char| c.is_ascii_digit())
+}
+
+fn days_in_month(year: u32, month: u32) -> u32 {
+    match month {
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => {
+            29
+        }
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+fn valid_date(year: &str, month: &str, day: &str) -> bool {
+    let (Ok(year), Ok(month), Ok(day)) = (year.parse(), month.parse(), day.parse()) else {
+        return false;
+    };
+    (1..=12).contains(&month) && (1..=days_in_month(year, month)).contains(&day)
+}
+
+fn valid_time(hour: &str, minute: &str, second: Option<&str>) -> bool {
+    let in_range = |value: &str, max: u32| value.parse::<u32>().is_ok_and(|v| v <= max);
+    in_range(hour, 23) && in_range(minute, 59) && second.is_none_or(|s| in_range(s, 59))
+}
+
+/// `2024-01-01`, optionally with a time: `2024-01-01T09:30`, `2024-01-01T09:30:00Z`
+fn iso_date(s: &mut &str) -> PResult<String> {
+    let time = (
+        "T",
+        digits(2),
+        ":",
+        digits(2),
+        opt((":", digits(2)).map(|(_, second)| second)),
+        opt("Z"),
+    )
+        .verify(|(_, hour, _, minute, second, _)| valid_time(hour, minute, *second));
+    (
+        (digits(4), "-", digits(2), "-", digits(2))
+            .verify(|(year, _, month, _, day)| valid_date(year, month, day)),
+        opt(time),
+    )
+        .take()
+        .map(str::to_string)
+        .parse_next(s)
+}
+
+/// `20240101T093000Z`, the format taskwarrior exports dates in
+fn compact_date(s: &mut &str) -> PResult<String> {
+    (
+        (digits(4), digits(2), digits(2)).verify(|(year, month, day)| valid_date(year, month, day)),
+        "T",
+        (digits(2), digits(2), digits(2))
+            .verify(|(hour, minute, second)| valid_time(hour, minute, Some(second))),
+        "Z",
+    )
+        .take()
+        .map(str::to_string)
+        .parse_next(s)
+}
+
+/// Day of the month, like `1st` or `15th`
+fn ordinal(s: &mut &str) -> PResult<String> {
+    (
+        digit1.verify(|day: &str| day.parse::<u32>().is_ok_and(|d| (1..=31).contains(&d))),
+        alt(("st", "nd", "rd", "th")),
+    )
+        .take()
+        .map(str::to_string)
+        .parse_next(s)
+}
+
+fn synonym(s: &mut &str) -> PResult<String> {
+    alpha1
+        .map(str::to_lowercase)
+        .verify(|name: &String| SYNONYMS.contains(&name.as_str()))
+        .parse_next(s)
+}
+
+/// A date written in the user's `rc.dateformat`, like `12/31/2024`, which can't be checked
+/// without knowing the format
+fn formatted_date(s: &mut &str) -> PResult<String> {
+    take_while(1.., |c: char| c.is_ascii_digit() || "/.:".contains(c))
+        .verify(|date: &str| date.chars().any(|c| c.is_ascii_digit()))
+        .map(str::to_string)
+        .parse_next(s)
+}
+
+fn iso_duration(s: &mut &str) -> PResult<String> {
+    (
+        "P",
+        take_while(1.., |c: char| c.is_ascii_digit() || "YMWDTHS".contains(c)),
+    )
+        .take()
+        .map(str::to_string)
+        .parse_next(s)
+}
+
+fn unit_duration(s: &mut &str) -> PResult<String> {
+    let count = opt(take_while(1.., |c: char| c.is_ascii_digit() || c == '.')).parse_next(s)?;
+    let unit = alpha1
+        .map(str::to_lowercase)
+        .verify(|unit: &String| DURATION_UNITS.contains(&unit.as_str()))
+        .parse_next(s)?;
+    Ok(format!("{}{}", count.unwrap_or_default(), unit))
+}
+
+pub(super) fn duration(s: &mut &str) -> PResult<String> {
+    alt((iso_duration, unit_duration)).parse_next(s)
+}
+
+fn offsets(s: &mut &str) -> PResult<String> {
+    repeat(0.., (one_of(['+', '-']), duration))
+        .fold(String::new, |mut acc, (sign, duration)| {
+            acc.push(sign);
+            acc.push_str(&duration);
+            acc
+        })
+        .parse_next(s)
+}
+
+fn date(s: &mut &str) -> PResult<String> {
+    let base = alt((iso_date, compact_date, ordinal, synonym, formatted_date));
+    alt((
+        eof.map(|_| String::new()),
+        terminated((base, offsets), eof).map(|(base, offsets)| base + &offsets),
+        // Offsets and bare durations count from now
+        terminated(offsets.verify(|offsets: &str| !offsets.is_empty()), eof)
+            .map(|offsets| format!("now{}", offsets)),
+        terminated(duration, eof).map(|duration| format!("now+{}", duration)),
+    ))
+    .parse_next(s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn normalized(input: &str) -> String {
+        Date::from_str(input).unwrap().to_string()
+    }
+
+    #[test]
+    fn test_dates() {
+        assert_eq!("eom", normalized("eom"));
+        assert_eq!("monday", normalized("Monday"));
+        assert_eq!("2024-01-01", normalized("2024-01-01"));
+        assert_eq!("2024-02-29T09:30:00Z", normalized("2024-02-29T09:30:00Z"));
+        assert_eq!("20240101T093000Z", normalized("20240101T093000Z"));
+        assert_eq!("eom+1d", normalized("eom+1d"));
+        assert_eq!("15th", normalized("15th"));
+        assert_eq!("12/31/2024", normalized("12/31/2024"));
+        assert_eq!("now+3d", normalized("+3d"));
+        assert_eq!("now+P1D", normalized("+P1D"));
+        assert_eq!("now-1w+2d", normalized("-1W+2D"));
+        assert_eq!("now+2weeks", normalized("2weeks"));
+        assert_eq!("", normalized(""));
+    }
+
+    #[test]
+    fn test_invalid_dates() {
+        for input in [
+            "tomorow",
+            "2023-02-29",
+            "2024-13-01",
+            "eom+3",
+            "32nd",
+            "+",
+            "now+",
+        ] {
+            assert!(
+                matches!(Date::from_str(input), Err(ParseError::Date(_))),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_durations() {
+        assert_eq!("3d", Duration::from_str("3d").unwrap().to_string());
+        assert_eq!("weekly", Duration::from_str("Weekly").unwrap().to_string());
+        assert_eq!("P1D", Duration::from_str("P1D").unwrap().to_string());
+        assert!(Duration::from_str("3x").is_err());
+    }
+}
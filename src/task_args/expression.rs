@@ -1,34 +1,168 @@
-use super::multi_word;
-use super::project::project;
-use super::project::Project;
-use super::word;
-use super::word_space_or_end;
-use super::ParseError;
-use color_eyre::eyre::bail;
-use color_eyre::Result;
-use core::fmt::Error;
-use log::debug;
-use log::info;
-use log::trace;
-use serde::Serialize;
-use std::env;
+use super::filter::{filter, Filter};
 use std::fmt;
-use std::fs::File;
-use std::io::{Read, Write};
-use std::path::Path;
-use std::process::Command;
 use std::str;
-use std::str::FromStr;
-use winnow::ascii::alphanumeric1;
-use winnow::ascii::multispace1;
-use winnow::combinator::alt;
-use winnow::combinator::eof;
-use winnow::combinator::repeat;
-use winnow::stream::Accumulate;
-use winnow::stream::AsChar;
-use winnow::token::one_of;
-use winnow::token::take_while;
+use winnow::ascii::{multispace0, multispace1};
+use winnow::combinator::{alt, delimited, opt, peek, repeat, terminated};
 use winnow::PResult;
 use winnow::Parser;
 
-pub struct Expression {}
+/// Logical operator joining two parts of a filter expression
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Operator {
+    And,
+    Or,
+    Xor,
+}
+
+impl fmt::Display for Operator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let repr = match self {
+            Operator::And => "and",
+            Operator::Or => "or",
+            Operator::Xor => "xor",
+        };
+        write!(f, "{}", repr)
+    }
+}
+
+/// Piece of a filter expression. Adjacent filters without an operator between them are joined
+/// by an implicit `and`, like taskwarrior does
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Token {
+    Filter(Filter),
+    Operator(Operator),
+    Open,
+    Close,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Filter(filter) => write!(f, "{}", filter),
+            Token::Operator(operator) => write!(f, "{}", operator),
+            Token::Open => write!(f, "("),
+            Token::Close => write!(f, ")"),
+        }
+    }
+}
+
+/// Whether `tokens` have an `or` or `xor` outside of any parentheses, so they need grouping
+/// before more filters can be added without changing what they match
+pub fn needs_grouping(tokens: &[Token]) -> bool {
+    let mut depth = 0;
+    tokens.iter().any(|token| {
+        match token {
+            Token::Open => depth += 1,
+            Token::Close => depth -= 1,
+            Token::Operator(Operator::Or | Operator::Xor) => return depth == 0,
+            _ => {}
+        }
+        false
+    })
+}
+
+/// Render `tokens` the way they would be typed: space separated, without spaces inside
+/// parentheses
+pub fn render(tokens: &[Token]) -> String {
+    let mut repr = String::new();
+    let mut previous = None;
+    for token in tokens {
+        if previous.is_some_and(|p| p != &Token::Open) && token != &Token::Close {
+            repr.push(' ');
+        }
+        repr.push_str(&token.to_string());
+        previous = Some(token);
+    }
+    repr
+}
+
+fn operator(s: &mut &str) -> PResult<Operator> {
+    // An operator is a whole word, so 'order:x' is still a filter
+    terminated(
+        alt((
+            "and".value(Operator::And),
+            "xor".value(Operator::Xor),
+            "or".value(Operator::Or),
+        )),
+        peek(alt((multispace1, "("))),
+    )
+    .parse_next(s)
+}
+
+fn group(s: &mut &str) -> PResult<Vec<Token>> {
+    delimited(("(", multispace0), expression, (multispace0, ")"))
+        .map(|inner| {
+            let mut tokens = vec![Token::Open];
+            tokens.extend(inner);
+            tokens.push(Token::Close);
+            tokens
+        })
+        .parse_next(s)
+}
+
+fn term(s: &mut &str) -> PResult<Vec<Token>> {
+    alt((group, filter.map(|f| vec![Token::Filter(f)]))).parse_next(s)
+}
+
+fn joined_term(s: &mut &str) -> PResult<Vec<Token>> {
+    let _ = multispace0.parse_next(s)?;
+    let operator = opt(terminated(operator, multispace0)).parse_next(s)?;
+    let term = term.parse_next(s)?;
+    let mut tokens: Vec<Token> = operator.map(Token::Operator).into_iter().collect();
+    tokens.extend(term);
+    Ok(tokens)
+}
+
+/// Parse filters joined by `and`, `or` and `xor`, grouped with parentheses
+pub fn expression(s: &mut &str) -> PResult<Vec<Token>> {
+    let mut tokens = term.parse_next(s)?;
+    let rest: Vec<Vec<Token>> = repeat(0.., joined_term).parse_next(s)?;
+    tokens.extend(rest.into_iter().flatten());
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task_args::project::Project;
+    use pretty_assertions::assert_eq;
+
+    fn project(name: &str) -> Token {
+        Token::Filter(Filter::Project(Project::with_name(name)))
+    }
+
+    #[test]
+    fn test_expression() {
+        let mut input = "(project:foo or project:bar) and project:baz";
+        let expected = vec![
+            Token::Open,
+            project("foo"),
+            Token::Operator(Operator::Or),
+            project("bar"),
+            Token::Close,
+            Token::Operator(Operator::And),
+            project("baz"),
+        ];
+        let actual = expression.parse_next(&mut input).unwrap();
+        assert_eq!(expected, actual);
+        assert_eq!("", input);
+        assert_eq!(
+            "(project:foo or project:bar) and project:baz",
+            render(&actual)
+        );
+        assert!(!needs_grouping(&actual));
+    }
+
+    #[test]
+    fn test_expression_unbalanced() {
+        let mut input = "(project:foo or project:bar";
+        assert!(expression.parse_next(&mut input).is_err());
+    }
+
+    #[test]
+    fn test_needs_grouping() {
+        let mut input = "project:foo xor project:bar";
+        let actual = expression.parse_next(&mut input).unwrap();
+        assert!(needs_grouping(&actual));
+    }
+}
@@ -1,27 +1,15 @@
+use super::expression::{expression, needs_grouping, render, Token};
 use super::project::{project, Project};
 use super::word;
 use super::ParseError;
-use color_eyre::eyre::bail;
 use color_eyre::Result;
-use core::fmt::Error;
-use log::debug;
-use log::info;
-use log::trace;
-use serde::Serialize;
-use std::env;
+use std::collections::BTreeMap;
 use std::fmt;
-use std::fs::File;
-use std::io::{Read, Write};
-use std::path::Path;
-use std::process::Command;
 use std::str;
 use std::str::FromStr;
-use winnow::ascii::{alphanumeric1, multispace1};
-use winnow::combinator::repeat;
-use winnow::combinator::{alt, eof};
-use winnow::stream::Accumulate;
+use winnow::ascii::{dec_uint, multispace0, multispace1};
+use winnow::combinator::{alt, delimited, eof, opt, peek, preceded, separated, terminated};
 use winnow::stream::AsChar;
-use winnow::token::one_of;
 use winnow::token::take_while;
 use winnow::PResult;
 use winnow::Parser;
@@ -29,13 +17,158 @@ use winnow::Parser;
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub enum Filter {
     Project(Project),
-    Other { name: String, value: String },
+    /// Reference to a filter defined in the config file
+    Named(String),
+    /// Tasks by working set id, like `12` or `1-5,8`
+    Ids(Vec<IdRange>),
+    /// Task by uuid, in full or shortened to its first 8 digits
+    Uuid(String),
+    /// `+name` matches tasks with the tag, `-name` tasks without it
+    Tag {
+        name: String,
+        negated: bool,
+    },
+    /// `+NAME` or `-NAME` for a tag taskwarrior computes from the task, like `+OVERDUE`
+    VirtualTag {
+        name: String,
+        negated: bool,
+    },
+    /// `name.modifier:value`, like `due.before:eom` or `project.not:home`
+    Attribute {
+        name: String,
+        modifier: String,
+        value: String,
+    },
+    Other {
+        name: String,
+        value: String,
+    },
+}
+
+/// Inclusive range of task ids. A single id starts and ends at the same id
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct IdRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl fmt::Display for IdRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.start == self.end {
+            write!(f, "{}", self.start)
+        } else {
+            write!(f, "{}-{}", self.start, self.end)
+        }
+    }
+}
+
+/// Tags taskwarrior computes from each task rather than storing
+pub const VIRTUAL_TAGS: &[&str] = &[
+    "ACTIVE",
+    "ANNOTATED",
+    "BLOCKED",
+    "BLOCKING",
+    "CHILD",
+    "COMPLETED",
+    "DELETED",
+    "DUE",
+    "DUETODAY",
+    "INSTANCE",
+    "LATEST",
+    "MONTH",
+    "ORPHAN",
+    "OVERDUE",
+    "PARENT",
+    "PENDING",
+    "PRIORITY",
+    "PROJECT",
+    "QUARTER",
+    "READY",
+    "SCHEDULED",
+    "TAGGED",
+    "TEMPLATE",
+    "TODAY",
+    "TOMORROW",
+    "UDA",
+    "UNBLOCKED",
+    "UNTIL",
+    "WAITING",
+    "WEEK",
+    "YEAR",
+    "YESTERDAY",
+];
+
+/// Attribute modifiers taskwarrior understands
+const MODIFIERS: &[&str] = &[
+    "before",
+    "after",
+    "under",
+    "over",
+    "below",
+    "above",
+    "by",
+    "none",
+    "any",
+    "is",
+    "equals",
+    "isnt",
+    "not",
+    "has",
+    "contains",
+    "hasnt",
+    "startswith",
+    "left",
+    "endswith",
+    "right",
+    "word",
+    "noword",
+];
+
+impl Filter {
+    /// The attribute this filter constrains, if any
+    pub fn attribute(&self) -> Option<&str> {
+        match self {
+            Filter::Project(_) => Some("project"),
+            Filter::Ids(_) => Some("id"),
+            Filter::Uuid(_) => Some("uuid"),
+            Filter::Named(_) | Filter::Tag { .. } | Filter::VirtualTag { .. } => None,
+            Filter::Attribute { name, .. } | Filter::Other { name, .. } => Some(name),
+        }
+    }
+
+    /// The tag this filter requires or excludes, if any
+    fn tag(&self) -> Option<&str> {
+        match self {
+            Filter::Tag { name, .. } | Filter::VirtualTag { name, .. } => Some(name),
+            _ => None,
+        }
+    }
+
+    /// Whether this filter and `other` constrain the same attribute or tag
+    fn overlaps(&self, other: &Filter) -> bool {
+        (self.attribute().is_some() && self.attribute() == other.attribute())
+            || (self.tag().is_some() && self.tag() == other.tag())
+    }
 }
 
 impl fmt::Display for Filter {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let repr = match self {
             Filter::Project(project) => project.to_string(),
+            Filter::Named(name) => format!("@{}", name),
+            Filter::Ids(ranges) => {
+                let ranges: Vec<String> = ranges.iter().map(|r| r.to_string()).collect();
+                ranges.join(",")
+            }
+            Filter::Uuid(uuid) => uuid.clone(),
+            Filter::Tag { name, negated } | Filter::VirtualTag { name, negated } => {
+                format!("{}{}", if *negated { "-" } else { "+" }, name)
+            }
+            Filter::Attribute {
+                name,
+                modifier,
+                value,
+            } => format!("{}.{}:{}", name, modifier, value),
             Filter::Other { name, value } => {
                 format!("{}:{}", name, value)
             }
@@ -51,55 +184,265 @@ fn other(s: &mut &str) -> PResult<Filter> {
     Ok(Filter::Other { name, value })
 }
 
-fn filter(s: &mut &str) -> PResult<Filter> {
-    alt((project.map(|p| Filter::Project(p)), other)).parse_next(s)
+fn attribute(s: &mut &str) -> PResult<Filter> {
+    let name = take_while(1.., |c: char| c.is_alphanum() || c == '_' || c == '-')
+        .map(|s: &str| s.to_string())
+        .parse_next(s)?;
+    let _ = ".".parse_next(s)?;
+    let modifier = take_while(1.., AsChar::is_alpha)
+        .verify(|m: &str| MODIFIERS.contains(&m))
+        .map(|s: &str| s.to_string())
+        .parse_next(s)?;
+    let _ = ":".parse_next(s)?;
+    let value = opt(word)
+        .map(|s: Option<&str>| s.unwrap_or_default().to_string())
+        .parse_next(s)?;
+    Ok(Filter::Attribute {
+        name,
+        modifier,
+        value,
+    })
+}
+
+fn named(s: &mut &str) -> PResult<Filter> {
+    let _ = "@".parse_next(s)?;
+    word.map(|s: &str| Filter::Named(s.to_string()))
+        .parse_next(s)
+}
+
+/// End of a token that stands on its own, so `12` isn't taken from the front of `12:x`
+fn boundary(s: &mut &str) -> PResult<()> {
+    peek(alt((multispace1, ")", eof))).void().parse_next(s)
+}
+
+fn id_range(s: &mut &str) -> PResult<IdRange> {
+    let start = dec_uint.parse_next(s)?;
+    let end = opt(preceded("-", dec_uint)).parse_next(s)?;
+    Ok(IdRange {
+        start,
+        end: end.unwrap_or(start),
+    })
+}
+
+fn ids(s: &mut &str) -> PResult<Filter> {
+    terminated(separated(1.., id_range, ","), boundary)
+        .map(Filter::Ids)
+        .parse_next(s)
+}
+
+/// A full uuid, or its first 8 digits
+pub(super) fn uuid_text<'a>(s: &mut &'a str) -> PResult<&'a str> {
+    let hex = |count: usize| take_while(count, AsChar::is_hex_digit);
+    let long = (hex(8), "-", hex(4), "-", hex(4), "-", hex(4), "-", hex(12));
+    alt((long.take(), hex(8))).parse_next(s)
+}
+
+fn uuid(s: &mut &str) -> PResult<Filter> {
+    terminated(uuid_text, boundary)
+        .map(|uuid: &str| Filter::Uuid(uuid.to_string()))
+        .parse_next(s)
+}
+
+fn tag(s: &mut &str) -> PResult<Filter> {
+    let negated = alt(("+".value(false), "-".value(true))).parse_next(s)?;
+    let name = word.map(|s: &str| s.to_string()).parse_next(s)?;
+    if VIRTUAL_TAGS.contains(&name.as_str()) {
+        return Ok(Filter::VirtualTag { name, negated });
+    }
+    Ok(Filter::Tag { name, negated })
+}
+
+/// Number of single character edits to turn `a` into `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// The virtual tag a tag written with capitals was likely meant to be, like `OVERDUE` for
+/// `Overdue` or `OVERDEU`. Lowercase tags are the user's own and left alone
+fn intended_virtual_tag(name: &str) -> Option<&'static str> {
+    if !name.chars().any(char::is_uppercase) {
+        return None;
+    }
+    let upper = name.to_uppercase();
+    let uppercase = upper == name;
+    VIRTUAL_TAGS
+        .iter()
+        .map(|tag| (edit_distance(&upper, tag), *tag))
+        .filter(|(distance, _)| *distance == 0 || (uppercase && *distance <= 2))
+        .min()
+        .map(|(_, tag)| tag)
+}
+
+pub(super) fn filter(s: &mut &str) -> PResult<Filter> {
+    alt((
+        project.map(Filter::Project),
+        named,
+        tag,
+        uuid,
+        ids,
+        attribute,
+        other,
+    ))
+    .parse_next(s)
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Filters {
-    filters: Vec<Filter>,
+    tokens: Vec<Token>,
 }
 
 impl Filters {
-    fn with_filters(filters: Vec<Filter>) -> Self {
-        Filters { filters }
+    /// Filters joined by implicit `and`s
+    pub fn with_filters(filters: Vec<Filter>) -> Self {
+        Filters {
+            tokens: filters.into_iter().map(Token::Filter).collect(),
+        }
     }
 
-    pub fn filters(&self) -> &[Filter] {
-        &self.filters
+    fn with_tokens(tokens: Vec<Token>) -> Self {
+        Filters { tokens }
+    }
+
+    /// Every filter in the expression, ignoring the operators and parentheses joining them
+    pub fn filters(&self) -> impl Iterator<Item = &Filter> {
+        self.tokens.iter().filter_map(|token| match token {
+            Token::Filter(filter) => Some(filter),
+            _ => None,
+        })
+    }
+
+    pub fn tokens(&self) -> &[Token] {
+        &self.tokens
+    }
+
+    /// Whether any filter in the expression constrains `attribute`
+    pub fn constrains(&self, attribute: &str) -> bool {
+        self.filters().any(|f| f.attribute() == Some(attribute))
+    }
+
+    /// Whether the expression picks out tasks by id or uuid
+    pub fn names_tasks(&self) -> bool {
+        self.filters()
+            .any(|f| matches!(f, Filter::Ids(_) | Filter::Uuid(_)))
+    }
+
+    /// Whether the expression is only filters, without operators or parentheses
+    fn is_simple(&self) -> bool {
+        self.tokens
+            .iter()
+            .all(|token| matches!(token, Token::Filter(_)))
+    }
+
+    /// Tokens of the expression, parenthesized if other filters can't be added next to them as is
+    fn grouped(&self) -> Vec<Token> {
+        if !needs_grouping(&self.tokens) {
+            return self.tokens.clone();
+        }
+        let mut tokens = vec![Token::Open];
+        tokens.extend(self.tokens.iter().cloned());
+        tokens.push(Token::Close);
+        tokens
     }
-}
 
-impl Accumulate<Filter> for Filters {
-    fn initial(capacity: Option<usize>) -> Self {
-        let filters = match capacity {
-            Some(c) => Vec::with_capacity(c),
-            None => Vec::new(),
+    /// Arguments to pass the expression to taskwarrior with, one per token. Grouped so filters
+    /// added around them, like the detected project, constrain the whole expression
+    pub fn args(&self) -> Vec<String> {
+        self.grouped()
+            .iter()
+            .map(|token| token.to_string())
+            .collect()
+    }
+
+    /// Prepend each of `defaults` whose attribute or tag is not already constrained by these
+    /// filters. Defaults using operators are kept or dropped as a whole
+    pub fn with_defaults(&self, defaults: &Filters) -> Self {
+        let constrained = |default: &Filter| self.filters().any(|f| f.overlaps(default));
+        let mut tokens: Vec<Token> = if defaults.is_simple() {
+            defaults
+                .filters()
+                .filter(|default| !constrained(default))
+                .cloned()
+                .map(Token::Filter)
+                .collect()
+        } else if defaults.filters().any(constrained) {
+            Vec::new()
+        } else {
+            defaults.grouped()
         };
-        Filters { filters }
+        tokens.extend(self.grouped());
+        Filters::with_tokens(tokens)
+    }
+
+    /// Replace named filters with the filters they are defined as
+    pub fn expand(&self, named: &BTreeMap<String, String>) -> Result<Self, ParseError> {
+        self.expand_inner(named, &mut Vec::new())
     }
 
-    fn accumulate(&mut self, acc: Filter) {
-        self.filters.push(acc);
+    fn expand_inner(
+        &self,
+        named: &BTreeMap<String, String>,
+        seen: &mut Vec<String>,
+    ) -> Result<Self, ParseError> {
+        let mut tokens = Vec::with_capacity(self.tokens.len());
+        for token in &self.tokens {
+            let Token::Filter(Filter::Named(name)) = token else {
+                tokens.push(token.clone());
+                continue;
+            };
+            if seen.contains(name) {
+                return Err(ParseError::RecursiveFilter(name.clone()));
+            }
+            let Some(definition) = named.get(name) else {
+                return Err(ParseError::UnknownFilter(name.clone()));
+            };
+            let expanded =
+                Filters::from_str(definition).map_err(|_| ParseError::NamedFilter(name.clone()))?;
+            seen.push(name.clone());
+            tokens.extend(expanded.expand_inner(named, seen)?.grouped());
+            seen.pop();
+        }
+        Ok(Filters::with_tokens(tokens))
     }
 }
 
-fn filter_space_or_end<'a>(s: &mut &'a str) -> PResult<Filter> {
-    let f = filter.parse_next(s)?;
-    let _ = alt((multispace1, eof)).parse_next(s)?;
-    Ok(f)
+impl fmt::Display for Filters {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", render(&self.tokens))
+    }
 }
 
 fn filters(s: &mut &str) -> PResult<Filters> {
-    repeat(0.., filter_space_or_end)
-        .map(|filters| Filters::with_filters(filters))
+    delimited(multispace0, opt(expression), multispace0)
+        .map(|tokens| Filters::with_tokens(tokens.unwrap_or_default()))
         .parse_next(s)
 }
 
 impl FromStr for Filters {
     type Err = ParseError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        filters.parse(s).map_err(|_| ParseError::Filter)
+        let filters = filters.parse(s).map_err(|_| ParseError::Filter)?;
+        for filter in filters.filters() {
+            let Filter::Tag { name, .. } = filter else {
+                continue;
+            };
+            if let Some(suggestion) = intended_virtual_tag(name) {
+                return Err(ParseError::VirtualTag {
+                    name: name.clone(),
+                    suggestion: suggestion.to_string(),
+                });
+            }
+        }
+        Ok(filters)
     }
 }
 
@@ -107,13 +450,12 @@ impl FromStr for Filters {
 mod tests {
     use super::*;
     use pretty_assertions::assert_eq;
+    use s_string::s;
 
     #[test]
     fn test_filters_single() {
         let mut input = "project:test";
-        let expected = Filters {
-            filters: vec![Filter::Project(Project::with_name("test"))],
-        };
+        let expected = Filters::with_filters(vec![Filter::Project(Project::with_name("test"))]);
         let actual = filters.parse_next(&mut input).unwrap();
         assert_eq!(expected, actual);
         assert_eq!("", input);
@@ -122,15 +464,13 @@ mod tests {
     #[test]
     fn test_filters_multi() {
         let mut input = "project:test foo:bar";
-        let expected = Filters {
-            filters: vec![
-                Filter::Project(Project::with_name("test")),
-                Filter::Other {
-                    name: String::from("foo"),
-                    value: String::from("bar"),
-                },
-            ],
-        };
+        let expected = Filters::with_filters(vec![
+            Filter::Project(Project::with_name("test")),
+            Filter::Other {
+                name: String::from("foo"),
+                value: String::from("bar"),
+            },
+        ]);
         let actual = filters.parse_next(&mut input).unwrap();
         assert_eq!(expected, actual);
         assert_eq!("", input);
@@ -139,16 +479,218 @@ mod tests {
     #[test]
     fn test_filters_from_str() {
         let input = "project:test foo:bar";
-        let expected = Filters {
-            filters: vec![
-                Filter::Project(Project::with_name("test")),
-                Filter::Other {
-                    name: String::from("foo"),
-                    value: String::from("bar"),
-                },
-            ],
+        let expected = Filters::with_filters(vec![
+            Filter::Project(Project::with_name("test")),
+            Filter::Other {
+                name: String::from("foo"),
+                value: String::from("bar"),
+            },
+        ]);
+        let actual = Filters::from_str(input).unwrap();
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_filters_tags() {
+        let input = "+work -home project:test";
+        let expected = Filters::with_filters(vec![
+            Filter::Tag {
+                name: s!("work"),
+                negated: false,
+            },
+            Filter::Tag {
+                name: s!("home"),
+                negated: true,
+            },
+            Filter::Project(Project::with_name("test")),
+        ]);
+        let actual = Filters::from_str(input).unwrap();
+        assert_eq!(expected, actual);
+        assert_eq!(input, actual.to_string());
+    }
+
+    #[test]
+    fn test_filters_attribute_modifiers() {
+        let input = "due.before:eom project.not:home description.contains:x project.none:";
+        let attribute = |name: &str, modifier: &str, value: &str| Filter::Attribute {
+            name: s!(name),
+            modifier: s!(modifier),
+            value: s!(value),
         };
+        let expected = Filters::with_filters(vec![
+            attribute("due", "before", "eom"),
+            attribute("project", "not", "home"),
+            attribute("description", "contains", "x"),
+            attribute("project", "none", ""),
+        ]);
+        let actual = Filters::from_str(input).unwrap();
+        assert_eq!(expected, actual);
+        assert_eq!(input, actual.to_string());
+        assert_eq!(
+            Some("project"),
+            actual.filters().nth(1).unwrap().attribute()
+        );
+
+        // Not a modifier, so just an attribute with a dot in its name
+        let actual = Filters::from_str("foo.bar:baz").unwrap();
+        let expected = Filters::with_filters(vec![Filter::Other {
+            name: s!("foo.bar"),
+            value: s!("baz"),
+        }]);
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_filters_ids() {
+        let input = "12 1-5,8 8d3f2a1c 8d3f2a1c-8ab4-4c4b-9d5e-0123456789ab 12:x";
+        let expected = Filters::with_filters(vec![
+            Filter::Ids(vec![IdRange { start: 12, end: 12 }]),
+            Filter::Ids(vec![
+                IdRange { start: 1, end: 5 },
+                IdRange { start: 8, end: 8 },
+            ]),
+            Filter::Uuid(s!("8d3f2a1c")),
+            Filter::Uuid(s!("8d3f2a1c-8ab4-4c4b-9d5e-0123456789ab")),
+            Filter::Other {
+                name: s!("12"),
+                value: s!("x"),
+            },
+        ]);
         let actual = Filters::from_str(input).unwrap();
         assert_eq!(expected, actual);
+        assert_eq!(input, actual.to_string());
+        assert!(actual.names_tasks());
+        assert!(!Filters::from_str("+work").unwrap().names_tasks());
+    }
+
+    #[test]
+    fn test_filters_virtual_tags() {
+        let actual = Filters::from_str("+OVERDUE -BLOCKED +overdue +WIP").unwrap();
+        let expected = Filters::with_filters(vec![
+            Filter::VirtualTag {
+                name: s!("OVERDUE"),
+                negated: false,
+            },
+            Filter::VirtualTag {
+                name: s!("BLOCKED"),
+                negated: true,
+            },
+            Filter::Tag {
+                name: s!("overdue"),
+                negated: false,
+            },
+            Filter::Tag {
+                name: s!("WIP"),
+                negated: false,
+            },
+        ]);
+        assert_eq!(expected, actual);
+
+        for typo in ["+OVERDEU", "+Overdue"] {
+            let actual = Filters::from_str(typo);
+            assert!(
+                matches!(actual, Err(ParseError::VirtualTag { ref suggestion, .. }) if suggestion == "OVERDUE"),
+                "{:?}",
+                actual
+            );
+        }
+    }
+
+    #[test]
+    fn test_filters_operators() {
+        let input = "(project:foo or project:bar) +work";
+        let actual = Filters::from_str(input).unwrap();
+        assert_eq!(input, actual.to_string());
+        assert!(actual.constrains("project"));
+        assert_eq!(
+            vec![
+                s!("("),
+                s!("project:foo"),
+                s!("or"),
+                s!("project:bar"),
+                s!(")"),
+                s!("+work"),
+            ],
+            actual.args()
+        );
+
+        let actual = Filters::from_str("+work or +home").unwrap();
+        assert_eq!(
+            vec![s!("("), s!("+work"), s!("or"), s!("+home"), s!(")")],
+            actual.args()
+        );
+        assert!(Filters::from_str("+work or").is_err());
+    }
+
+    #[test]
+    fn test_filters_with_operator_defaults() {
+        let defaults = Filters::from_str("status:pending or status:waiting").unwrap();
+        let user = Filters::from_str("+work or +home").unwrap();
+        assert_eq!(
+            "(status:pending or status:waiting) (+work or +home)",
+            user.with_defaults(&defaults).to_string()
+        );
+        let user = Filters::from_str("status:completed").unwrap();
+        assert_eq!(
+            "status:completed",
+            user.with_defaults(&defaults).to_string()
+        );
+    }
+
+    #[test]
+    fn test_filters_with_defaults() {
+        let defaults = Filters::from_str("project:home status:pending").unwrap();
+        let user = Filters::from_str("status:completed").unwrap();
+        let expected = Filters::with_filters(vec![
+            Filter::Project(Project::with_name("home")),
+            Filter::Other {
+                name: s!("status"),
+                value: s!("completed"),
+            },
+        ]);
+        assert_eq!(expected, user.with_defaults(&defaults));
+
+        let defaults = Filters::from_str("+READY -someday").unwrap();
+        let user = Filters::from_str("+someday").unwrap();
+        assert_eq!("+READY +someday", user.with_defaults(&defaults).to_string());
+        let user = Filters::from_str("-READY").unwrap();
+        assert_eq!("-someday -READY", user.with_defaults(&defaults).to_string());
+    }
+
+    #[test]
+    fn test_filters_expand() {
+        let named = BTreeMap::from([
+            (s!("mine"), s!("@work owner:me")),
+            (s!("work"), s!("project:work")),
+        ]);
+        let expected = Filters::with_filters(vec![
+            Filter::Project(Project::with_name("work")),
+            Filter::Other {
+                name: s!("owner"),
+                value: s!("me"),
+            },
+            Filter::Other {
+                name: s!("foo"),
+                value: s!("bar"),
+            },
+        ]);
+        let actual = Filters::from_str("@mine foo:bar")
+            .unwrap()
+            .expand(&named)
+            .unwrap();
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_filters_expand_unknown() {
+        let actual = Filters::from_str("@nope").unwrap().expand(&BTreeMap::new());
+        assert!(matches!(actual, Err(ParseError::UnknownFilter(name)) if name == "nope"));
+    }
+
+    #[test]
+    fn test_filters_expand_recursive() {
+        let named = BTreeMap::from([(s!("loop"), s!("@loop"))]);
+        let actual = Filters::from_str("@loop").unwrap().expand(&named);
+        assert!(matches!(actual, Err(ParseError::RecursiveFilter(_))));
     }
 }
@@ -1,33 +1,22 @@
-use super::multi_word;
+use super::date::{Date, Duration, DATE_ATTRIBUTES};
+use super::filter::uuid_text;
 use super::project::project;
 use super::project::Project;
 use super::word;
-use super::word_space_or_end;
 use super::ParseError;
-use color_eyre::eyre::bail;
 use color_eyre::Result;
-use core::fmt::Error;
-use log::debug;
-use log::info;
-use log::trace;
-use serde::Serialize;
-use std::env;
 use std::fmt;
-use std::fs::File;
-use std::io::{Read, Write};
-use std::path::Path;
-use std::process::Command;
 use std::str;
 use std::str::FromStr;
-use winnow::ascii::alphanumeric1;
-use winnow::ascii::multispace1;
+use winnow::ascii::{dec_uint, multispace1};
 use winnow::combinator::alt;
+use winnow::combinator::cut_err;
 use winnow::combinator::eof;
+use winnow::combinator::opt;
 use winnow::combinator::repeat;
+use winnow::combinator::{peek, separated, terminated};
 use winnow::stream::Accumulate;
-use winnow::stream::AsChar;
-use winnow::token::one_of;
-use winnow::token::take_while;
+use winnow::token::take_till;
 use winnow::PResult;
 use winnow::Parser;
 
@@ -35,27 +24,292 @@ use winnow::Parser;
 pub enum Modifier {
     Description(String),
     Project(Project),
-    Other { name: String, value: String },
+    /// `+name` adds the tag, `-name` removes it
+    Tag {
+        name: String,
+        removed: bool,
+    },
+    /// Date attribute, like `due:eom`
+    Date {
+        name: String,
+        value: Date,
+    },
+    /// `depends:3,7`, or `depends:` to clear them
+    Depends(Vec<Dependency>),
+    /// `recur:weekly`, or `recur:` to stop recurring
+    Recur(Option<Duration>),
+    /// `priority:H`, or `priority:` to clear it
+    Priority(Option<Priority>),
+    Other {
+        name: String,
+        value: String,
+    },
+}
+
+/// Attributes taskwarrior has without any UDAs configured
+const BUILTIN_ATTRIBUTES: &[&str] = &[
+    "description",
+    "project",
+    "priority",
+    "due",
+    "wait",
+    "scheduled",
+    "until",
+    "entry",
+    "start",
+    "end",
+    "modified",
+    "recur",
+    "depends",
+    "tags",
+    "status",
+    "uuid",
+    "id",
+    "mask",
+    "imask",
+    "parent",
+    "template",
+    "last",
+    "rtype",
+    "urgency",
+];
+
+/// Shortest abbreviation taskwarrior accepts for an attribute name, its default
+/// `rc.abbreviation.minimum`
+const ABBREVIATION_MINIMUM: usize = 2;
+
+/// Names the depends attribute can be set by
+const DEPENDS_ATTRIBUTES: &[&str] = &["depends", "dep"];
+
+/// Task referred to by its id or uuid
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum TaskRef {
+    Id(u64),
+    Uuid(String),
+}
+
+impl fmt::Display for TaskRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TaskRef::Id(id) => write!(f, "{}", id),
+            TaskRef::Uuid(uuid) => write!(f, "{}", uuid),
+        }
+    }
+}
+
+/// Entry of `depends:`. A `-` before the task removes the dependency instead of adding it
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Dependency {
+    pub task: TaskRef,
+    pub removed: bool,
+}
+
+impl fmt::Display for Dependency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", if self.removed { "-" } else { "" }, self.task)
+    }
+}
+
+/// Names the priority attribute can be set by
+const PRIORITY_ATTRIBUTES: &[&str] = &["priority", "pri"];
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Priority {
+    High,
+    Medium,
+    Low,
+}
+
+impl FromStr for Priority {
+    type Err = ParseError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "H" => Ok(Priority::High),
+            "M" => Ok(Priority::Medium),
+            "L" => Ok(Priority::Low),
+            _ => {
+                // 'high', 'med' and 'l' were likely meant as the letter they start with
+                let suggestion = s
+                    .chars()
+                    .next()
+                    .map(|c| c.to_ascii_uppercase().to_string())
+                    .filter(|c| ["H", "M", "L"].contains(&c.as_str()));
+                Err(ParseError::Priority {
+                    value: s.to_string(),
+                    suggestion,
+                })
+            }
+        }
+    }
+}
+
+impl fmt::Display for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let repr = match self {
+            Priority::High => "H",
+            Priority::Medium => "M",
+            Priority::Low => "L",
+        };
+        write!(f, "{}", repr)
+    }
+}
+
+impl Modifier {
+    /// The attribute this sets, if any
+    pub fn attribute(&self) -> Option<&str> {
+        match self {
+            Modifier::Description(_) | Modifier::Tag { .. } => None,
+            Modifier::Project(_) => Some("project"),
+            Modifier::Date { name, .. } | Modifier::Other { name, .. } => Some(name),
+            Modifier::Depends(_) => Some("depends"),
+            Modifier::Recur(_) => Some("recur"),
+            Modifier::Priority(_) => Some("priority"),
+        }
+    }
+
+    /// Name of the attribute this sets if it's neither built in nor one of `udas`, so taskwarrior
+    /// would take it as part of the description
+    pub fn unknown_attribute(&self, udas: &[String]) -> Option<&str> {
+        let Modifier::Other { name, .. } = self else {
+            return None;
+        };
+        let known = is_builtin_attribute(name) || udas.iter().any(|a| abbreviates(name, a));
+        (!known).then_some(name.as_str())
+    }
+}
+
+/// Whether `name` is `attribute` or an abbreviation taskwarrior accepts for it
+pub(crate) fn abbreviates(name: &str, attribute: &str) -> bool {
+    attribute == name || (name.len() >= ABBREVIATION_MINIMUM && attribute.starts_with(name))
+}
+
+/// Whether `name` is an attribute taskwarrior has without any UDAs configured, or abbreviates one
+pub(crate) fn is_builtin_attribute(name: &str) -> bool {
+    BUILTIN_ATTRIBUTES.iter().any(|a| abbreviates(name, a))
 }
 
 impl FromStr for Modifier {
     type Err = ParseError;
     fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
-        modifier.parse(s).map_err(|_| ParseError::Modifier)
+        modifier
+            .parse(s)
+            .map_err(|_| value_error(s).unwrap_or(ParseError::Modifier))
     }
 }
 
+/// Why `s` didn't parse, if it sets an attribute whose values are checked
+fn value_error(s: &str) -> Option<ParseError> {
+    let (name, value) = s.split_once(':')?;
+    if DATE_ATTRIBUTES.contains(&name) {
+        return Date::from_str(value).err();
+    }
+    if DEPENDS_ATTRIBUTES.contains(&name) {
+        return Some(ParseError::Depends(value.to_string()));
+    }
+    if name == "recur" && !value.is_empty() {
+        return Duration::from_str(value).err();
+    }
+    if PRIORITY_ATTRIBUTES.contains(&name) && !value.is_empty() {
+        return Priority::from_str(value).err();
+    }
+    None
+}
+
 impl fmt::Display for Modifier {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let repr = match self {
             Modifier::Description(desc) => desc.to_string(),
             Modifier::Project(project) => project.to_string(),
+            Modifier::Tag { name, removed } => {
+                format!("{}{}", if *removed { "-" } else { "+" }, name)
+            }
+            Modifier::Date { name, value } => format!("{}:{}", name, value),
+            Modifier::Depends(dependencies) => {
+                let dependencies: Vec<String> =
+                    dependencies.iter().map(|d| d.to_string()).collect();
+                format!("depends:{}", dependencies.join(","))
+            }
+            Modifier::Recur(recur) => match recur {
+                Some(recur) => format!("recur:{}", recur),
+                None => String::from("recur:"),
+            },
+            Modifier::Priority(priority) => match priority {
+                Some(priority) => format!("priority:{}", priority),
+                None => String::from("priority:"),
+            },
             Modifier::Other { name, value } => format!("{}:{}", name, value),
         };
         write!(f, "{}", repr)
     }
 }
 
+fn tag_modifier(s: &mut &str) -> PResult<Modifier> {
+    let removed = alt(("+".value(false), "-".value(true))).parse_next(s)?;
+    let name = word.map(|s: &str| s.to_string()).parse_next(s)?;
+    Ok(Modifier::Tag { name, removed })
+}
+
+fn date_modifier(s: &mut &str) -> PResult<Modifier> {
+    let name = word
+        .verify(|name: &str| DATE_ATTRIBUTES.contains(&name))
+        .map(|s: &str| s.to_string())
+        .parse_next(s)?;
+    let _ = ":".parse_next(s)?;
+    // Don't fall back to a generic modifier for a date taskwarrior can't use
+    let value =
+        cut_err(take_till(0.., char::is_whitespace).try_map(Date::from_str)).parse_next(s)?;
+    Ok(Modifier::Date { name, value })
+}
+
+fn task_ref(s: &mut &str) -> PResult<TaskRef> {
+    alt((
+        terminated(uuid_text, peek(alt((",", multispace1, eof))))
+            .map(|uuid: &str| TaskRef::Uuid(uuid.to_string())),
+        dec_uint.map(TaskRef::Id),
+    ))
+    .parse_next(s)
+}
+
+fn dependency(s: &mut &str) -> PResult<Dependency> {
+    let removed = opt("-").map(|sign| sign.is_some()).parse_next(s)?;
+    let task = task_ref.parse_next(s)?;
+    Ok(Dependency { task, removed })
+}
+
+fn depends_modifier(s: &mut &str) -> PResult<Modifier> {
+    let _ = word
+        .verify(|name: &str| DEPENDS_ATTRIBUTES.contains(&name))
+        .parse_next(s)?;
+    let _ = ":".parse_next(s)?;
+    cut_err(terminated(
+        separated(0.., dependency, ","),
+        peek(alt((multispace1, eof))),
+    ))
+    .map(Modifier::Depends)
+    .parse_next(s)
+}
+
+fn recur_modifier(s: &mut &str) -> PResult<Modifier> {
+    let _ = "recur:".parse_next(s)?;
+    let recur = cut_err(opt(
+        take_till(1.., char::is_whitespace).try_map(Duration::from_str)
+    ))
+    .parse_next(s)?;
+    Ok(Modifier::Recur(recur))
+}
+
+fn priority_modifier(s: &mut &str) -> PResult<Modifier> {
+    let _ = word
+        .verify(|name: &str| PRIORITY_ATTRIBUTES.contains(&name))
+        .parse_next(s)?;
+    let _ = ":".parse_next(s)?;
+    let priority = cut_err(opt(
+        take_till(1.., char::is_whitespace).try_map(Priority::from_str)
+    ))
+    .parse_next(s)?;
+    Ok(Modifier::Priority(priority))
+}
+
 fn other(s: &mut &str) -> PResult<Modifier> {
     let name = word.map(|s: &str| s.to_string()).parse_next(s)?;
     let _ = ":".parse_next(s)?;
@@ -63,11 +317,13 @@ fn other(s: &mut &str) -> PResult<Modifier> {
     Ok(Modifier::Other { name, value })
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Modifiers {
     modifiers: Vec<Modifier>,
 }
 
+#[allow(dead_code)]
 impl Modifiers {
     pub fn modifiers(&self) -> &[Modifier] {
         &self.modifiers
@@ -101,19 +357,30 @@ fn description(s: &mut &str) -> PResult<Modifier> {
 }
 
 fn standard_modifier(s: &mut &str) -> PResult<Modifier> {
-    alt((project.map(|m| Modifier::Project(m)), other)).parse_next(s)
+    alt((
+        project.map(Modifier::Project),
+        tag_modifier,
+        date_modifier,
+        depends_modifier,
+        recur_modifier,
+        priority_modifier,
+        other,
+    ))
+    .parse_next(s)
 }
 
 fn modifier(s: &mut &str) -> PResult<Modifier> {
     alt((standard_modifier, description)).parse_next(s)
 }
 
-fn modifier_space_or_end<'a>(s: &mut &'a str) -> PResult<Modifier> {
+#[allow(dead_code)]
+fn modifier_space_or_end(s: &mut &str) -> PResult<Modifier> {
     let m = modifier.parse_next(s)?;
     let _ = alt((multispace1, eof)).parse_next(s)?;
     Ok(m)
 }
 
+#[allow(dead_code)]
 fn modifiers(s: &mut &str) -> PResult<Modifiers> {
     repeat(0.., modifier_space_or_end)
         .map(|modifiers| Modifiers { modifiers })
@@ -123,6 +390,7 @@ fn modifiers(s: &mut &str) -> PResult<Modifiers> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::task_args::multi_word;
     use pretty_assertions::assert_eq;
 
     #[test]
@@ -156,6 +424,102 @@ mod tests {
         assert_eq!(" bar test project:test foo:bar", input);
     }
 
+    #[test]
+    fn test_tag_modifier() {
+        let actual = Modifier::from_str("+bug").unwrap();
+        let expected = Modifier::Tag {
+            name: String::from("bug"),
+            removed: false,
+        };
+        assert_eq!(expected, actual);
+        assert_eq!("-bug", Modifier::from_str("-bug").unwrap().to_string());
+    }
+
+    #[test]
+    fn test_date_modifier() {
+        let actual = Modifier::from_str("due:Monday").unwrap();
+        assert_eq!("due:monday", actual.to_string());
+        let actual = Modifier::from_str("wait:+3d").unwrap();
+        assert_eq!("wait:now+3d", actual.to_string());
+        let actual = Modifier::from_str("scheduled:").unwrap();
+        assert_eq!("scheduled:", actual.to_string());
+        let actual = Modifier::from_str("until:tomorow");
+        assert!(matches!(actual, Err(ParseError::Date(value)) if value == "tomorow"));
+    }
+
+    #[test]
+    fn test_depends_modifier() {
+        let actual = Modifier::from_str("depends:3,7,-8d3f2a1c").unwrap();
+        let expected = Modifier::Depends(vec![
+            Dependency {
+                task: TaskRef::Id(3),
+                removed: false,
+            },
+            Dependency {
+                task: TaskRef::Id(7),
+                removed: false,
+            },
+            Dependency {
+                task: TaskRef::Uuid(String::from("8d3f2a1c")),
+                removed: true,
+            },
+        ]);
+        assert_eq!(expected, actual);
+        assert_eq!("depends:3,7,-8d3f2a1c", actual.to_string());
+        assert_eq!(
+            Modifier::Depends(Vec::new()),
+            Modifier::from_str("depends:").unwrap()
+        );
+        let actual = Modifier::from_str("depends:3,x");
+        assert!(matches!(actual, Err(ParseError::Depends(value)) if value == "3,x"));
+    }
+
+    #[test]
+    fn test_recur_modifier() {
+        let actual = Modifier::from_str("recur:Weekly").unwrap();
+        assert_eq!("recur:weekly", actual.to_string());
+        let actual = Modifier::from_str("recur:3d").unwrap();
+        assert_eq!("recur:3d", actual.to_string());
+        assert_eq!(Modifier::Recur(None), Modifier::from_str("recur:").unwrap());
+        let actual = Modifier::from_str("recur:often");
+        assert!(matches!(actual, Err(ParseError::Duration(value)) if value == "often"));
+    }
+
+    #[test]
+    fn test_unknown_attribute() {
+        let udas = vec![String::from("estimate")];
+        let modifier = |s: &str| Modifier::from_str(s).unwrap();
+        assert_eq!(None, modifier("estimate:3").unknown_attribute(&udas));
+        assert_eq!(None, modifier("est:3").unknown_attribute(&udas));
+        assert_eq!(None, modifier("status:pending").unknown_attribute(&udas));
+        assert_eq!(None, modifier("foo").unknown_attribute(&udas));
+        assert_eq!(Some("foo"), modifier("foo:bar").unknown_attribute(&udas));
+        assert_eq!(Some("e"), modifier("e:3").unknown_attribute(&udas));
+    }
+
+    #[test]
+    fn test_priority_modifier() {
+        let actual = Modifier::from_str("priority:H").unwrap();
+        assert_eq!(Modifier::Priority(Some(Priority::High)), actual);
+        let actual = Modifier::from_str("pri:L").unwrap();
+        assert_eq!("priority:L", actual.to_string());
+        let actual = Modifier::from_str("priority:").unwrap();
+        assert_eq!(Modifier::Priority(None), actual);
+        let actual = Modifier::from_str("priority:high");
+        assert!(matches!(
+            actual,
+            Err(ParseError::Priority { suggestion: Some(ref s), .. }) if s == "H"
+        ));
+        let actual = Modifier::from_str("priority:urgent");
+        assert!(matches!(
+            actual,
+            Err(ParseError::Priority {
+                suggestion: None,
+                ..
+            })
+        ));
+    }
+
     #[test]
     fn test_modifiers_1() {
         let mods = Modifiers {
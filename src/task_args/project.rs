@@ -1,29 +1,7 @@
-use super::filter::Filter;
-use super::filter::Filters;
 use super::word;
-use super::ParseError;
-use color_eyre::eyre::bail;
-use color_eyre::Result;
-use core::fmt::Error;
-use log::debug;
-use log::info;
-use log::trace;
-use serde::Serialize;
-use std::env;
 use std::fmt;
-use std::fs::File;
-use std::io::{Read, Write};
-use std::path::Path;
-use std::process::Command;
 use std::str;
-use std::str::FromStr;
-use winnow::ascii::alphanumeric1;
-use winnow::combinator::alt;
-use winnow::combinator::repeat;
-use winnow::stream::Accumulate;
-use winnow::stream::AsChar;
-use winnow::token::one_of;
-use winnow::token::take_while;
+use winnow::combinator::{alt, opt};
 use winnow::PResult;
 
 use winnow::Parser;
@@ -54,10 +32,15 @@ fn project_name(s: &mut &str) -> PResult<String> {
     word.map(|s: &str| s.to_string()).parse_next(s)
 }
 
+/// Parse `project:<name>`. An empty name (`project:`) explicitly selects no project
 pub fn project(s: &mut &str) -> PResult<Project> {
     let _ = alt(("project", "proj")).parse_next(s)?;
     let _ = ":".parse_next(s)?;
-    project_name.map(|name| Project { name }).parse_next(s)
+    opt(project_name)
+        .map(|name| Project {
+            name: name.unwrap_or_default(),
+        })
+        .parse_next(s)
 }
 
 #[cfg(test)]
@@ -73,4 +56,14 @@ mod tests {
         assert_eq!(expected, actual);
         assert_eq!("", input);
     }
+
+    #[test]
+    fn test_project_empty() {
+        let mut input = "project: foo";
+        let expected = Project::with_name("");
+        let actual = project.parse_next(&mut input).unwrap();
+        assert_eq!(expected, actual);
+        assert_eq!(" foo", input);
+        assert_eq!("project:", actual.to_string());
+    }
 }
@@ -0,0 +1,26 @@
+use color_eyre::eyre::bail;
+use color_eyre::Result;
+use log::debug;
+use std::path::Path;
+use std::process::Command;
+
+const TASKOPEN_BIN: &str = "taskopen";
+
+/// Run taskopen with `args`, returning its exit code. `taskrc` and `taskdata` point it at the
+/// same data taskwarrior would use
+pub fn open(args: &[String], taskrc: Option<&Path>, taskdata: Option<&Path>) -> Result<i32> {
+    let Ok(bin) = which::which(TASKOPEN_BIN) else {
+        bail!("Unable to find '{}' on the $PATH", TASKOPEN_BIN);
+    };
+    let mut cmd = Command::new(bin);
+    cmd.args(args);
+    if let Some(taskrc) = taskrc {
+        cmd.env("TASKRC", taskrc);
+    }
+    if let Some(taskdata) = taskdata {
+        cmd.env("TASKDATA", taskdata);
+    }
+    debug!("Running command {:?}", cmd);
+    let status = cmd.status()?;
+    Ok(status.code().unwrap_or(1))
+}
@@ -0,0 +1,369 @@
+use color_eyre::eyre::bail;
+use color_eyre::Result;
+use std::ffi::{OsStr, OsString};
+use std::io::{self, IsTerminal};
+#[cfg(unix)]
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+
+const SSH_BIN: &str = "ssh";
+
+/// Where taskwarrior runs
+#[derive(Debug, Clone)]
+enum Backend {
+    /// A taskwarrior binary on this machine
+    Local(PathBuf),
+    /// Taskwarrior on another machine, run over ssh
+    Ssh { host: String, task: String },
+    /// Taskwarrior in a running container, with the data directory bind-mounted
+    Container {
+        runtime: String,
+        name: String,
+        /// Bind mounts, from the path on this machine to the path in the container
+        mounts: Vec<(PathBuf, PathBuf)>,
+        task: String,
+    },
+}
+
+/// Environment variables taskwarrior reads paths from
+const PATH_VARS: [&str; 2] = ["TASKDATA", "TASKRC"];
+
+/// Override taskwarrior reads the data directory from
+const DATA_LOCATION: &str = "rc.data.location=";
+
+/// How to invoke taskwarrior
+#[derive(Debug, Clone)]
+pub struct Taskwarrior {
+    backend: Backend,
+    env: Vec<(OsString, OsString)>,
+    /// Niceness to run with, also lowering IO priority
+    nice: Option<i32>,
+    /// UDAs taskwarrior is configured with, looked up the first time they're needed
+    udas: OnceLock<Vec<String>>,
+}
+
+/// Quote `s` for a POSIX shell
+pub fn shell_quote(s: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "-_./:=+@,%".contains(c);
+    if !s.is_empty() && s.chars().all(safe) {
+        s.to_string()
+    } else {
+        format!("'{}'", s.replace('\'', r"'\''"))
+    }
+}
+
+/// Set the niceness of the calling process and drop it to the lowest best-effort IO priority.
+/// Failures are ignored, running at normal priority is better than not running
+#[cfg(unix)]
+fn lower_priority(nice: i32) {
+    unsafe {
+        libc::setpriority(libc::PRIO_PROCESS, 0, nice);
+    }
+    #[cfg(target_os = "linux")]
+    {
+        const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+        const IOPRIO_CLASS_BE: libc::c_int = 2;
+        const IOPRIO_CLASS_SHIFT: libc::c_int = 13;
+        const IOPRIO_LOWEST: libc::c_int = 7;
+        unsafe {
+            libc::syscall(
+                libc::SYS_ioprio_set,
+                IOPRIO_WHO_PROCESS,
+                0,
+                (IOPRIO_CLASS_BE << IOPRIO_CLASS_SHIFT) | IOPRIO_LOWEST,
+            );
+        }
+    }
+}
+
+impl Taskwarrior {
+    pub fn new(bin: PathBuf) -> Self {
+        Taskwarrior {
+            backend: Backend::Local(bin),
+            env: Vec::new(),
+            nice: None,
+            udas: OnceLock::new(),
+        }
+    }
+
+    /// Run `task` on `host` over ssh
+    pub fn ssh(host: &str, task: &str) -> Self {
+        Taskwarrior {
+            backend: Backend::Ssh {
+                host: host.to_string(),
+                task: task.to_string(),
+            },
+            env: Vec::new(),
+            nice: None,
+            udas: OnceLock::new(),
+        }
+    }
+
+    /// Run `task` in the running container `name` with `runtime` (podman, docker, ...). Paths
+    /// on this machine are passed to it through the container's bind `mounts`
+    pub fn container(
+        runtime: &str,
+        name: &str,
+        mounts: Vec<(PathBuf, PathBuf)>,
+        task: &str,
+    ) -> Self {
+        Taskwarrior {
+            backend: Backend::Container {
+                runtime: runtime.to_string(),
+                name: name.to_string(),
+                mounts,
+                task: task.to_string(),
+            },
+            env: Vec::new(),
+            nice: None,
+            udas: OnceLock::new(),
+        }
+    }
+
+    /// Where taskwarrior runs, for diagnostics
+    pub fn describe(&self) -> String {
+        match &self.backend {
+            Backend::Local(bin) => format!("local, {}", bin.display()),
+            Backend::Ssh { host, task } => format!("ssh, '{}' on {}", task, host),
+            Backend::Container { runtime, name, .. } => format!("{} container {}", runtime, name),
+        }
+    }
+
+    /// Whether taskwarrior runs on this machine, against local data
+    pub fn is_local(&self) -> bool {
+        matches!(self.backend, Backend::Local(_))
+    }
+
+    /// Set an environment variable for every taskwarrior process, replacing an earlier value
+    pub fn env<K: AsRef<OsStr>, V: AsRef<OsStr>>(mut self, key: K, value: V) -> Self {
+        self.env.retain(|(k, _)| k != key.as_ref());
+        self.env
+            .push((key.as_ref().to_os_string(), value.as_ref().to_os_string()));
+        self
+    }
+
+    /// Fail unless taskwarrior can use `path` on this machine. Remote hosts see none of them,
+    /// containers only the ones mounted in them
+    pub fn reach(&self, path: &Path) -> Result<()> {
+        match &self.backend {
+            Backend::Ssh { host, .. } => bail!(
+                "'{}' is on this machine, taskwarrior on '{}' can't use it",
+                path.display(),
+                host
+            ),
+            Backend::Container { name, mounts, .. } if container_path(mounts, path).is_none() => {
+                bail!(
+                    "'{}' isn't mounted in container '{}', add it to the container's mounts",
+                    path.display(),
+                    name
+                )
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Fail unless taskwarrior can use the paths its environment points it at
+    pub fn check_env(&self) -> Result<()> {
+        for (k, v) in &self.env {
+            if PATH_VARS.iter().any(|var| k == var) {
+                self.reach(Path::new(v))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Run taskwarrior with a lower CPU and IO priority
+    pub fn low_priority(mut self, nice: i32) -> Self {
+        self.nice = Some(nice);
+        self
+    }
+
+    /// Names of the user defined attributes taskwarrior is configured with
+    pub fn udas(&self) -> io::Result<&[String]> {
+        if let Some(udas) = self.udas.get() {
+            return Ok(udas);
+        }
+        let output = self.command(&["_udas"]).output()?;
+        let udas = String::from_utf8_lossy(&output.stdout)
+            .split_whitespace()
+            .map(String::from)
+            .collect();
+        Ok(self.udas.get_or_init(|| udas))
+    }
+
+    /// A command running taskwarrior with `args`
+    pub fn command<S: AsRef<str>>(&self, args: &[S]) -> Command {
+        match &self.backend {
+            Backend::Local(bin) => {
+                let mut cmd = Command::new(bin);
+                cmd.envs(self.env.iter().map(|(k, v)| (k, v)));
+                cmd.args(args.iter().map(|arg| arg.as_ref()));
+                if let Some(nice) = self.nice {
+                    // Safety: only async-signal-safe syscalls are made between fork and exec
+                    #[cfg(unix)]
+                    unsafe {
+                        cmd.pre_exec(move || {
+                            lower_priority(nice);
+                            Ok(())
+                        });
+                    }
+                    #[cfg(not(unix))]
+                    log::warn!("Ignoring niceness {}, not supported on this platform", nice);
+                }
+                cmd
+            }
+            Backend::Ssh { host, task } => {
+                let mut cmd = Command::new(SSH_BIN);
+                // A remote terminal merges stderr into stdout, only ask for one to interact with
+                let terminal = io::stdin().is_terminal() && io::stdout().is_terminal();
+                cmd.arg(if terminal { "-t" } else { "-T" });
+                cmd.arg(host).arg("--");
+                cmd.arg(self.remote_command(task, args));
+                cmd
+            }
+            Backend::Container {
+                runtime,
+                name,
+                mounts,
+                task,
+            } => {
+                let mut cmd = Command::new(runtime);
+                // Stdin is passed on for prompts and imports, a terminal only when there is one
+                cmd.args(["exec", "-i"]);
+                if io::stdin().is_terminal() {
+                    cmd.arg("-t");
+                }
+                for (k, v) in &self.env {
+                    let mut var = k.clone();
+                    var.push("=");
+                    match container_path(mounts, Path::new(v)) {
+                        Some(path) if PATH_VARS.iter().any(|var| k == var) => var.push(path),
+                        _ => var.push(v),
+                    }
+                    cmd.arg("-e").arg(var);
+                }
+                cmd.arg(name).arg(task);
+                cmd.args(args.iter().map(|arg| {
+                    let arg = arg.as_ref();
+                    arg.strip_prefix(DATA_LOCATION)
+                        .and_then(|location| container_path(mounts, Path::new(location)))
+                        .map_or_else(
+                            || OsString::from(arg),
+                            |path| OsString::from(format!("{}{}", DATA_LOCATION, path.display())),
+                        )
+                }));
+                cmd
+            }
+        }
+    }
+
+    /// The command line the remote shell runs, environment included
+    fn remote_command<S: AsRef<str>>(&self, task: &str, args: &[S]) -> String {
+        let mut words = Vec::new();
+        if !self.env.is_empty() {
+            words.push(String::from("env"));
+            for (k, v) in &self.env {
+                let var = format!("{}={}", k.to_string_lossy(), v.to_string_lossy());
+                words.push(shell_quote(&var));
+            }
+        }
+        words.push(shell_quote(task));
+        words.extend(args.iter().map(|arg| shell_quote(arg.as_ref())));
+        words.join(" ")
+    }
+}
+
+/// Where `path` on this machine is in a container with bind `mounts`, `None` if it isn't mounted
+fn container_path(mounts: &[(PathBuf, PathBuf)], path: &Path) -> Option<PathBuf> {
+    mounts
+        .iter()
+        .filter_map(|(host, container)| Some((host, container, path.strip_prefix(host).ok()?)))
+        .max_by_key(|(host, _, _)| host.components().count())
+        .map(|(_, container, rest)| {
+            if rest.as_os_str().is_empty() {
+                container.clone()
+            } else {
+                container.join(rest)
+            }
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!("project:foo", shell_quote("project:foo"));
+        assert_eq!("'two words'", shell_quote("two words"));
+        assert_eq!(r"'it'\''s'", shell_quote("it's"));
+        assert_eq!("''", shell_quote(""));
+    }
+
+    #[test]
+    fn test_container_command() {
+        let mounts = vec![
+            (PathBuf::from("/home/me/.task"), PathBuf::from("/taskdata")),
+            (PathBuf::from("/home/me/.taskrc"), PathBuf::from("/taskrc")),
+            (
+                PathBuf::from("/home/me/.local/share/taskhelper/projects"),
+                PathBuf::from("/projects"),
+            ),
+        ];
+        let task = Taskwarrior::container("podman", "tw", mounts, "task")
+            .env("TASKDATA", "/home/me/.task")
+            .env("TASKRC", "/home/me/.taskrc");
+        let cmd = task.command(&[
+            "rc.data.location=/home/me/.local/share/taskhelper/projects/repo",
+            "list",
+        ]);
+        assert_eq!("podman", cmd.get_program());
+        let args: Vec<&OsStr> = cmd.get_args().filter(|arg| *arg != "-t").collect();
+        assert_eq!(
+            vec![
+                "exec",
+                "-i",
+                "-e",
+                "TASKDATA=/taskdata",
+                "-e",
+                "TASKRC=/taskrc",
+                "tw",
+                "task",
+                "rc.data.location=/projects/repo",
+                "list"
+            ],
+            args
+        );
+
+        assert!(task.reach(Path::new("/home/me/.task/sub")).is_ok());
+        assert!(task.reach(Path::new("/home/me/.taskrc")).is_ok());
+        assert!(task.reach(Path::new("/home/me/.task2")).is_err());
+        assert!(task.check_env().is_ok());
+        assert!(task.env("TASKDATA", "/elsewhere").check_env().is_err());
+    }
+
+    #[test]
+    fn test_ssh_command() {
+        let task = Taskwarrior::ssh("server", "task");
+        let cmd = task.command(&["list"]);
+        assert_eq!(SSH_BIN, cmd.get_program());
+        let args: Vec<&OsStr> = cmd.get_args().skip(1).collect();
+        assert_eq!(vec!["server", "--", "task list"], args);
+
+        assert!(task.reach(Path::new("/home/me/.task")).is_err());
+        assert!(task.check_env().is_ok());
+        assert!(task.env("TASKDATA", "/home/me/.task").check_env().is_err());
+    }
+
+    #[test]
+    fn test_remote_command() {
+        let task = Taskwarrior::ssh("server", "task").env("TASKRC", "/home/me/.taskrc");
+        assert_eq!(
+            "env TASKRC=/home/me/.taskrc task add 'buy milk'",
+            task.remote_command("task", &["add", "buy milk"])
+        );
+    }
+}
@@ -0,0 +1,97 @@
+use crate::config::TimewConfig;
+use crate::task::{export_tasks, Task};
+use crate::taskwarrior::Taskwarrior;
+use color_eyre::eyre::{bail, eyre};
+use color_eyre::Result;
+use log::debug;
+use std::path::PathBuf;
+use std::process::Command;
+
+const TIMEW_BIN: &str = "timew";
+
+/// How to invoke timewarrior
+#[derive(Debug, Clone)]
+pub struct Timew {
+    bin: PathBuf,
+}
+
+/// Timewarrior tags for `task`: its description, project and tags, like taskwarrior's
+/// on-modify.timewarrior hook tags them
+fn timew_tags(task: &Task) -> Vec<String> {
+    let mut tags = vec![task.description.clone()];
+    tags.extend(task.project.clone());
+    tags.extend(task.tags.iter().cloned());
+    tags
+}
+
+impl Timew {
+    /// Timewarrior set in `config`, or the one on the $PATH
+    pub fn find(config: &TimewConfig) -> Result<Self> {
+        if let Some(bin) = &config.bin {
+            return Ok(Timew { bin: bin.clone() });
+        }
+        let Ok(bin) = which::which(TIMEW_BIN) else {
+            bail!("Unable to find '{}' on the $PATH", TIMEW_BIN);
+        };
+        Ok(Timew { bin })
+    }
+
+    fn run(&self, args: &[String]) -> Result<()> {
+        let mut cmd = Command::new(&self.bin);
+        cmd.args(args);
+        debug!("Running command {:?}", cmd);
+        if !cmd.status()?.success() {
+            bail!("'{} {}' failed", TIMEW_BIN, args.join(" "));
+        }
+        Ok(())
+    }
+
+    /// Start tracking time with `tags`, stopping whatever was tracked before
+    fn start(&self, tags: &[String]) -> Result<()> {
+        let mut args = vec![String::from("start")];
+        args.extend_from_slice(tags);
+        self.run(&args)
+    }
+
+    /// Stop tracking time
+    pub fn stop(&self) -> Result<()> {
+        self.run(&[String::from("stop")])
+    }
+
+    /// Start tracking time on the one task matching `filter`. `rc` is prepended to the
+    /// taskwarrior command looking it up
+    pub fn track(&self, task: &Taskwarrior, filter: &[String], rc: &[String]) -> Result<()> {
+        let mut args = rc.to_vec();
+        args.extend_from_slice(filter);
+        let tasks = export_tasks(task, &args)
+            .map_err(|e| eyre!("Unable to find the task to track: {}", e))?;
+        match tasks.as_slice() {
+            [tracked] => self.start(&timew_tags(tracked)),
+            [] => bail!("No task matches '{}'", filter.join(" ")),
+            _ => bail!(
+                "{} tasks match '{}', timewarrior tracks one at a time",
+                tasks.len(),
+                filter.join(" ")
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use s_string::s;
+
+    #[test]
+    fn test_timew_tags() {
+        let tracked: Task = serde_json::from_str(
+            r#"{"id":3,"uuid":"a1b2","description":"Write docs","project":"repo","tags":["docs"],"status":"pending"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            vec![s!("Write docs"), s!("repo"), s!("docs")],
+            timew_tags(&tracked)
+        );
+    }
+}
@@ -0,0 +1,266 @@
+use crate::download::hex;
+use crate::table::date;
+use crate::task::{export_tasks, format_date, parse_day, Task};
+use crate::taskwarrior::Taskwarrior;
+use color_eyre::Result;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::time::SystemTime;
+
+/// Taskwarrior priorities by todo.txt priority. Letters after C are taken as low
+const PRIORITIES: &[(char, &str)] = &[('A', "H"), ('B', "M"), ('C', "L")];
+
+/// Pending and completed tasks matching `args`, which may also hold rc overrides
+pub fn query(task: &Taskwarrior, args: &[String]) -> Result<Vec<Task>> {
+    let mut export_args = args.to_vec();
+    export_args.extend(["(", "status:pending", "or", "status:completed", ")"].map(String::from));
+    export_tasks(task, &export_args)
+}
+
+fn todotxt_priority(priority: &str) -> Option<char> {
+    PRIORITIES
+        .iter()
+        .find(|(_, p)| *p == priority)
+        .map(|(letter, _)| *letter)
+}
+
+fn taskwarrior_priority(letter: char) -> Option<&'static str> {
+    match letter {
+        'A'..='C' => PRIORITIES
+            .iter()
+            .find(|(l, _)| *l == letter)
+            .map(|(_, p)| *p),
+        'D'..='Z' => Some("L"),
+        _ => None,
+    }
+}
+
+/// `task` as a todo.txt line. The UUID is kept in a `uuid:` tag, so importing the line again
+/// updates the task rather than adding another
+pub fn line(task: &Task) -> String {
+    let completed = task.status == "completed";
+    let priority = task.priority.as_deref().and_then(todotxt_priority);
+    let mut words = Vec::new();
+    if completed {
+        words.push(String::from("x"));
+        if task.end.is_some() {
+            words.push(date(task.end));
+        }
+    } else if let Some(priority) = priority {
+        words.push(format!("({})", priority));
+    }
+    if task.entry.is_some() && (!completed || task.end.is_some()) {
+        words.push(date(task.entry));
+    }
+    words.push(task.description.clone());
+    words.extend(task.project.iter().map(|project| format!("+{}", project)));
+    words.extend(task.tags.iter().map(|tag| format!("@{}", tag)));
+    if let (true, Some(priority)) = (completed, priority) {
+        words.push(format!("pri:{}", priority));
+    }
+    if task.due.is_some() {
+        words.push(format!("due:{}", date(task.due)));
+    }
+    if !task.uuid.is_empty() {
+        words.push(format!("uuid:{}", task.uuid));
+    }
+    words.join(" ")
+}
+
+/// `tasks` as the lines of a todo.txt file
+pub fn render(tasks: &[Task]) -> String {
+    tasks
+        .iter()
+        .map(|task| format!("{}\n", line(task)))
+        .collect()
+}
+
+/// UUID for a line without one, made from what identifies the task so importing the line again,
+/// even once it's completed, updates the task
+fn derived_uuid(description: &str, entry: Option<&str>, project: Option<&str>) -> String {
+    let mut hasher = Sha256::new();
+    for part in [
+        description,
+        entry.unwrap_or_default(),
+        project.unwrap_or_default(),
+    ] {
+        hasher.update(part.as_bytes());
+        hasher.update([0]);
+    }
+    let hash = hex(&hasher.finalize());
+    // Marked as a name based UUID in the RFC 4122 variant
+    let variant = 8 + u8::from_str_radix(&hash[16..17], 16).unwrap_or(0) % 4;
+    format!(
+        "{}-{}-5{}-{:x}{}-{}",
+        &hash[..8],
+        &hash[8..12],
+        &hash[13..16],
+        variant,
+        &hash[17..20],
+        &hash[20..32]
+    )
+}
+
+/// A task to import for the todo.txt `line`, None for blank lines. Tasks without a `+project`
+/// are put in `default_project`, completed ones without a completion date are completed at `now`
+fn parse_line(line: &str, default_project: Option<&str>, now: SystemTime) -> Option<Value> {
+    let mut words = line.split_whitespace().peekable();
+    let day = |word: Option<&&str>| word.and_then(|word| parse_day(word)).map(format_date);
+    let mut task = json!({"status": "pending"});
+
+    if words.next_if_eq(&"x").is_some() {
+        task["status"] = json!("completed");
+        match day(words.peek()) {
+            Some(end) => {
+                task["end"] = json!(end);
+                words.next();
+            }
+            None => task["end"] = json!(format_date(now)),
+        }
+    } else if let Some(word) = words.peek() {
+        let letter = match word.as_bytes() {
+            [b'(', letter, b')'] => taskwarrior_priority(*letter as char),
+            _ => None,
+        };
+        if let Some(priority) = letter {
+            task["priority"] = json!(priority);
+            words.next();
+        }
+    }
+    if let Some(entry) = day(words.peek()) {
+        task["entry"] = json!(entry);
+        words.next();
+    }
+
+    let mut description = Vec::new();
+    let mut projects = Vec::new();
+    let mut tags = Vec::new();
+    let mut uuid = None;
+    for word in words {
+        match word.split_once(':') {
+            Some(("due", value)) if parse_day(value).is_some() => {
+                task["due"] = json!(day(Some(&value)));
+                continue;
+            }
+            Some(("pri", value)) if value.len() == 1 => {
+                if let Some(priority) = value.chars().next().and_then(taskwarrior_priority) {
+                    task["priority"] = json!(priority);
+                }
+                continue;
+            }
+            Some(("uuid", value)) if !value.is_empty() => {
+                uuid = Some(value.to_string());
+                continue;
+            }
+            _ => {}
+        }
+        if let Some(project) = word.strip_prefix('+').filter(|p| !p.is_empty()) {
+            projects.push(project);
+        } else if let Some(context) = word.strip_prefix('@').filter(|c| !c.is_empty()) {
+            tags.push(context);
+        } else {
+            description.push(word);
+        }
+    }
+    if description.is_empty() {
+        return None;
+    }
+
+    let description = description.join(" ");
+    // Taskwarrior has one project, the others are kept as tags
+    let project = projects.first().copied().or(default_project);
+    tags.extend(projects.iter().skip(1));
+    task["description"] = json!(description);
+    if let Some(project) = project {
+        task["project"] = json!(project);
+    }
+    if !tags.is_empty() {
+        task["tags"] = json!(tags);
+    }
+    let uuid = uuid.unwrap_or_else(|| {
+        let entry = task["entry"].as_str();
+        derived_uuid(&description, entry, project)
+    });
+    task["uuid"] = json!(uuid);
+    Some(task)
+}
+
+/// Tasks to import for the lines of a todo.txt file
+pub fn parse(todotxt: &str, default_project: Option<&str>, now: SystemTime) -> Vec<Value> {
+    todotxt
+        .lines()
+        .filter_map(|line| parse_line(line, default_project, now))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::time::UNIX_EPOCH;
+
+    #[test]
+    fn test_line() {
+        let tasks: Vec<Task> = serde_json::from_str(
+            r#"[
+                {"uuid": "a1", "description": "Write docs", "status": "pending", "priority": "H",
+                 "project": "repo", "tags": ["home"], "entry": "20240101T120000Z",
+                 "due": "20240501T000000Z"},
+                {"uuid": "b2", "description": "Fix the build", "status": "completed",
+                 "priority": "M", "entry": "20240101T120000Z", "end": "20240102T120000Z"}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            "(A) 2024-01-01 Write docs +repo @home due:2024-05-01 uuid:a1\n\
+             x 2024-01-02 2024-01-01 Fix the build pri:B uuid:b2\n",
+            render(&tasks)
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        let todotxt = "\
+(A) 2024-01-01 Call mom +family +phone @home due:2024-05-01 see http://example.com
+
+x 2024-01-02 2024-01-01 Pay rent pri:B uuid:b2
+Plan trip
+";
+        let tasks = parse(todotxt, Some("repo"), UNIX_EPOCH);
+        assert_eq!(3, tasks.len());
+        assert_eq!(
+            json!({
+                "status": "pending",
+                "priority": "H",
+                "entry": "20240101T000000Z",
+                "due": "20240501T000000Z",
+                "description": "Call mom see http://example.com",
+                "project": "family",
+                "tags": ["home", "phone"],
+                "uuid": derived_uuid(
+                    "Call mom see http://example.com",
+                    Some("20240101T000000Z"),
+                    Some("family")
+                ),
+            }),
+            tasks[0]
+        );
+        assert_eq!(
+            json!({
+                "status": "completed",
+                "end": "20240102T000000Z",
+                "entry": "20240101T000000Z",
+                "priority": "M",
+                "description": "Pay rent",
+                "project": "repo",
+                "uuid": "b2",
+            }),
+            tasks[1]
+        );
+        assert_eq!(json!("repo"), tasks[2]["project"]);
+        assert_eq!(
+            tasks[2]["uuid"],
+            parse("x Plan trip", Some("repo"), UNIX_EPOCH)[0]["uuid"]
+        );
+    }
+}
@@ -0,0 +1,173 @@
+use crate::config::{project_dirs, ToolchainConfig};
+use crate::download::{download, extract, target, verify_checksum};
+use crate::taskwarrior::Taskwarrior;
+use crate::{task_version, TASK_BIN};
+use color_eyre::eyre::bail;
+use color_eyre::Result;
+use log::info;
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const TOOLCHAINS_DIR: &str = "toolchains";
+/// File in the toolchains directory naming the version to use
+const DEFAULT_FILE: &str = "default";
+const CHECKSUM_EXTENSION: &str = ".sha256";
+
+/// Directory installed taskwarrior versions are kept in, one subdirectory per version
+fn toolchains_dir() -> Option<PathBuf> {
+    project_dirs().map(|dirs| dirs.data_dir().join(TOOLCHAINS_DIR))
+}
+
+fn task_file_name() -> String {
+    format!("{}{}", TASK_BIN, env::consts::EXE_SUFFIX)
+}
+
+fn binary_in(dir: &Path, version: &str) -> PathBuf {
+    dir.join(version).join(task_file_name())
+}
+
+/// `template` with its `{version}` and `{target}` placeholders filled in
+fn expand_url(template: &str, version: &str, target: &str) -> String {
+    template
+        .replace("{version}", version)
+        .replace("{target}", target)
+}
+
+/// Name of the archive at `url`, used to pick how it's decompressed
+fn archive_name(url: &str) -> &str {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+/// Version named in the toolchains directory's default file
+fn default_version(dir: &Path) -> Option<String> {
+    let version = fs::read_to_string(dir.join(DEFAULT_FILE)).ok()?;
+    let version = version.trim();
+    (!version.is_empty()).then(|| version.to_string())
+}
+
+/// Taskwarrior installed by `toolchain install`, if there is one. It's preferred over the one on
+/// the $PATH
+pub fn preferred() -> Option<PathBuf> {
+    let dir = toolchains_dir()?;
+    let version = default_version(&dir)?;
+    let bin = binary_in(&dir, &version);
+    bin.is_file().then_some(bin)
+}
+
+/// Write `binary` to `path` as an executable, replacing whatever is there
+fn write_executable(path: &Path, binary: &[u8]) -> Result<()> {
+    let Some(dir) = path.parent() else {
+        bail!("Unable to find the directory of '{}'", path.display());
+    };
+    fs::create_dir_all(dir)?;
+    let mut tmp = tempfile::NamedTempFile::new_in(dir)?;
+    io::Write::write_all(&mut tmp, binary)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(tmp.path(), fs::Permissions::from_mode(0o755))?;
+    }
+    tmp.persist(path)?;
+    Ok(())
+}
+
+/// Download taskwarrior `version` from the URL in `config` and make it the one taskhelper runs
+pub fn install(config: &ToolchainConfig, version: &str) -> Result<PathBuf> {
+    let Some(template) = &config.url else {
+        bail!(
+            "Taskwarrior does not publish prebuilt binaries. Set 'url' under [toolchain] in the config file to where they can be downloaded from"
+        );
+    };
+    let Some(target) = target() else {
+        bail!("No prebuilt taskwarrior is known for this platform");
+    };
+    let Some(dir) = toolchains_dir() else {
+        bail!("Unable to find a data directory");
+    };
+
+    let url = expand_url(template, version, &target);
+    let archive = download(&url)?;
+    let checksum = download(&format!("{}{}", url, CHECKSUM_EXTENSION))?;
+    verify_checksum(&archive, &String::from_utf8_lossy(&checksum))?;
+    let binary = extract(archive_name(&url), &archive, &[&task_file_name()])?;
+
+    let path = binary_in(&dir, version);
+    write_executable(&path, &binary)?;
+    let installed = task_version(&Taskwarrior::new(path.clone()))?;
+    if installed != version {
+        fs::remove_dir_all(dir.join(version))?;
+        bail!(
+            "Downloaded taskwarrior reports version '{}', not '{}'",
+            installed,
+            version
+        );
+    }
+    fs::write(dir.join(DEFAULT_FILE), format!("{}\n", version))?;
+    info!("Installed taskwarrior {} to '{}'", version, path.display());
+    Ok(path)
+}
+
+/// Installed versions, marking the one in use with `*`
+pub fn list() -> Result<String> {
+    let mut out = String::new();
+    let Some(dir) = toolchains_dir().filter(|dir| dir.is_dir()) else {
+        return Ok(out);
+    };
+    let default = default_version(&dir);
+    let mut versions = Vec::new();
+    for entry in fs::read_dir(&dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            versions.push(entry.file_name().to_string_lossy().to_string());
+        }
+    }
+    versions.sort();
+    for version in versions {
+        let marker = if default.as_ref() == Some(&version) {
+            '*'
+        } else {
+            ' '
+        };
+        let _ = writeln!(out, "{} {}", marker, version);
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_expand_url() {
+        assert_eq!(
+            "https://example.com/3.1.0/task-x86_64-unknown-linux-gnu.tar.gz",
+            expand_url(
+                "https://example.com/{version}/task-{target}.tar.gz",
+                "3.1.0",
+                "x86_64-unknown-linux-gnu"
+            )
+        );
+    }
+
+    #[test]
+    fn test_archive_name() {
+        assert_eq!(
+            "task.tar.xz",
+            archive_name("https://example.com/3.1.0/task.tar.xz?download=1")
+        );
+        assert_eq!("task.tar.gz", archive_name("task.tar.gz"));
+    }
+
+    #[test]
+    fn test_default_version() {
+        let dir = tempfile::TempDir::new().unwrap();
+        assert_eq!(None, default_version(dir.path()));
+        fs::write(dir.path().join(DEFAULT_FILE), "3.1.0\n").unwrap();
+        assert_eq!(Some(String::from("3.1.0")), default_version(dir.path()));
+    }
+}
@@ -0,0 +1,269 @@
+use crate::run_interactive;
+use crate::table::date;
+use crate::task::{export_tasks, Task};
+use crate::taskwarrior::Taskwarrior;
+use color_eyre::Result;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Row, Table, TableState};
+use ratatui::{DefaultTerminal, Frame};
+
+const HELP: &str = "j/k move  d done  s start/stop  a annotate  e edit  r refresh  q quit";
+
+/// What a key press asks for
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Action {
+    None,
+    Quit,
+    Refresh,
+    /// Run taskwarrior with these args, after the rc overrides
+    Task(Vec<String>),
+    /// Edit the task with this uuid in taskwarrior's editor
+    Edit(String),
+}
+
+/// State of the browser: the tasks shown, which is selected and the annotation being typed
+#[derive(Debug, Default)]
+struct App {
+    tasks: Vec<Task>,
+    table: TableState,
+    annotation: Option<String>,
+    message: Option<String>,
+}
+
+impl App {
+    fn set_tasks(&mut self, tasks: Vec<Task>) {
+        self.tasks = tasks;
+        let selected = match self.table.selected() {
+            _ if self.tasks.is_empty() => None,
+            Some(i) => Some(i.min(self.tasks.len() - 1)),
+            None => Some(0),
+        };
+        self.table.select(selected);
+    }
+
+    fn selected(&self) -> Option<&Task> {
+        self.table.selected().and_then(|i| self.tasks.get(i))
+    }
+
+    fn on_key(&mut self, key: KeyEvent) -> Action {
+        if let Some(annotation) = &mut self.annotation {
+            match key.code {
+                KeyCode::Enter => {
+                    let text = std::mem::take(annotation);
+                    self.annotation = None;
+                    return match self.selected() {
+                        Some(task) if !text.trim().is_empty() => {
+                            Action::Task(vec![task.uuid.clone(), String::from("annotate"), text])
+                        }
+                        _ => Action::None,
+                    };
+                }
+                KeyCode::Esc => self.annotation = None,
+                KeyCode::Backspace => {
+                    annotation.pop();
+                }
+                KeyCode::Char(c) => annotation.push(c),
+                _ => {}
+            }
+            return Action::None;
+        }
+
+        self.message = None;
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Action::Quit,
+            KeyCode::Char('r') => return Action::Refresh,
+            KeyCode::Char('j') | KeyCode::Down => self.table.select_next(),
+            KeyCode::Char('k') | KeyCode::Up => self.table.select_previous(),
+            _ => {}
+        }
+        let Some(task) = self.selected() else {
+            return Action::None;
+        };
+        let uuid = task.uuid.clone();
+        match key.code {
+            KeyCode::Char('d') => Action::Task(vec![uuid, String::from("done")]),
+            KeyCode::Char('s') if task.start.is_some() => {
+                Action::Task(vec![uuid, String::from("stop")])
+            }
+            KeyCode::Char('s') => Action::Task(vec![uuid, String::from("start")]),
+            KeyCode::Char('e') => Action::Edit(uuid),
+            KeyCode::Char('a') => {
+                self.annotation = Some(String::new());
+                Action::None
+            }
+            _ => Action::None,
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [table_area, status_area] =
+            Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+        let rows = self.tasks.iter().map(|task| {
+            let row = Row::new([
+                task.id.to_string(),
+                date(task.due),
+                task.description.clone(),
+                format!("{:.1}", task.urgency),
+            ]);
+            match task.start {
+                Some(_) => row.style(Style::new().add_modifier(Modifier::BOLD)),
+                None => row,
+            }
+        });
+        let widths = [
+            Constraint::Length(4),
+            Constraint::Length(10),
+            Constraint::Fill(1),
+            Constraint::Length(7),
+        ];
+        let table = Table::new(rows, widths)
+            .header(
+                Row::new(["ID", "DUE", "DESCRIPTION", "URGENCY"])
+                    .style(Style::new().add_modifier(Modifier::UNDERLINED)),
+            )
+            .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(table, table_area, &mut self.table);
+
+        let status = match (&self.annotation, &self.message) {
+            (Some(annotation), _) => format!("Annotation: {}", annotation),
+            (None, Some(message)) => message.clone(),
+            (None, None) => String::from(HELP),
+        };
+        frame.render_widget(Line::raw(status), status_area);
+    }
+}
+
+fn load(task: &Taskwarrior, rc: &[String], filter: &[String]) -> Result<Vec<Task>> {
+    let mut args = rc.to_vec();
+    args.extend_from_slice(filter);
+    args.push(String::from("status:pending"));
+    export_tasks(task, &args)
+}
+
+fn browse(
+    terminal: &mut DefaultTerminal,
+    task: &Taskwarrior,
+    rc: &[String],
+    filter: &[String],
+) -> Result<()> {
+    let mut app = App::default();
+    app.set_tasks(load(task, rc, filter)?);
+    loop {
+        terminal.draw(|frame| app.draw(frame))?;
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match app.on_key(key) {
+            Action::None => continue,
+            Action::Quit => return Ok(()),
+            Action::Refresh => {}
+            Action::Task(action) => {
+                let mut args = rc.to_vec();
+                args.extend(action);
+                let output = task.command(&args).output()?;
+                if !output.status.success() {
+                    let stderr = String::from_utf8_lossy(&output.stderr);
+                    app.message = Some(stderr.trim().to_string());
+                }
+            }
+            Action::Edit(uuid) => {
+                ratatui::restore();
+                let mut args = rc.to_vec();
+                args.extend([uuid, String::from("edit")]);
+                let res = run_interactive(task, &args);
+                *terminal = ratatui::init();
+                res?;
+            }
+        }
+        app.set_tasks(load(task, rc, filter)?);
+    }
+}
+
+/// Browse the pending tasks matching `filter`, with keys to act on the selected one. `rc`
+/// overrides are passed to every taskwarrior command
+pub fn run(task: &Taskwarrior, rc: &[String], filter: &[String]) -> Result<()> {
+    let mut terminal = ratatui::init();
+    let res = browse(&mut terminal, task, rc, filter);
+    ratatui::restore();
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use ratatui::crossterm::event::KeyModifiers;
+
+    fn key(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    fn app() -> App {
+        let tasks = serde_json::from_str(
+            r#"[
+                {"id": 1, "uuid": "a", "description": "first", "status": "pending"},
+                {"id": 2, "uuid": "b", "description": "second", "status": "pending",
+                 "start": "20240101T120000Z"}
+            ]"#,
+        )
+        .unwrap();
+        let mut app = App::default();
+        app.set_tasks(tasks);
+        app
+    }
+
+    #[test]
+    fn test_keys() {
+        let mut app = app();
+        assert_eq!(
+            Action::Task(vec![String::from("a"), String::from("start")]),
+            app.on_key(key(KeyCode::Char('s')))
+        );
+        app.on_key(key(KeyCode::Down));
+        assert_eq!(
+            Action::Task(vec![String::from("b"), String::from("stop")]),
+            app.on_key(key(KeyCode::Char('s')))
+        );
+        assert_eq!(
+            Action::Edit(String::from("b")),
+            app.on_key(key(KeyCode::Char('e')))
+        );
+        assert_eq!(Action::Quit, app.on_key(key(KeyCode::Char('q'))));
+    }
+
+    #[test]
+    fn test_annotate() {
+        let mut app = app();
+        assert_eq!(Action::None, app.on_key(key(KeyCode::Char('a'))));
+        for c in "hiq".chars() {
+            assert_eq!(Action::None, app.on_key(key(KeyCode::Char(c))));
+        }
+        app.on_key(key(KeyCode::Backspace));
+        assert_eq!(
+            Action::Task(vec![
+                String::from("a"),
+                String::from("annotate"),
+                String::from("hi")
+            ]),
+            app.on_key(key(KeyCode::Enter))
+        );
+        assert_eq!(None, app.annotation);
+    }
+
+    #[test]
+    fn test_selection_kept_in_range() {
+        let mut app = app();
+        app.on_key(key(KeyCode::Down));
+        let first = app.tasks[..1].to_vec();
+        app.set_tasks(first);
+        assert_eq!(Some(0), app.table.selected());
+        app.set_tasks(Vec::new());
+        assert_eq!(None, app.table.selected());
+    }
+}
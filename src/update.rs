@@ -0,0 +1,155 @@
+use crate::download::{download, extract, get, target, verify_signature};
+use crate::{NAME, VERSION};
+use color_eyre::eyre::{bail, eyre};
+use color_eyre::Result;
+use log::info;
+use serde::Deserialize;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const LATEST_RELEASE: &str = "https://api.github.com/repos/adam-gaia/taskhelper/releases/latest";
+/// Releases are zipped for Windows and tarred elsewhere
+#[cfg(windows)]
+const ARCHIVE_EXTENSION: &str = ".zip";
+#[cfg(not(windows))]
+const ARCHIVE_EXTENSION: &str = ".tar.xz";
+const SIGNATURE_EXTENSION: &str = ".minisig";
+
+/// minisign public key release archives are signed with. Set by the release workflow, as a
+/// checksum published next to the archive can be replaced along with it
+const PUBLIC_KEY: Option<&str> = option_env!("TASKHELPER_UPDATE_PUBLIC_KEY");
+
+#[derive(Debug, Deserialize)]
+struct Release {
+    tag_name: String,
+    assets: Vec<Asset>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Asset {
+    name: String,
+    browser_download_url: String,
+}
+
+/// `major.minor.patch` of a version or tag like `v0.2.0`
+fn parse_version(version: &str) -> Option<(u64, u64, u64)> {
+    let version = version.trim_start_matches('v');
+    let version = version.split(['-', '+']).next()?;
+    let mut parts = version.split('.').map(|part| part.parse::<u64>().ok());
+    Some((parts.next()??, parts.next()??, parts.next()??))
+}
+
+fn newer(latest: &str, current: &str) -> bool {
+    match (parse_version(latest), parse_version(current)) {
+        (Some(latest), Some(current)) => latest > current,
+        _ => false,
+    }
+}
+
+impl Release {
+    fn asset(&self, name: &str) -> Result<&Asset> {
+        self.assets
+            .iter()
+            .find(|asset| asset.name == name)
+            .ok_or_else(|| eyre!("Release {} has no asset '{}'", self.tag_name, name))
+    }
+}
+
+/// Pull the taskhelper binary out of a release archive
+fn extract_binary(archive_name: &str, archive: &[u8]) -> Result<Vec<u8>> {
+    let exe = format!("{}.exe", NAME);
+    extract(archive_name, archive, &[NAME, &exe])
+}
+
+/// Atomically replace the binary at `path` with `binary`. `path` is resolved first, so symlinks
+/// pointing at it, such as the multicall `task` link, keep working
+fn replace_binary(path: &Path, binary: &[u8]) -> Result<PathBuf> {
+    let path = fs::canonicalize(path)?;
+    let Some(dir) = path.parent() else {
+        bail!("Unable to find the directory of '{}'", path.display());
+    };
+    let mut tmp = tempfile::NamedTempFile::new_in(dir)?;
+    io::Write::write_all(&mut tmp, binary)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(&path)?.permissions().mode();
+        fs::set_permissions(tmp.path(), fs::Permissions::from_mode(mode))?;
+    }
+    tmp.persist(&path)?;
+    Ok(path)
+}
+
+/// Update taskhelper to the latest release, or with `check` only report whether there is one
+pub fn self_update(check: bool) -> Result<()> {
+    let release: Release = serde_json::from_reader(get(LATEST_RELEASE)?.into_reader())?;
+    if !newer(&release.tag_name, VERSION) {
+        println!("{} {} is up to date", NAME, VERSION);
+        return Ok(());
+    }
+    println!(
+        "{} {} is available (installed: {})",
+        NAME, release.tag_name, VERSION
+    );
+    if check {
+        return Ok(());
+    }
+
+    let Some(public_key) = PUBLIC_KEY.filter(|key| !key.is_empty()) else {
+        bail!(
+            "This build of {} has no key to verify releases with, update it the way it was installed",
+            NAME
+        );
+    };
+    let Some(target) = target() else {
+        bail!("No releases are built for this platform");
+    };
+    let archive_name = format!("{}-{}{}", NAME, target, ARCHIVE_EXTENSION);
+    let archive = download(&release.asset(&archive_name)?.browser_download_url)?;
+    let signature_name = format!("{}{}", archive_name, SIGNATURE_EXTENSION);
+    let signature = download(&release.asset(&signature_name)?.browser_download_url)?;
+    verify_signature(&archive, &String::from_utf8_lossy(&signature), public_key)?;
+
+    let binary = extract_binary(&archive_name, &archive)?;
+    let path = replace_binary(&env::current_exe()?, &binary)?;
+    info!("Replaced '{}'", path.display());
+    println!("Updated {} to {}", NAME, release.tag_name);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(Some((0, 2, 0)), parse_version("v0.2.0"));
+        assert_eq!(Some((1, 0, 3)), parse_version("1.0.3-rc.1"));
+        assert_eq!(None, parse_version("latest"));
+    }
+
+    #[test]
+    fn test_newer() {
+        assert!(newer("v0.2.0", "0.1.9"));
+        assert!(!newer("v0.1.0", "0.1.0"));
+        assert!(!newer("nightly", "0.1.0"));
+    }
+
+    #[test]
+    fn test_release_asset() {
+        let release: Release = serde_json::from_str(
+            r#"{"tag_name": "v0.2.0", "assets": [
+                {"name": "taskhelper-x86_64-unknown-linux-gnu.tar.xz", "browser_download_url": "https://x/a"}
+            ]}"#,
+        )
+        .unwrap();
+        let asset = release
+            .asset("taskhelper-x86_64-unknown-linux-gnu.tar.xz")
+            .unwrap();
+        assert_eq!("https://x/a", asset.browser_download_url);
+        assert!(release.asset("missing").is_err());
+    }
+}
@@ -0,0 +1,105 @@
+use crate::taskwarrior::Taskwarrior;
+use crate::{run_quiet, CommandResult, Exit};
+use color_eyre::eyre::bail;
+use color_eyre::Result;
+use directories::BaseDirs;
+use log::debug;
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use std::io::{self, IsTerminal};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+
+/// How long changes are gathered before rendering again. Taskwarrior writes several files per
+/// command
+const SETTLE: Duration = Duration::from_millis(200);
+
+/// Taskwarrior's data directory with the rc `overrides` applied
+pub fn data_dir(task: &Taskwarrior, overrides: &[String]) -> Result<PathBuf> {
+    let mut args = overrides.to_vec();
+    args.extend([String::from("_get"), String::from("rc.data.location")]);
+    let res = run_quiet(task, &args)?;
+    let location = res.stdout.trim();
+    if !res.exit.success() || location.is_empty() {
+        bail!(
+            "Unable to find taskwarrior's data directory: {}",
+            res.stderr.trim()
+        );
+    }
+    Ok(expand_home(location))
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), BaseDirs::new()) {
+        (Some(rest), Some(dirs)) => dirs.home_dir().join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Whether `event` is a change to the data rather than a read
+fn is_change(event: &notify::Result<Event>) -> bool {
+    match event {
+        Ok(event) => !matches!(event.kind, EventKind::Access(_)),
+        Err(_) => true,
+    }
+}
+
+/// Block until the data changes, then until it settles
+fn wait(events: &Receiver<notify::Result<Event>>) -> Result<()> {
+    loop {
+        let event = events.recv()?;
+        if is_change(&event) {
+            debug!("Task data changed: {:?}", event);
+            break;
+        }
+    }
+    while events.recv_timeout(SETTLE).is_ok() {}
+    Ok(())
+}
+
+/// Call `render` now and every time the data in `dir` changes, clearing the terminal first. Runs
+/// until taskwarrior is interrupted, returning that run's result
+pub fn watch<F>(dir: &Path, mut render: F) -> Result<CommandResult>
+where
+    F: FnMut() -> Result<CommandResult>,
+{
+    let (sender, events) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender)?;
+    watcher.watch(dir, RecursiveMode::NonRecursive)?;
+    let clear = io::stdout().is_terminal();
+    loop {
+        if clear {
+            print!("\x1b[H\x1b[2J");
+        }
+        let res = render()?;
+        if matches!(res.exit, Exit::Signaled(_)) {
+            return Ok(res);
+        }
+        // Rendering may have touched the data itself, as taskwarrior 2 does when it renumbers
+        // tasks
+        while events.try_recv().is_ok() {}
+        wait(&events)?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{AccessKind, ModifyKind};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_is_change() {
+        let access = Event::new(EventKind::Access(AccessKind::Any));
+        let modify = Event::new(EventKind::Modify(ModifyKind::Any));
+        assert!(!is_change(&Ok(access)));
+        assert!(is_change(&Ok(modify)));
+    }
+
+    #[test]
+    fn test_expand_home() {
+        assert_eq!(PathBuf::from("/data/task"), expand_home("/data/task"));
+        let home = BaseDirs::new().unwrap().home_dir().to_path_buf();
+        assert_eq!(home.join(".task"), expand_home("~/.task"));
+    }
+}